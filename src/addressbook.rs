@@ -202,7 +202,7 @@ pub fn open_addressbook_window(app: &Application) {
                     } else {
                         format!("{} <{}>", name, email)
                     };
-                    composer::open_composer_window(&app_click, Some(&to_str), None, None);
+                    composer::open_composer_window(&app_click, composer::MailDraft::to(&to_str));
                 }
             }
         }
@@ -269,7 +269,7 @@ pub fn open_addressbook_window(app: &Application) {
                         } else {
                             format!("{} <{}>", name, email)
                         };
-                        composer::open_composer_window(&app_keys, Some(&to_str), None, None);
+                        composer::open_composer_window(&app_keys, composer::MailDraft::to(&to_str));
                     }
                 }
                 gtk4::glib::Propagation::Stop
@@ -0,0 +1,202 @@
+use gtk4::gio;
+use gtk4::prelude::*;
+use gtk4::{Label, ListBox, Orientation};
+use std::path::{Path, PathBuf};
+
+// Dateiendung <-> MIME-Typ für die gängigen Anhänge
+const MIME_TYPES: [(&str, &str); 24] = [
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("odt", "application/vnd.oasis.opendocument.text"),
+    ("ods", "application/vnd.oasis.opendocument.spreadsheet"),
+    (
+        "docx",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    ),
+    (
+        "xlsx",
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    ),
+    ("doc", "application/msword"),
+    ("xls", "application/vnd.ms-excel"),
+    ("asc", "application/pgp-keys"),
+    ("sig", "application/pgp-signature"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("svg", "image/svg+xml"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+    ("txt", "text/plain"),
+    ("html", "text/html"),
+    ("csv", "text/csv"),
+    ("ics", "text/calendar"),
+    ("eml", "message/rfc822"),
+];
+
+#[derive(Clone)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: String,
+    // Bereits dekodiert (base64 / quoted-printable)
+    pub data: Vec<u8>,
+}
+
+// Alle MIME-Teile ausser dem Mailtext: Dateien, Bilder, weitergeleitete Mails
+pub fn collect_attachments(parsed: &mailparse::ParsedMail) -> Vec<Attachment> {
+    let mut found = Vec::new();
+    collect(parsed, &mut found);
+    found
+}
+
+fn collect(part: &mailparse::ParsedMail, found: &mut Vec<Attachment>) {
+    if !part.subparts.is_empty() {
+        for sub in &part.subparts {
+            collect(sub, found);
+        }
+        return;
+    }
+
+    let disposition = part.get_content_disposition();
+    let filename = disposition
+        .params
+        .get("filename")
+        .or_else(|| part.ctype.params.get("name"))
+        .map(|f| sanitize_filename(f))
+        .filter(|f| !f.is_empty());
+    let mimetype = part.ctype.mimetype.to_lowercase();
+
+    // Text-Teile ohne Dateiname sind der Mailtext (bzw. dessen Alternativen)
+    let is_body = (mimetype == "text/plain" || mimetype == "text/html")
+        && filename.is_none()
+        && disposition.disposition != mailparse::DispositionType::Attachment;
+    if is_body {
+        return;
+    }
+
+    let Ok(data) = part.get_body_raw() else {
+        return;
+    };
+    let filename = filename
+        .unwrap_or_else(|| format!("anhang-{}.{}", found.len() + 1, extension_for(&mimetype)));
+
+    found.push(Attachment {
+        filename,
+        content_type: mimetype,
+        data,
+    });
+}
+
+pub fn mime_for_extension(extension: &str) -> Option<&'static str> {
+    MIME_TYPES
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, mime)| *mime)
+}
+
+pub fn extension_for(mimetype: &str) -> &'static str {
+    MIME_TYPES
+        .iter()
+        .find(|(_, mime)| *mime == mimetype)
+        .map(|(ext, _)| *ext)
+        .unwrap_or("bin")
+}
+
+// Nur der letzte Pfadteil, damit ein Anhang nicht ausserhalb des Zielordners landet
+fn sanitize_filename(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or("");
+    let clean: String = base.chars().filter(|c| !c.is_control()).collect();
+    let clean = clean.trim().to_string();
+    if clean == "." || clean == ".." {
+        String::new()
+    } else {
+        clean
+    }
+}
+
+pub fn format_size(bytes: usize) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0).replace('.', ",")
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0)).replace('.', ",")
+    }
+}
+
+// Freier Dateiname im Zielordner: "bericht.pdf", sonst "bericht (1).pdf" usw.
+fn unique_path(dir: &Path, filename: &str) -> PathBuf {
+    let candidate = dir.join(filename);
+    if !candidate.exists() {
+        return candidate;
+    }
+
+    let (stem, ext) = match filename.rfind('.') {
+        Some(pos) if pos > 0 => (&filename[..pos], &filename[pos..]),
+        _ => (filename, ""),
+    };
+    (1..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, ext)))
+        .find(|p| !p.exists())
+        .unwrap()
+}
+
+pub fn save_attachment(attachment: &Attachment, dir: &Path) -> std::io::Result<PathBuf> {
+    let path = unique_path(dir, &attachment.filename);
+    std::fs::write(&path, &attachment.data)?;
+    Ok(path)
+}
+
+// Über eine temporäre Datei mit dem Standardprogramm öffnen
+pub fn open_attachment(attachment: &Attachment) -> Result<(), String> {
+    let tmp_dir = std::env::temp_dir().join("nox-anhaenge");
+    std::fs::create_dir_all(&tmp_dir).map_err(|e| e.to_string())?;
+    let path = save_attachment(attachment, &tmp_dir).map_err(|e| e.to_string())?;
+
+    let uri = gio::File::for_path(&path).uri();
+    gio::AppInfo::launch_default_for_uri(&uri, None::<&gio::AppLaunchContext>)
+        .map_err(|e| e.to_string())
+}
+
+// Anhänge im Kopfbereich des Viewers auflisten; ohne Anhänge bleibt die Liste unsichtbar
+pub fn fill_attachment_list(list: &ListBox, attachments: &[Attachment]) {
+    while let Some(child) = list.first_child() {
+        list.remove(&child);
+    }
+
+    for attachment in attachments {
+        let hbox = gtk4::Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(8)
+            .margin_start(5)
+            .margin_end(5)
+            .margin_top(2)
+            .margin_bottom(2)
+            .build();
+        let icon = gtk4::Image::from_icon_name("mail-attachment-symbolic");
+        let lbl_name = Label::builder()
+            .label(&attachment.filename)
+            .xalign(0.0)
+            .ellipsize(gtk4::pango::EllipsizeMode::Middle)
+            .build();
+        let lbl_info = Label::builder()
+            .label(format!(
+                "{}, {}",
+                attachment.content_type,
+                format_size(attachment.data.len())
+            ))
+            .xalign(0.0)
+            .css_classes(["dim-label"])
+            .build();
+
+        hbox.append(&icon);
+        hbox.append(&lbl_name);
+        hbox.append(&lbl_info);
+        list.append(&hbox);
+    }
+
+    list.set_visible(!attachments.is_empty());
+}
@@ -0,0 +1,259 @@
+use crate::db;
+use crate::mime;
+use gtk4::prelude::*;
+use gtk4::{Entry, Label, ListBox, Orientation, Popover, gdk};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const MAX_SUGGESTIONS: usize = 8;
+
+// Ein Kontakt aus der DB, ergänzt um die Anzahl an ihn gesendeter Mails
+pub struct Candidate {
+    pub name: String,
+    pub email: String,
+    pub is_verified: bool,
+    pub has_key: bool,
+    pub sent_count: usize,
+}
+
+pub fn load_candidates() -> Vec<Candidate> {
+    let counts = db::get_sent_counts().unwrap_or_default();
+    db::get_all_contacts()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, email, is_verified, has_key)| Candidate {
+            sent_count: counts.get(&email.to_lowercase()).copied().unwrap_or(0),
+            name,
+            email,
+            is_verified,
+            has_key,
+        })
+        .collect()
+}
+
+// Beginn der Adresse, an der gerade getippt wird: nach dem letzten Komma ausserhalb
+// von Anführungszeichen ("Muster, Hans" <hm@example.ch>)
+fn current_token_start(text: &str) -> usize {
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => start = i + 1,
+            _ => {}
+        }
+    }
+    start
+}
+
+// Höher ist besser; None, wenn die Eingabe nicht passt. Wortanfang vor Teilstring vor
+// Buchstabenfolge mit Lücken ("hmu" findet "Hans Muster")
+fn fuzzy_score(query: &str, candidate: &Candidate) -> Option<u32> {
+    let name = candidate.name.to_lowercase();
+    let email = candidate.email.to_lowercase();
+
+    let word_start = email.starts_with(query)
+        || name
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|w| w.starts_with(query));
+    if word_start {
+        return Some(3);
+    }
+    if name.contains(query) || email.contains(query) {
+        return Some(2);
+    }
+
+    let is_subsequence = |haystack: &str| {
+        let mut chars = haystack.chars();
+        query.chars().all(|q| chars.any(|c| c == q))
+    };
+    if is_subsequence(&name) || is_subsequence(&email) {
+        return Some(1);
+    }
+    None
+}
+
+fn suggestions<'a>(query: &str, candidates: &'a [Candidate]) -> Vec<&'a Candidate> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut scored: Vec<(u32, &Candidate)> = candidates
+        .iter()
+        .filter_map(|c| fuzzy_score(&query, c).map(|s| (s, c)))
+        .collect();
+    scored.sort_by(|(sa, a), (sb, b)| {
+        sb.cmp(sa)
+            .then(b.sent_count.cmp(&a.sent_count))
+            .then(b.is_verified.cmp(&a.is_verified))
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, c)| c)
+        .collect()
+}
+
+fn build_row(candidate: &Candidate) -> gtk4::Box {
+    let row_box = gtk4::Box::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(10)
+        .margin_start(5)
+        .margin_end(5)
+        .margin_top(3)
+        .margin_bottom(3)
+        .build();
+
+    let name_label = Label::builder()
+        .label(if candidate.name.is_empty() {
+            "Unbekannt"
+        } else {
+            &candidate.name
+        })
+        .xalign(0.0)
+        .width_request(160)
+        .ellipsize(gtk4::pango::EllipsizeMode::End)
+        .build();
+    let email_label = Label::builder()
+        .label(&candidate.email)
+        .xalign(0.0)
+        .hexpand(true)
+        .css_classes(["dim-label"])
+        .ellipsize(gtk4::pango::EllipsizeMode::End)
+        .build();
+    row_box.append(&name_label);
+    row_box.append(&email_label);
+
+    if candidate.has_key {
+        let key_label = Label::builder()
+            .use_markup(true)
+            .label("<span foreground='#1e90ff' weight='bold'>[K]</span>")
+            .tooltip_text("Public Key vorhanden")
+            .build();
+        row_box.append(&key_label);
+    }
+    if candidate.is_verified {
+        let verified_label = Label::builder()
+            .use_markup(true)
+            .label("<span foreground='green' weight='bold'>✓</span>")
+            .tooltip_text("Verifiziert")
+            .build();
+        row_box.append(&verified_label);
+    }
+    row_box
+}
+
+// Vorschläge unter einem Empfängerfeld; ↑/↓ wählen, Enter/Tab übernimmt, Esc schliesst.
+// Die übernommene Adresse ersetzt nur den Teil nach dem letzten Komma.
+pub fn attach(entry: &Entry, candidates: Rc<Vec<Candidate>>) {
+    let list = ListBox::builder()
+        .selection_mode(gtk4::SelectionMode::Single)
+        .build();
+    let popover = Popover::builder()
+        .child(&list)
+        .position(gtk4::PositionType::Bottom)
+        .has_arrow(false)
+        .autohide(false)
+        .can_focus(false)
+        .halign(gtk4::Align::Start)
+        .build();
+    popover.set_parent(entry);
+
+    let popover_unparent = popover.clone();
+    entry.connect_destroy(move |_| popover_unparent.unparent());
+
+    // (Name, E-Mail) der angezeigten Zeilen, in Reihenfolge
+    let shown: Rc<RefCell<Vec<(String, String)>>> = Rc::default();
+
+    let accept = {
+        let entry = entry.clone();
+        let popover = popover.clone();
+        let shown = shown.clone();
+        Rc::new(move |idx: usize| {
+            let Some((name, email)) = shown.borrow().get(idx).cloned() else {
+                return;
+            };
+            let text = entry.text().to_string();
+            let start = current_token_start(&text);
+            let prefix = text[..start].trim_end();
+            let address = mime::display_address(&name, &email);
+            let new_text = if prefix.is_empty() {
+                format!("{}, ", address)
+            } else {
+                format!("{} {}, ", prefix, address)
+            };
+            popover.popdown();
+            entry.set_text(&new_text);
+            entry.set_position(-1);
+        })
+    };
+
+    let list_changed = list.clone();
+    let popover_changed = popover.clone();
+    let shown_changed = shown.clone();
+    entry.connect_changed(move |e| {
+        let text = e.text().to_string();
+        let token = &text[current_token_start(&text)..];
+        let matches = suggestions(token, &candidates);
+
+        while let Some(child) = list_changed.first_child() {
+            list_changed.remove(&child);
+        }
+        // Bereits vollständig getippte Adresse: nichts mehr vorschlagen
+        let complete = matches.len() == 1 && matches[0].email.eq_ignore_ascii_case(token.trim());
+        if matches.is_empty() || complete {
+            shown_changed.borrow_mut().clear();
+            popover_changed.popdown();
+            return;
+        }
+
+        *shown_changed.borrow_mut() = matches
+            .iter()
+            .map(|c| (c.name.clone(), c.email.clone()))
+            .collect();
+        for candidate in &matches {
+            list_changed.append(&build_row(candidate));
+        }
+        popover_changed.popup();
+    });
+
+    let accept_click = accept.clone();
+    list.connect_row_activated(move |_, row| accept_click(row.index() as usize));
+
+    let key_ctrl = gtk4::EventControllerKey::new();
+    // Vor dem Text-Widget im Entry abfangen, sonst verschiebt ↑/↓ nur den Cursor
+    key_ctrl.set_propagation_phase(gtk4::PropagationPhase::Capture);
+    let list_keys = list.clone();
+    let popover_keys = popover.clone();
+    key_ctrl.connect_key_pressed(move |_, keyval, _, _| {
+        if !popover_keys.is_visible() {
+            return gtk4::glib::Propagation::Proceed;
+        }
+        let current = list_keys.selected_row().map(|r| r.index());
+        match keyval {
+            gdk::Key::Down | gdk::Key::Up => {
+                let next = match (keyval, current) {
+                    (gdk::Key::Down, Some(i)) => i + 1,
+                    (gdk::Key::Up, Some(i)) => i - 1,
+                    _ => 0,
+                };
+                if let Some(row) = list_keys.row_at_index(next) {
+                    list_keys.select_row(Some(&row));
+                }
+                gtk4::glib::Propagation::Stop
+            }
+            gdk::Key::Return | gdk::Key::KP_Enter | gdk::Key::Tab => {
+                accept(current.unwrap_or(0) as usize);
+                gtk4::glib::Propagation::Stop
+            }
+            gdk::Key::Escape => {
+                popover_keys.popdown();
+                gtk4::glib::Propagation::Stop
+            }
+            _ => gtk4::glib::Propagation::Proceed,
+        }
+    });
+    entry.add_controller(key_ctrl);
+}
@@ -1,29 +1,129 @@
+use crate::attachments;
+use crate::completion;
+use crate::drafts;
+use crate::editor;
+use crate::markdown;
+use crate::mime;
+use crate::msmtprc;
+use crate::outbox;
+use crate::send_checks;
+use crate::signature;
 use gtk4::prelude::*;
 use gtk4::{
-    Application, ApplicationWindow, Box, Button, Entry, HeaderBar, Orientation, ScrolledWindow,
-    TextView,
+    Application, ApplicationWindow, Box, Button, DropDown, Entry, HeaderBar, Label, ListBox,
+    Orientation, ScrolledWindow, SelectionMode, TextView, ToggleButton, gdk,
 };
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// Vorbelegung des Composers; leere Felder bleiben leer
+#[derive(Clone, Default, PartialEq)]
+pub struct MailDraft {
+    pub to: String,
+    pub cc: String,
+    pub bcc: String,
+    pub subject: String,
+    pub body: String,
+    // Threading-Header einer Antwort, Message-IDs mit spitzen Klammern
+    pub in_reply_to: String,
+    pub references: String,
+    // z.B. die Anhänge einer weitergeleiteten Mail
+    pub attachments: Vec<mime::OutgoingAttachment>,
+    // msmtp-Konto für From und Versand; leer = Standardkonto
+    pub account: String,
+    // Text ist Markdown und geht zusätzlich als HTML hinaus
+    pub markdown: bool,
+}
+
+impl MailDraft {
+    pub fn to(to: &str) -> Self {
+        MailDraft {
+            to: to.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+// Abstand der automatischen Entwurfsspeicherung
+const AUTOSAVE_SECONDS: u32 = 30;
+
+pub fn open_composer_window(app: &Application, draft: MailDraft) {
+    build_composer(app, draft, None);
+}
+
+// Entwurf aus ~/.Mail/Drafts weiterbearbeiten; gespeichert wird wieder in dieselbe Datei
+pub fn open_draft_window(app: &Application, path: &Path) -> Result<(), String> {
+    let draft = drafts::load_draft(path)?;
+    build_composer(app, draft, Some(path.to_path_buf()));
+    Ok(())
+}
+
+fn build_composer(app: &Application, mut draft: MailDraft, draft_path: Option<PathBuf>) {
+    // Absender: ein Eintrag pro msmtp-Konto, vorgewählt das Konto der Vorlage
+    let identities = Rc::new(msmtprc::identities());
+    let identity_labels: Vec<String> = identities
+        .iter()
+        .map(|a| format!("{} ({})", a.from, a.name))
+        .collect();
+    let label_refs: Vec<&str> = identity_labels.iter().map(String::as_str).collect();
+    let from_dropdown = DropDown::from_strings(&label_refs);
+    from_dropdown.set_tooltip_text(Some("Absender (msmtp-Konto)"));
+    from_dropdown.set_visible(!identities.is_empty());
+    from_dropdown.set_sensitive(identities.len() > 1);
+    let initial = identities
+        .iter()
+        .position(|a| a.name == draft.account)
+        .unwrap_or(0);
+    from_dropdown.set_selected(initial as u32);
+    draft.account = identities
+        .get(initial)
+        .map(|a| a.name.clone())
+        .unwrap_or_default();
+
+    // Signatur nur in neue Mails; Entwürfe und zurückgeholte Mails enthalten sie schon
+    let is_reply = !draft.in_reply_to.is_empty();
+    let mut initial_signature = String::new();
+    if draft_path.is_none()
+        && let Some(sig) = signature::for_account(&draft.account)
+        && !draft.body.contains(&signature::block(&sig))
+    {
+        draft.body = signature::insert(&draft.body, &sig, is_reply);
+        initial_signature = signature::block(&sig);
+    }
+
+    let selected_account = {
+        let from_dropdown = from_dropdown.clone();
+        let identities = identities.clone();
+        Rc::new(move || {
+            identities
+                .get(from_dropdown.selected() as usize)
+                .map(|a| a.name.clone())
+                .unwrap_or_default()
+        })
+    };
 
-pub fn open_composer_window(
-    app: &Application,
-    to: Option<&str>,
-    subject: Option<&str>,
-    body: Option<&str>,
-) {
     let to_entry = Entry::builder().placeholder_text("Empfänger").build();
-    if let Some(t) = to {
-        to_entry.set_text(t);
+    to_entry.set_text(&draft.to);
+
+    // Mehrere Adressen jeweils durch Komma getrennt
+    let cc_entry = Entry::builder().placeholder_text("Cc").build();
+    cc_entry.set_text(&draft.cc);
+    let bcc_entry = Entry::builder().placeholder_text("Bcc").build();
+    bcc_entry.set_text(&draft.bcc);
+
+    let candidates = Rc::new(completion::load_candidates());
+    for entry in [&to_entry, &cc_entry, &bcc_entry] {
+        completion::attach(entry, candidates.clone());
+        // Markierung aus der Prüfung vor dem Senden verschwindet beim Bearbeiten
+        entry.connect_changed(|e| e.remove_css_class("warning"));
     }
 
     let subject_entry = Entry::builder().placeholder_text("Betreff").build();
-    if let Some(s) = subject {
-        subject_entry.set_text(s);
-    }
+    subject_entry.set_text(&draft.subject);
 
     let text_buffer = gtk4::TextBuffer::new(None);
-    if let Some(b) = body {
-        text_buffer.set_text(b);
-    }
+    text_buffer.set_text(&draft.body);
 
     let text_view = TextView::builder()
         .buffer(&text_buffer)
@@ -39,6 +139,23 @@ pub fn open_composer_window(
         .vexpand(true)
         .build();
 
+    // Markdown-Vorschau anstelle des Textfelds, so wie der HTML-Teil aussehen wird
+    let preview_label = Label::builder()
+        .xalign(0.0)
+        .yalign(0.0)
+        .wrap(true)
+        .selectable(true)
+        .margin_start(10)
+        .margin_end(10)
+        .margin_top(10)
+        .margin_bottom(10)
+        .build();
+    let preview_scroll = ScrolledWindow::builder()
+        .child(&preview_label)
+        .vexpand(true)
+        .visible(false)
+        .build();
+
     let vbox = Box::builder()
         .orientation(Orientation::Vertical)
         .spacing(6)
@@ -48,15 +165,109 @@ pub fn open_composer_window(
         .margin_bottom(10)
         .build();
 
+    // Anhänge: Dateidialog, Drag-and-Drop aufs Fenster, Entf entfernt den gewählten
+    // Dateien werden beim Hinzufügen gelesen, damit auch Anhänge ohne Datei (Weiterleiten)
+    // in derselben Liste stehen
+    let attachments = Rc::new(RefCell::new(draft.attachments.clone()));
+    let attachment_list = ListBox::builder()
+        .selection_mode(SelectionMode::Single)
+        .visible(false)
+        .build();
+    refresh_attachment_list(&attachment_list, &attachments);
+
+    let info_label = Label::builder()
+        .halign(gtk4::Align::Start)
+        .css_classes(["error"])
+        .visible(false)
+        .build();
+
+    vbox.append(&from_dropdown);
+
+    // Beim Wechsel des Absenders die eingefügte Signatur austauschen, solange sie
+    // unverändert im Text steht
+    let current_signature = Rc::new(RefCell::new(initial_signature));
+    let buffer_for_sig = text_buffer.clone();
+    let account_for_sig = selected_account.clone();
+    from_dropdown.connect_selected_notify(move |_| {
+        let new_sig = signature::for_account(&account_for_sig());
+        let new_block = new_sig.as_ref().map(signature::block).unwrap_or_default();
+        let (start, end) = buffer_for_sig.bounds();
+        let body = buffer_for_sig.text(&start, &end, false).to_string();
+        let old_block = current_signature.borrow().clone();
+
+        let new_body = if !old_block.is_empty() && body.contains(&old_block) {
+            body.replacen(&old_block, &new_block, 1)
+        } else if old_block.is_empty()
+            && let Some(sig) = &new_sig
+        {
+            signature::insert(&body, sig, is_reply)
+        } else {
+            return;
+        };
+        buffer_for_sig.set_text(&new_body);
+        *current_signature.borrow_mut() = new_block;
+    });
     vbox.append(&to_entry);
+    vbox.append(&cc_entry);
+    vbox.append(&bcc_entry);
     vbox.append(&subject_entry);
+    vbox.append(&attachment_list);
     vbox.append(&text_scroll);
+    vbox.append(&preview_scroll);
+    vbox.append(&info_label);
 
     let composer_header = HeaderBar::new();
     let send_btn = Button::with_label("Senden");
     send_btn.add_css_class("suggested-action");
     composer_header.pack_end(&send_btn);
 
+    let later_btn = Button::from_icon_name("alarm-symbolic");
+    later_btn.set_tooltip_text(Some("Später senden"));
+    composer_header.pack_end(&later_btn);
+
+    let attach_btn = Button::from_icon_name("mail-attachment-symbolic");
+    attach_btn.set_tooltip_text(Some("Anhang hinzufügen"));
+    composer_header.pack_start(&attach_btn);
+
+    let editor_btn = Button::from_icon_name("document-edit-symbolic");
+    editor_btn.set_tooltip_text(Some("Im externen Editor bearbeiten (Strg+E)"));
+    composer_header.pack_start(&editor_btn);
+
+    let markdown_btn = ToggleButton::builder()
+        .icon_name("format-text-bold-symbolic")
+        .tooltip_text("Markdown: zusätzlich als formatierte HTML-Mail senden")
+        .active(draft.markdown)
+        .build();
+    composer_header.pack_start(&markdown_btn);
+
+    let preview_btn = ToggleButton::builder()
+        .icon_name("view-reveal-symbolic")
+        .tooltip_text("Vorschau (Strg+P)")
+        .sensitive(draft.markdown)
+        .build();
+    composer_header.pack_start(&preview_btn);
+
+    let buffer_for_preview = text_buffer.clone();
+    let text_scroll_preview = text_scroll.clone();
+    let preview_scroll_c = preview_scroll.clone();
+    preview_btn.connect_toggled(move |btn| {
+        if btn.is_active() {
+            let (start, end) = buffer_for_preview.bounds();
+            let body = buffer_for_preview.text(&start, &end, false);
+            preview_label.set_markup(&markdown::to_pango(&body));
+        }
+        text_scroll_preview.set_visible(!btn.is_active());
+        preview_scroll_c.set_visible(btn.is_active());
+    });
+
+    let preview_for_markdown = preview_btn.clone();
+    markdown_btn.connect_toggled(move |btn| {
+        if !btn.is_active() {
+            preview_for_markdown.set_active(false);
+        }
+        preview_for_markdown.set_sensitive(btn.is_active());
+    });
+
     let composer_window = ApplicationWindow::builder()
         .application(app)
         .title("Neue Mail")
@@ -65,49 +276,763 @@ pub fn open_composer_window(
         .child(&vbox)
         .build();
 
-    let win_clone = composer_window.clone();
+    let atts_for_dialog = attachments.clone();
+    let list_for_dialog = attachment_list.clone();
+    let info_for_dialog = info_label.clone();
+    let win_for_dialog = composer_window.clone();
+    attach_btn.connect_clicked(move |_| {
+        let dialog = gtk4::FileChooserDialog::new(
+            Some("Anhang hinzufügen"),
+            Some(&win_for_dialog),
+            gtk4::FileChooserAction::Open,
+            &[
+                ("Abbrechen", gtk4::ResponseType::Cancel),
+                ("Anhängen", gtk4::ResponseType::Accept),
+            ],
+        );
+        dialog.set_modal(true);
+        dialog.set_select_multiple(true);
+
+        let atts_c = atts_for_dialog.clone();
+        let list_c = list_for_dialog.clone();
+        let info_c = info_for_dialog.clone();
+        dialog.connect_response(move |d, response| {
+            if response == gtk4::ResponseType::Accept {
+                let files = d.files();
+                let new_paths: Vec<PathBuf> = (0..files.n_items())
+                    .filter_map(|i| files.item(i).and_downcast::<gtk4::gio::File>())
+                    .filter_map(|f| f.path())
+                    .collect();
+                add_attachments(&list_c, &atts_c, &info_c, new_paths);
+            }
+            d.destroy();
+        });
+        dialog.present();
+    });
+
+    let drop_target = gtk4::DropTarget::new(gdk::FileList::static_type(), gdk::DragAction::COPY);
+    // Vor dem TextView abfangen, sonst landet der Dateipfad als Text in der Mail
+    drop_target.set_propagation_phase(gtk4::PropagationPhase::Capture);
+    let atts_for_drop = attachments.clone();
+    let list_for_drop = attachment_list.clone();
+    let info_for_drop = info_label.clone();
+    drop_target.connect_drop(move |_, value, _, _| {
+        let Ok(file_list) = value.get::<gdk::FileList>() else {
+            return false;
+        };
+        let new_paths = file_list.files().iter().filter_map(|f| f.path()).collect();
+        add_attachments(&list_for_drop, &atts_for_drop, &info_for_drop, new_paths);
+        true
+    });
+    vbox.add_controller(drop_target);
+
+    let key_ctrl = gtk4::EventControllerKey::new();
+    let atts_for_key = attachments.clone();
+    let list_for_key = attachment_list.clone();
+    key_ctrl.connect_key_pressed(move |_, keyval, _, _| {
+        if keyval != gdk::Key::Delete {
+            return gtk4::glib::Propagation::Proceed;
+        }
+        if let Some(row) = list_for_key.selected_row() {
+            let idx = row.index() as usize;
+            if idx < atts_for_key.borrow().len() {
+                atts_for_key.borrow_mut().remove(idx);
+            }
+            refresh_attachment_list(&list_for_key, &atts_for_key);
+        }
+        gtk4::glib::Propagation::Stop
+    });
+    attachment_list.add_controller(key_ctrl);
+
+    // Aktueller Inhalt des Fensters als MailDraft (für Entwurf und Vergleich)
+    let read_draft = {
+        let to_entry = to_entry.clone();
+        let cc_entry = cc_entry.clone();
+        let bcc_entry = bcc_entry.clone();
+        let subject_entry = subject_entry.clone();
+        let text_buffer = text_buffer.clone();
+        let attachments = attachments.clone();
+        let in_reply_to = draft.in_reply_to.clone();
+        let references = draft.references.clone();
+        let selected_account = selected_account.clone();
+        let markdown_btn = markdown_btn.clone();
+        Rc::new(move || {
+            let (start, end) = text_buffer.bounds();
+            MailDraft {
+                to: to_entry.text().to_string(),
+                cc: cc_entry.text().to_string(),
+                bcc: bcc_entry.text().to_string(),
+                subject: subject_entry.text().to_string(),
+                body: text_buffer.text(&start, &end, false).to_string(),
+                in_reply_to: in_reply_to.clone(),
+                references: references.clone(),
+                attachments: attachments.borrow().clone(),
+                account: selected_account(),
+                markdown: markdown_btn.is_active(),
+            }
+        })
+    };
+
+    // Externer Editor: solange er läuft, ist der Composer gesperrt
+    let do_edit_external = {
+        let read_draft = read_draft.clone();
+        let vbox = vbox.clone();
+        let send_btn = send_btn.clone();
+        let editor_btn = editor_btn.clone();
+        let info_label = info_label.clone();
+        let to_entry = to_entry.clone();
+        let cc_entry = cc_entry.clone();
+        let bcc_entry = bcc_entry.clone();
+        let subject_entry = subject_entry.clone();
+        let text_buffer = text_buffer.clone();
+        let preview_btn = preview_btn.clone();
+        Rc::new(move || {
+            if !editor_btn.is_sensitive() {
+                return;
+            }
+            // Die Vorschau würde sonst den alten Text zeigen
+            preview_btn.set_active(false);
+            let vbox_c = vbox.clone();
+            let send_c = send_btn.clone();
+            let editor_c = editor_btn.clone();
+            let info_c = info_label.clone();
+            let to_c = to_entry.clone();
+            let cc_c = cc_entry.clone();
+            let bcc_c = bcc_entry.clone();
+            let subject_c = subject_entry.clone();
+            let buffer_c = text_buffer.clone();
+            let started = editor::edit_in_terminal(&read_draft(), move |result| {
+                vbox_c.set_sensitive(true);
+                send_c.set_sensitive(true);
+                editor_c.set_sensitive(true);
+                match result {
+                    Ok(edited) => {
+                        to_c.set_text(&edited.to);
+                        cc_c.set_text(&edited.cc);
+                        bcc_c.set_text(&edited.bcc);
+                        subject_c.set_text(&edited.subject);
+                        buffer_c.set_text(&edited.body);
+                        info_c.set_visible(false);
+                    }
+                    Err(e) => {
+                        info_c.set_label(&e);
+                        info_c.set_visible(true);
+                    }
+                }
+            });
+            match started {
+                Ok(()) => {
+                    vbox.set_sensitive(false);
+                    send_btn.set_sensitive(false);
+                    editor_btn.set_sensitive(false);
+                    info_label.set_label("Externer Editor läuft …");
+                    info_label.set_visible(true);
+                }
+                Err(e) => {
+                    info_label.set_label(&e);
+                    info_label.set_visible(true);
+                }
+            }
+        })
+    };
+
+    let edit_click = do_edit_external.clone();
+    editor_btn.connect_clicked(move |_| edit_click());
+
+    let window_key_ctrl = gtk4::EventControllerKey::new();
+    let preview_key = preview_btn.clone();
+    window_key_ctrl.connect_key_pressed(move |_, keyval, _, state| {
+        if state.contains(gdk::ModifierType::CONTROL_MASK) && keyval == gdk::Key::e {
+            do_edit_external();
+            return gtk4::glib::Propagation::Stop;
+        }
+        if state.contains(gdk::ModifierType::CONTROL_MASK)
+            && keyval == gdk::Key::p
+            && preview_key.is_sensitive()
+        {
+            preview_key.set_active(!preview_key.is_active());
+            return gtk4::glib::Propagation::Stop;
+        }
+        gtk4::glib::Propagation::Proceed
+    });
+    composer_window.add_controller(window_key_ctrl);
+
+    // Stand der letzten Speicherung; ein unveränderter Composer erzeugt keinen Entwurf
+    let saved_draft = Rc::new(RefCell::new(draft.clone()));
+    let draft_path = Rc::new(RefCell::new(draft_path));
+    // Nur Entwürfe aus dieser Sitzung werden beim Verwerfen gelöscht
+    let reopened = draft_path.borrow().is_some();
+    let allow_close = Rc::new(Cell::new(false));
+
+    let save_draft = {
+        let read_draft = read_draft.clone();
+        let saved_draft = saved_draft.clone();
+        let draft_path = draft_path.clone();
+        let info_label = info_label.clone();
+        // Nur schwach: der Autosave-Timer hält diese Closure
+        let window = composer_window.downgrade();
+        Rc::new(move || -> bool {
+            let current = read_draft();
+            if current == *saved_draft.borrow() {
+                return true;
+            }
+            let previous = draft_path.borrow().clone();
+            match drafts::save_draft(
+                &current,
+                &sender_address(&current.account),
+                previous.as_deref(),
+            ) {
+                Ok(path) => {
+                    *draft_path.borrow_mut() = Some(path);
+                    *saved_draft.borrow_mut() = current;
+                    let time = gtk4::glib::DateTime::now_local()
+                        .and_then(|dt| dt.format("%H:%M"))
+                        .map(|s| s.to_string())
+                        .unwrap_or_default();
+                    if let Some(window) = window.upgrade() {
+                        window
+                            .set_title(Some(&format!("Neue Mail – Entwurf gespeichert {}", time)));
+                    }
+                    true
+                }
+                Err(e) => {
+                    info_label
+                        .set_label(&format!("Entwurf konnte nicht gespeichert werden: {}", e));
+                    info_label.set_visible(true);
+                    false
+                }
+            }
+        })
+    };
+
+    // Endet mit dem Fenster; nach Senden oder Verwerfen darf nichts mehr gespeichert werden
+    let window_weak = composer_window.downgrade();
+    let save_timer = save_draft.clone();
+    let allow_close_timer = allow_close.clone();
+    gtk4::glib::timeout_add_seconds_local(AUTOSAVE_SECONDS, move || {
+        if window_weak.upgrade().is_none() || allow_close_timer.get() {
+            return gtk4::glib::ControlFlow::Break;
+        }
+        save_timer();
+        gtk4::glib::ControlFlow::Continue
+    });
+
+    // Schliessen mit ungespeicherten Änderungen nachfragen
+    let read_close = read_draft.clone();
+    let saved_close = saved_draft.clone();
+    let path_close = draft_path.clone();
+    let allow_close_req = allow_close.clone();
+    let save_close = save_draft.clone();
+    composer_window.connect_close_request(move |win| {
+        if allow_close_req.get() || read_close() == *saved_close.borrow() {
+            return gtk4::glib::Propagation::Proceed;
+        }
+
+        let dialog = gtk4::MessageDialog::builder()
+            .transient_for(win)
+            .modal(true)
+            .message_type(gtk4::MessageType::Question)
+            .text("Ungespeicherte Änderungen")
+            .secondary_text("Soll die Mail als Entwurf in ~/.Mail/Drafts gespeichert werden?")
+            .build();
+        dialog.add_buttons(&[
+            ("Verwerfen", gtk4::ResponseType::Reject),
+            ("Abbrechen", gtk4::ResponseType::Cancel),
+            ("Speichern", gtk4::ResponseType::Accept),
+        ]);
+        dialog.set_default_response(gtk4::ResponseType::Accept);
+
+        let win_c = win.clone();
+        let read_c = read_close.clone();
+        let saved_c = saved_close.clone();
+        let path_c = path_close.clone();
+        let allow_c = allow_close_req.clone();
+        let save_c = save_close.clone();
+        dialog.connect_response(move |d, response| {
+            d.destroy();
+            let close = match response {
+                gtk4::ResponseType::Accept => save_c(),
+                gtk4::ResponseType::Reject => {
+                    if !reopened && let Some(path) = path_c.borrow_mut().take() {
+                        drafts::delete_draft(&path);
+                    }
+                    // Verworfen gilt als gespeichert, sonst schreibt ein Autosave den Stand zurück
+                    *saved_c.borrow_mut() = read_c();
+                    true
+                }
+                _ => false,
+            };
+            if close {
+                allow_c.set(true);
+                win_c.close();
+            }
+        });
+        dialog.present();
+        gtk4::glib::Propagation::Stop
+    });
+
+    let win_weak = composer_window.downgrade();
     let to_entry_clone = to_entry.clone();
+    let cc_entry_clone = cc_entry.clone();
+    let bcc_entry_clone = bcc_entry.clone();
+    let info_for_send = info_label.clone();
     let subject_entry_clone = subject_entry.clone();
     let text_buffer_clone = text_buffer.clone();
+    let atts_for_send = attachments.clone();
+    let in_reply_to = draft.in_reply_to.clone();
+    let references = draft.references.clone();
+    let candidates_for_send = candidates.clone();
+    let path_for_send = draft_path.clone();
+    let allow_close_send = allow_close.clone();
+    let read_for_send = read_draft.clone();
+    let saved_for_send = saved_draft.clone();
+    let account_for_send = selected_account.clone();
+    let app_for_send = app.clone();
+    let send_btn_label = send_btn.clone();
+    let markdown_for_send = markdown_btn.clone();
+    // Bereits angezeigte Warnungen: derselbe Stand wird beim zweiten Klick gesendet
+    let confirmed_warnings = Rc::new(RefCell::new(Vec::<String>::new()));
+    // Nach jeder Änderung wird wieder normal geprüft und gesendet
+    {
+        let send_btn = send_btn.clone();
+        let confirmed = confirmed_warnings.clone();
+        let reset = Rc::new(move || {
+            if !confirmed.borrow().is_empty() {
+                confirmed.borrow_mut().clear();
+                send_btn.set_label("Senden");
+            }
+        });
+        for entry in [&to_entry, &cc_entry, &bcc_entry, &subject_entry] {
+            let reset = reset.clone();
+            entry.connect_changed(move |_| reset());
+        }
+        text_buffer.connect_changed(move |_| reset());
+    }
 
-    send_btn.connect_clicked(move |_| {
+    // Versand sofort (mit Rückgängig-Frist) oder zu einem geplanten Zeitpunkt (Unix-Zeit)
+    let do_send = Rc::new(move |scheduled: Option<i64>| {
         let to = to_entry_clone.text().to_string();
+        let cc = cc_entry_clone.text().to_string();
+        let bcc = bcc_entry_clone.text().to_string();
+
+        // Umschlag aus allen drei Feldern; ungültige Adressen verhindern den Versand
+        let mut envelope: Vec<String> = Vec::new();
+        let mut per_field = Vec::new();
+        for (field, value) in [("An", &to), ("Cc", &cc), ("Bcc", &bcc)] {
+            match mime::envelope_addresses(value) {
+                Ok(addresses) => {
+                    for addr in &addresses {
+                        if !envelope.iter().any(|a| a.eq_ignore_ascii_case(addr)) {
+                            envelope.push(addr.clone());
+                        }
+                    }
+                    per_field.push((field, addresses));
+                }
+                Err(bad) => {
+                    info_for_send.set_label(&format!("Ungültige Adresse in {}: {}", field, bad));
+                    info_for_send.set_visible(true);
+                    return;
+                }
+            }
+        }
+        if envelope.is_empty() {
+            info_for_send.set_label("Keine Empfänger angegeben");
+            info_for_send.set_visible(true);
+            return;
+        }
+
         let subj = subject_entry_clone.text().to_string();
 
         let (start, end) = text_buffer_clone.bounds();
         let body = text_buffer_clone.text(&start, &end, false).to_string();
 
-        // Minimalistischer RFC 2822 Header (MTA wie msmtp ergänzt Date und Message-ID)
-        let raw_mail = format!(
-            "To: {}\r\nSubject: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}",
-            to, subj, body
-        );
+        let outgoing = atts_for_send.borrow().clone();
 
-        let outbox_dir = dirs::home_dir()
-            .unwrap()
-            .join(".Mail")
-            .join("Outbox")
-            .join("new");
-        if let Err(e) = std::fs::create_dir_all(&outbox_dir) {
-            eprintln!("Fehler beim Erstellen des Outbox-Ordners: {}", e);
-        } else {
-            let timestamp = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_millis();
+        let warnings = send_checks::check(
+            &per_field,
+            &body,
+            !outgoing.is_empty(),
+            &candidates_for_send,
+        );
+        for (field, entry) in [
+            ("An", &to_entry_clone),
+            ("Cc", &cc_entry_clone),
+            ("Bcc", &bcc_entry_clone),
+        ] {
+            if warnings.iter().any(|w| w.field == Some(field)) {
+                entry.add_css_class("warning");
+            }
+        }
+        let messages: Vec<String> = warnings.into_iter().map(|w| w.message).collect();
+        if messages.is_empty() {
+            confirmed_warnings.borrow_mut().clear();
+            send_btn_label.set_label("Senden");
+        } else if *confirmed_warnings.borrow() != messages {
+            info_for_send.set_label(&format!(
+                "{}\nNochmals klicken, um trotzdem zu senden.",
+                messages.join("\n")
+            ));
+            info_for_send.set_visible(true);
+            send_btn_label.set_label("Trotzdem senden");
+            *confirmed_warnings.borrow_mut() = messages;
+            return;
+        }
 
-            let file_path = outbox_dir.join(format!("{}.nox", timestamp));
+        // Vollständige RFC 5322 Header, damit die Mail nicht vom MTA ergänzt werden muss
+        // (sonst bricht z.B. eine DKIM-Signatur über Date/Message-ID)
+        let account = account_for_send();
+        let from = sender_address(&account);
+        let mut headers = String::new();
+        headers.push_str(&mime::header_line("From", &from));
+        if !to.trim().is_empty() {
+            headers.push_str(&mime::header_line("To", &to));
+        }
+        if !cc.trim().is_empty() {
+            headers.push_str(&mime::header_line("Cc", &cc));
+        }
+        // Kein Bcc-Header: die Bcc-Empfänger stehen nur im Umschlag
+        headers.push_str(&mime::header_line("Subject", &subj));
+        // Geplante Mails tragen den Versandzeitpunkt als Datum
+        let date = match scheduled.and_then(|t| gtk4::glib::DateTime::from_unix_local(t).ok()) {
+            Some(dt) => mime::rfc5322_date(dt.to_unix(), dt.utc_offset().as_seconds()),
+            None => mime::rfc5322_now(),
+        };
+        headers.push_str(&mime::header_line("Date", &date));
+        headers.push_str(&mime::header_line(
+            "Message-ID",
+            &mime::new_message_id(&from),
+        ));
+        if !in_reply_to.is_empty() {
+            headers.push_str(&mime::header_line("In-Reply-To", &in_reply_to));
+        }
+        if !references.is_empty() {
+            headers.push_str(&mime::header_line("References", &references));
+        }
+        let html = markdown_for_send
+            .is_active()
+            .then(|| markdown::to_html(&body));
+        let raw_mail = mime::build_message(&headers, &body, html.as_deref(), &outgoing);
 
-            if let Err(e) = std::fs::write(&file_path, raw_mail) {
-                eprintln!("Fehler beim Speichern der Mail: {}", e);
-            } else {
+        let send_at = scheduled.unwrap_or_else(|| outbox::now_secs() + outbox::undo_delay());
+        match outbox::enqueue(&raw_mail, &envelope, &account, send_at) {
+            Ok(file_path) => {
                 println!("Mail für Versand gepuffert: {:?}", file_path);
+                // Hauptfenster: Rückgängig bzw. Hinweis auf den geplanten Versand
+                let name = file_path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                app_for_send.activate_action("mail-queued", Some(&(name, send_at).to_variant()));
+                // Verschickt: der Entwurf wird nicht mehr gebraucht
+                if let Some(path) = path_for_send.borrow_mut().take() {
+                    drafts::delete_draft(&path);
+                }
+                *saved_for_send.borrow_mut() = read_for_send();
+                allow_close_send.set(true);
+                if let Some(window) = win_weak.upgrade() {
+                    window.close();
+                }
+            }
+            Err(e) => {
+                info_for_send.set_label(&format!("Fehler beim Speichern der Mail: {}", e));
+                info_for_send.set_visible(true);
             }
         }
+    });
+
+    let send_now = do_send.clone();
+    send_btn.connect_clicked(move |_| send_now(None));
+
+    // Später senden: Zeitpunkt eingeben, der Worker gibt die Mail erst dann frei
+    let later_entry = Entry::builder()
+        .placeholder_text("18:30, morgen 8:00, +2h, 24.12.2025 9:00")
+        .width_chars(32)
+        .build();
+    let later_confirm = Button::with_label("Planen");
+    later_confirm.add_css_class("suggested-action");
+    let later_error = Label::builder()
+        .halign(gtk4::Align::Start)
+        .css_classes(["error"])
+        .visible(false)
+        .build();
+    let later_box = Box::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(6)
+        .build();
+    let later_row = Box::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(6)
+        .build();
+    later_row.append(&later_entry);
+    later_row.append(&later_confirm);
+    later_box.append(&later_row);
+    later_box.append(&later_error);
 
-        win_clone.close();
+    let later_popover = gtk4::Popover::builder().child(&later_box).build();
+    later_popover.set_parent(&later_btn);
+    let popover_unparent = later_popover.clone();
+    later_btn.connect_destroy(move |_| popover_unparent.unparent());
+
+    let popover_show = later_popover.clone();
+    let entry_focus = later_entry.clone();
+    later_btn.connect_clicked(move |_| {
+        popover_show.popup();
+        entry_focus.grab_focus();
     });
 
+    let schedule = {
+        let entry = later_entry.clone();
+        let error = later_error.clone();
+        let popover = later_popover.clone();
+        Rc::new(move || {
+            let Some(send_at) = parse_send_time(&entry.text()) else {
+                error.set_label("Unbekanntes Format oder Zeitpunkt in der Vergangenheit");
+                error.set_visible(true);
+                return;
+            };
+            error.set_visible(false);
+            popover.popdown();
+            do_send(Some(send_at));
+        })
+    };
+    let schedule_click = schedule.clone();
+    later_confirm.connect_clicked(move |_| schedule_click());
+    later_entry.connect_activate(move |_| schedule());
+
     composer_window.set_titlebar(Some(&composer_header));
     composer_window.present();
 }
+
+// Zeitpunkt für "Später senden" als Unix-Zeit, nur in der Zukunft:
+// "18:30" (heute, sonst morgen), "morgen 8:00", "+30m" / "+2h" / "+1d",
+// "24.12.2025 9:00" oder "2025-12-24 09:00"
+fn parse_send_time(text: &str) -> Option<i64> {
+    send_time_from(&gtk4::glib::DateTime::now_local().ok()?, text)
+}
+
+fn send_time_from(now: &gtk4::glib::DateTime, text: &str) -> Option<i64> {
+    let text = text.trim().to_lowercase();
+
+    if let Some(rel) = text.strip_prefix('+') {
+        let (num, unit) = rel.split_at(rel.find(|c: char| !c.is_ascii_digit())?);
+        let n: i64 = num.parse().ok()?;
+        let secs = match unit.trim() {
+            "m" | "min" => n.checked_mul(60),
+            "h" => n.checked_mul(3600),
+            "d" => n.checked_mul(86400),
+            _ => return None,
+        }?;
+        // Auch als Datum darstellbar (glib kennt nur die Jahre 1 bis 9999)
+        let send_at = now.to_unix().checked_add(secs)?;
+        gtk4::glib::DateTime::from_unix_local(send_at).ok()?;
+        return (send_at > now.to_unix()).then_some(send_at);
+    }
+
+    let parse_time = |s: &str| -> Option<(i32, i32)> {
+        let (h, m) = s.split_once(':')?;
+        let (h, m) = (h.parse().ok()?, m.parse().ok()?);
+        (h < 24 && m < 60).then_some((h, m))
+    };
+    let at = |date: &gtk4::glib::DateTime, (h, m): (i32, i32)| {
+        gtk4::glib::DateTime::from_local(date.year(), date.month(), date.day_of_month(), h, m, 0.0)
+            .ok()
+            .map(|dt| dt.to_unix())
+    };
+
+    let parts: Vec<&str> = text.split_whitespace().collect();
+    let send_at = match parts.as_slice() {
+        [time] => {
+            let today = at(now, parse_time(time)?)?;
+            // Bereits vorbei: morgen zur selben Zeit
+            if today > now.to_unix() {
+                today
+            } else {
+                at(&now.add_days(1).ok()?, parse_time(time)?)?
+            }
+        }
+        ["morgen", time] => at(&now.add_days(1).ok()?, parse_time(time)?)?,
+        [date, time] => {
+            let (y, mo, d) = if let Some((d, rest)) = date.split_once('.') {
+                let (mo, y) = rest.split_once('.')?;
+                (y.parse().ok()?, mo.parse().ok()?, d.parse().ok()?)
+            } else {
+                let mut it = date.splitn(3, '-');
+                let y = it.next()?.parse().ok()?;
+                let mo = it.next()?.parse().ok()?;
+                (y, mo, it.next()?.parse().ok()?)
+            };
+            let (h, m) = parse_time(time)?;
+            gtk4::glib::DateTime::from_local(y, mo, d, h, m, 0.0)
+                .ok()?
+                .to_unix()
+        }
+        _ => return None,
+    };
+    (send_at > now.to_unix()).then_some(send_at)
+}
+
+// Absender des gewählten msmtp-Kontos (leer: Standardkonto), ohne Konten benutzer@host
+fn sender_address(account: &str) -> String {
+    let identities = msmtprc::identities();
+    let identity = identities
+        .iter()
+        .find(|a| a.name == account)
+        .or(identities.first());
+    identity.map(|a| a.from.clone()).unwrap_or_else(|| {
+        format!(
+            "{}@{}",
+            gtk4::glib::user_name().to_string_lossy(),
+            gtk4::glib::host_name()
+        )
+    })
+}
+
+fn add_attachments(
+    list: &ListBox,
+    attachments: &Rc<RefCell<Vec<mime::OutgoingAttachment>>>,
+    info_label: &Label,
+    new_paths: Vec<PathBuf>,
+) {
+    let mut errors = Vec::new();
+    {
+        let mut current = attachments.borrow_mut();
+        for path in new_paths.iter().filter(|p| p.is_file()) {
+            match mime::OutgoingAttachment::from_path(path) {
+                Ok(att) => {
+                    let duplicate = current
+                        .iter()
+                        .any(|a| a.filename == att.filename && a.data == att.data);
+                    if !duplicate {
+                        current.push(att);
+                    }
+                }
+                Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        info_label.set_label(&format!(
+            "Anhang konnte nicht gelesen werden: {}",
+            errors.join(", ")
+        ));
+    }
+    info_label.set_visible(!errors.is_empty());
+    refresh_attachment_list(list, attachments);
+}
+
+fn refresh_attachment_list(
+    list: &ListBox,
+    attachments: &Rc<RefCell<Vec<mime::OutgoingAttachment>>>,
+) {
+    while let Some(child) = list.first_child() {
+        list.remove(&child);
+    }
+
+    for (idx, attachment) in attachments.borrow().iter().enumerate() {
+        let hbox = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(8)
+            .margin_start(5)
+            .margin_end(5)
+            .build();
+        let icon = gtk4::Image::from_icon_name("mail-attachment-symbolic");
+        let lbl_name = Label::builder()
+            .label(&attachment.filename)
+            .xalign(0.0)
+            .hexpand(true)
+            .ellipsize(gtk4::pango::EllipsizeMode::Middle)
+            .build();
+        let lbl_size = Label::builder()
+            .label(attachments::format_size(attachment.data.len()))
+            .css_classes(["dim-label"])
+            .build();
+        let remove_btn = Button::builder()
+            .icon_name("window-close-symbolic")
+            .tooltip_text("Anhang entfernen (Entf)")
+            .css_classes(["flat"])
+            .build();
+
+        let list_c = list.clone();
+        let atts_c = attachments.clone();
+        remove_btn.connect_clicked(move |_| {
+            if idx < atts_c.borrow().len() {
+                atts_c.borrow_mut().remove(idx);
+            }
+            refresh_attachment_list(&list_c, &atts_c);
+        });
+
+        hbox.append(&icon);
+        hbox.append(&lbl_name);
+        hbox.append(&lbl_size);
+        hbox.append(&remove_btn);
+        list.append(&hbox);
+    }
+
+    list.set_visible(!attachments.borrow().is_empty());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> gtk4::glib::DateTime {
+        gtk4::glib::DateTime::from_local(2026, 10, 17, 12, 0, 0.0).unwrap()
+    }
+
+    fn local(y: i32, mo: i32, d: i32, h: i32, m: i32) -> Option<i64> {
+        Some(
+            gtk4::glib::DateTime::from_local(y, mo, d, h, m, 0.0)
+                .unwrap()
+                .to_unix(),
+        )
+    }
+
+    #[test]
+    fn relative() {
+        let now = now();
+        let base = now.to_unix();
+        assert_eq!(send_time_from(&now, "+30m"), Some(base + 1800));
+        assert_eq!(send_time_from(&now, "+30 min"), Some(base + 1800));
+        assert_eq!(send_time_from(&now, "+2h"), Some(base + 7200));
+        assert_eq!(send_time_from(&now, " +1D "), Some(base + 86400));
+        assert_eq!(send_time_from(&now, "+0m"), None);
+        assert_eq!(send_time_from(&now, "+5"), None);
+        assert_eq!(send_time_from(&now, "+5w"), None);
+        assert_eq!(send_time_from(&now, "+-5m"), None);
+    }
+
+    #[test]
+    fn relative_overflow() {
+        let now = now();
+        assert_eq!(send_time_from(&now, "+99999999999999d"), None);
+        assert_eq!(send_time_from(&now, "+9223372036854775807m"), None);
+        assert_eq!(send_time_from(&now, "+99999999999999999999h"), None);
+        // Jenseits des Jahres 9999
+        assert_eq!(send_time_from(&now, "+3000000d"), None);
+    }
+
+    #[test]
+    fn absolute() {
+        let now = now();
+        assert_eq!(send_time_from(&now, "18:30"), local(2026, 10, 17, 18, 30));
+        // Schon vorbei: morgen
+        assert_eq!(send_time_from(&now, "8:00"), local(2026, 10, 18, 8, 0));
+        assert_eq!(
+            send_time_from(&now, "Morgen 7:15"),
+            local(2026, 10, 18, 7, 15)
+        );
+        assert_eq!(
+            send_time_from(&now, "24.12.2026 9:00"),
+            local(2026, 12, 24, 9, 0)
+        );
+        assert_eq!(
+            send_time_from(&now, "2026-12-24 09:00"),
+            local(2026, 12, 24, 9, 0)
+        );
+        assert_eq!(send_time_from(&now, "24.12.2025 9:00"), None);
+        assert_eq!(send_time_from(&now, "25:00"), None);
+        assert_eq!(send_time_from(&now, "31.02.2027 9:00"), None);
+        assert_eq!(send_time_from(&now, "bald"), None);
+    }
+}
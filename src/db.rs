@@ -1,6 +1,14 @@
-use rusqlite::{Connection, Result};
+use rusqlite::{Connection, OptionalExtension, Result};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
+// Schema-Version des Nachrichten-Index (PRAGMA user_version)
+const INDEX_VERSION: i64 = 3;
+
+const MESSAGE_COLUMNS: &str = "unique_name, path, mtime, size, flags, sender, subject, date, \
+    timestamp, return_path, list_unsubscribe, to_addrs, cc_addrs, list_id, has_attachment, \
+    message_id, in_reply_to, refs";
+
 pub fn db_path() -> PathBuf {
     dirs::home_dir().unwrap().join(".noxmail.db")
 }
@@ -24,9 +32,375 @@ pub fn init_db() -> Result<()> {
         [],
     );
 
+    // Der Nachrichten-Index ist nur ein Cache: Bei neuem Schema wird er verworfen und
+    // beim nächsten Abgleich der Ordner neu aufgebaut
+    let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version < INDEX_VERSION {
+        conn.execute("DROP TABLE IF EXISTS message_fts", [])?;
+        conn.execute("DROP TABLE IF EXISTS messages", [])?;
+        conn.pragma_update(None, "user_version", INDEX_VERSION)?;
+    }
+
+    // Nachrichten-Index: Header-Cache pro Maildir-Datei, damit beim Öffnen eines Ordners
+    // nur neue oder veränderte Dateien geparst werden müssen
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS messages (
+            id INTEGER PRIMARY KEY,
+            folder TEXT NOT NULL,
+            unique_name TEXT NOT NULL,
+            path TEXT NOT NULL,
+            mtime INTEGER NOT NULL,
+            size INTEGER NOT NULL,
+            flags TEXT NOT NULL DEFAULT '',
+            sender TEXT NOT NULL,
+            subject TEXT NOT NULL,
+            date TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            return_path TEXT NOT NULL,
+            list_unsubscribe TEXT,
+            to_addrs TEXT NOT NULL DEFAULT '',
+            cc_addrs TEXT NOT NULL DEFAULT '',
+            list_id TEXT,
+            has_attachment BOOLEAN NOT NULL DEFAULT 0,
+            message_id TEXT NOT NULL DEFAULT '',
+            in_reply_to TEXT NOT NULL DEFAULT '',
+            refs TEXT NOT NULL DEFAULT '',
+            UNIQUE (folder, unique_name)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS messages_path ON messages (path)",
+        [],
+    )?;
+
+    // Volltext-Index über alle Ordner, rowid entspricht messages.id
+    conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS message_fts USING fts5(subject, sender, body)",
+        [],
+    )?;
+
+    // Gespeicherte Suchen, in der Seitenleiste als virtuelle Ordner
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS saved_searches (
+            id INTEGER PRIMARY KEY,
+            name TEXT UNIQUE NOT NULL,
+            query TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Versandversuche der Mails in Outbox/new, Schlüssel ist der Dateiname. Mails ohne
+    // Eintrag wurden noch nie versucht.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS outbox_attempts (
+            name TEXT PRIMARY KEY,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            next_attempt INTEGER NOT NULL DEFAULT 0,
+            last_error TEXT NOT NULL DEFAULT '',
+            failed BOOLEAN NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+#[derive(Clone)]
+pub struct IndexedMessage {
+    pub unique_name: String,
+    pub path: PathBuf,
+    pub mtime: i64,
+    pub size: i64,
+    pub flags: String,
+    pub from: String,
+    pub subject: String,
+    pub date: String,
+    pub timestamp: i64,
+    pub return_path: String,
+    pub list_unsubscribe: Option<String>,
+    pub to: String,
+    pub cc: String,
+    pub list_id: Option<String>,
+    pub has_attachment: bool,
+    // Für das Threading: Message-IDs ohne spitze Klammern, refs durch Leerzeichen getrennt
+    pub message_id: String,
+    pub in_reply_to: String,
+    pub refs: String,
+    // Nur gesetzt, wenn die Datei frisch geparst wurde; füttert den Volltext-Index
+    pub body: Option<String>,
+}
+
+// Spaltenreihenfolge wie MESSAGE_COLUMNS, beginnend bei Index `offset`
+fn message_from_row(row: &rusqlite::Row, offset: usize) -> Result<IndexedMessage> {
+    Ok(IndexedMessage {
+        unique_name: row.get(offset)?,
+        path: PathBuf::from(row.get::<_, String>(offset + 1)?),
+        mtime: row.get(offset + 2)?,
+        size: row.get(offset + 3)?,
+        flags: row.get(offset + 4)?,
+        from: row.get(offset + 5)?,
+        subject: row.get(offset + 6)?,
+        date: row.get(offset + 7)?,
+        timestamp: row.get(offset + 8)?,
+        return_path: row.get(offset + 9)?,
+        list_unsubscribe: row.get(offset + 10)?,
+        to: row.get(offset + 11)?,
+        cc: row.get(offset + 12)?,
+        list_id: row.get(offset + 13)?,
+        has_attachment: row.get(offset + 14)?,
+        message_id: row.get(offset + 15)?,
+        in_reply_to: row.get(offset + 16)?,
+        refs: row.get(offset + 17)?,
+        body: None,
+    })
+}
+
+pub fn get_folder_index(folder: &str) -> Result<HashMap<String, IndexedMessage>> {
+    let conn = Connection::open(db_path())?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM messages WHERE folder = ?1",
+        MESSAGE_COLUMNS
+    ))?;
+    let rows = stmt.query_map([folder], |row| message_from_row(row, 0))?;
+
+    let mut index = HashMap::new();
+    for msg in rows {
+        let msg = msg?;
+        index.insert(msg.unique_name.clone(), msg);
+    }
+    Ok(index)
+}
+
+// Gleicht den Index eines Ordners ab: geänderte/neue Einträge schreiben, verschwundene löschen
+pub fn sync_folder_index(
+    folder: &str,
+    upserts: &[IndexedMessage],
+    removed: &[String],
+) -> Result<()> {
+    let mut conn = Connection::open(db_path())?;
+    let tx = conn.transaction()?;
+
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO messages (folder, unique_name, path, mtime, size, flags, sender, subject,
+                                   date, timestamp, return_path, list_unsubscribe, to_addrs,
+                                   cc_addrs, list_id, has_attachment, message_id, in_reply_to,
+                                   refs)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+                     ?17, ?18, ?19)
+             ON CONFLICT(folder, unique_name) DO UPDATE SET
+                path = excluded.path,
+                mtime = excluded.mtime,
+                size = excluded.size,
+                flags = excluded.flags,
+                sender = excluded.sender,
+                subject = excluded.subject,
+                date = excluded.date,
+                timestamp = excluded.timestamp,
+                return_path = excluded.return_path,
+                list_unsubscribe = excluded.list_unsubscribe,
+                to_addrs = excluded.to_addrs,
+                cc_addrs = excluded.cc_addrs,
+                list_id = excluded.list_id,
+                has_attachment = excluded.has_attachment,
+                message_id = excluded.message_id,
+                in_reply_to = excluded.in_reply_to,
+                refs = excluded.refs",
+        )?;
+        for msg in upserts {
+            stmt.execute(rusqlite::params![
+                folder,
+                msg.unique_name,
+                msg.path.to_string_lossy(),
+                msg.mtime,
+                msg.size,
+                msg.flags,
+                msg.from,
+                msg.subject,
+                msg.date,
+                msg.timestamp,
+                msg.return_path,
+                msg.list_unsubscribe,
+                msg.to,
+                msg.cc,
+                msg.list_id,
+                msg.has_attachment,
+                msg.message_id,
+                msg.in_reply_to,
+                msg.refs,
+            ])?;
+
+            if let Some(body) = &msg.body {
+                let id: i64 = tx.query_row(
+                    "SELECT id FROM messages WHERE folder = ?1 AND unique_name = ?2",
+                    [folder, msg.unique_name.as_str()],
+                    |row| row.get(0),
+                )?;
+                tx.execute("DELETE FROM message_fts WHERE rowid = ?1", [id])?;
+                tx.execute(
+                    "INSERT INTO message_fts (rowid, subject, sender, body) VALUES (?1, ?2, ?3, ?4)",
+                    rusqlite::params![id, msg.subject, msg.from, body],
+                )?;
+            }
+        }
+
+        let mut del_fts = tx.prepare(
+            "DELETE FROM message_fts WHERE rowid IN
+                (SELECT id FROM messages WHERE folder = ?1 AND unique_name = ?2)",
+        )?;
+        let mut del = tx.prepare("DELETE FROM messages WHERE folder = ?1 AND unique_name = ?2")?;
+        for unique_name in removed {
+            del_fts.execute([folder, unique_name.as_str()])?;
+            del.execute([folder, unique_name.as_str()])?;
+        }
+    }
+
+    tx.commit()?;
     Ok(())
 }
 
+// Hält den Index nach Verschieben oder Umbenennen (Flags) einer Datei aktuell
+pub fn relocate_message(
+    old_path: &std::path::Path,
+    new_path: &std::path::Path,
+    folder: &str,
+) -> Result<()> {
+    let conn = Connection::open(db_path())?;
+    let flags = maildir_flags(new_path);
+    let unique_name: Option<String> = conn
+        .query_row(
+            "SELECT unique_name FROM messages WHERE path = ?1",
+            [old_path.to_string_lossy()],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    if let Some(unique_name) = unique_name {
+        conn.execute(
+            "DELETE FROM message_fts WHERE rowid IN
+                (SELECT id FROM messages WHERE folder = ?1 AND unique_name = ?2 AND path != ?3)",
+            rusqlite::params![folder, unique_name, old_path.to_string_lossy()],
+        )?;
+        conn.execute(
+            "DELETE FROM messages WHERE folder = ?1 AND unique_name = ?2 AND path != ?3",
+            rusqlite::params![folder, unique_name, old_path.to_string_lossy()],
+        )?;
+        conn.execute(
+            "UPDATE messages SET folder = ?1, path = ?2, flags = ?3 WHERE path = ?4",
+            rusqlite::params![
+                folder,
+                new_path.to_string_lossy(),
+                flags,
+                old_path.to_string_lossy()
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+// Pfade aller Mails, deren Text den Begriff enthält, nach Relevanz sortiert
+pub fn search_body_paths(term: &str) -> Result<Vec<PathBuf>> {
+    let match_expr = fts_match_expr(term);
+    if match_expr.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let conn = Connection::open(db_path())?;
+    let mut stmt = conn.prepare(
+        "SELECT messages.path FROM message_fts
+         JOIN messages ON messages.id = message_fts.rowid
+         WHERE message_fts MATCH ?1
+         ORDER BY bm25(message_fts, 5.0, 3.0, 1.0)",
+    )?;
+    let rows = stmt.query_map(rusqlite::params![match_expr], |row| row.get::<_, String>(0))?;
+
+    let mut paths = Vec::new();
+    for p in rows {
+        paths.push(PathBuf::from(p?));
+    }
+    Ok(paths)
+}
+
+pub fn get_all_indexed_messages() -> Result<Vec<(String, IndexedMessage)>> {
+    let conn = Connection::open(db_path())?;
+    let mut stmt = conn.prepare(&format!("SELECT folder, {} FROM messages", MESSAGE_COLUMNS))?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, message_from_row(row, 1)?))
+    })?;
+
+    let mut messages = Vec::new();
+    for r in rows {
+        messages.push(r?);
+    }
+    Ok(messages)
+}
+
+pub fn get_verified_emails() -> Result<HashSet<String>> {
+    let conn = Connection::open(db_path())?;
+    let mut stmt = conn.prepare("SELECT email FROM contacts WHERE is_verified = 1")?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+    rows.collect()
+}
+
+pub fn get_emails_with_key() -> Result<HashSet<String>> {
+    let conn = Connection::open(db_path())?;
+    let mut stmt =
+        conn.prepare("SELECT email FROM contacts WHERE pub_key IS NOT NULL AND pub_key != ''")?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+    rows.collect()
+}
+
+// Freitext in einen FTS5-Ausdruck übersetzen: jedes Wort als Phrase quoten (keine
+// Syntaxfehler bei Sonderzeichen), das letzte als Präfix für die Suche beim Tippen
+fn fts_match_expr(query: &str) -> String {
+    let words: Vec<String> = query
+        .split_whitespace()
+        .map(|w| format!("\"{}\"", w.replace('"', "\"\"")))
+        .collect();
+    if words.is_empty() {
+        return String::new();
+    }
+    format!("{}*", words.join(" "))
+}
+
+// Flag-Änderung innerhalb desselben Ordners (z.B. new -> cur beim Lesen)
+pub fn rename_message(old_path: &std::path::Path, new_path: &std::path::Path) -> Result<()> {
+    let conn = Connection::open(db_path())?;
+    conn.execute(
+        "UPDATE messages SET path = ?1, flags = ?2 WHERE path = ?3",
+        rusqlite::params![
+            new_path.to_string_lossy(),
+            maildir_flags(new_path),
+            old_path.to_string_lossy()
+        ],
+    )?;
+    Ok(())
+}
+
+// Eindeutiger Maildir-Name: Dateiname ohne den ":2,<Flags>"-Teil
+pub fn maildir_unique_name(path: &std::path::Path) -> String {
+    let file_name = path
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_default();
+    match file_name.find(":2,") {
+        Some(idx) => file_name[..idx].to_string(),
+        None => file_name,
+    }
+}
+
+pub fn maildir_flags(path: &std::path::Path) -> String {
+    let file_name = path
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_default();
+    match file_name.find(":2,") {
+        Some(idx) => file_name[idx + 3..].to_string(),
+        None => String::new(),
+    }
+}
+
 pub fn bulk_upsert(
     contacts: &std::collections::HashMap<String, (String, Option<String>)>,
 ) -> Result<()> {
@@ -73,6 +447,37 @@ pub fn get_all_contacts() -> Result<Vec<(String, String, bool, bool)>> {
     Ok(contacts)
 }
 
+// Wie oft wir an eine Adresse geschrieben haben, gezählt über To/Cc der Mails im
+// Postausgang und in Sent (Schlüssel kleingeschrieben)
+pub fn get_sent_counts() -> Result<HashMap<String, usize>> {
+    let conn = Connection::open(db_path())?;
+    let mut stmt =
+        conn.prepare("SELECT to_addrs, cc_addrs FROM messages WHERE folder IN ('Outbox', 'Sent')")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+
+    let mut counts = HashMap::new();
+    for row in rows {
+        let (to, cc) = row?;
+        for list in [to, cc] {
+            let Ok(parsed) = mailparse::addrparse(&list) else {
+                continue;
+            };
+            for addr in parsed.iter() {
+                let singles = match addr {
+                    mailparse::MailAddr::Single(info) => vec![info],
+                    mailparse::MailAddr::Group(group) => group.addrs.iter().collect(),
+                };
+                for info in singles {
+                    *counts.entry(info.addr.to_lowercase()).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+    Ok(counts)
+}
+
 pub fn hide_contact(email: &str) -> Result<()> {
     let conn = Connection::open(db_path())?;
     conn.execute(
@@ -118,6 +523,85 @@ pub fn update_contact_name(email: &str, new_name: &str) -> Result<()> {
     Ok(())
 }
 
+pub fn get_saved_searches() -> Result<Vec<(String, String)>> {
+    let conn = Connection::open(db_path())?;
+    let mut stmt =
+        conn.prepare("SELECT name, query FROM saved_searches ORDER BY name COLLATE NOCASE ASC")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
+// Gleicher Name überschreibt die bestehende Suche
+pub fn save_search(name: &str, query: &str) -> Result<()> {
+    let conn = Connection::open(db_path())?;
+    conn.execute(
+        "INSERT INTO saved_searches (name, query) VALUES (?1, ?2)
+         ON CONFLICT(name) DO UPDATE SET query = excluded.query",
+        [name, query],
+    )?;
+    Ok(())
+}
+
+pub fn delete_saved_search(name: &str) -> Result<()> {
+    let conn = Connection::open(db_path())?;
+    conn.execute("DELETE FROM saved_searches WHERE name = ?1", [name])?;
+    Ok(())
+}
+
+#[derive(Clone, Default)]
+pub struct OutboxState {
+    pub attempts: i64,
+    // Unix-Zeit, vor der kein neuer Versuch stattfindet
+    pub next_attempt: i64,
+    pub last_error: String,
+    // Aufgegeben: nur noch manuell erneut versuchen
+    pub failed: bool,
+}
+
+pub fn get_outbox_states() -> Result<HashMap<String, OutboxState>> {
+    let conn = Connection::open(db_path())?;
+    let mut stmt = conn
+        .prepare("SELECT name, attempts, next_attempt, last_error, failed FROM outbox_attempts")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get(0)?,
+            OutboxState {
+                attempts: row.get(1)?,
+                next_attempt: row.get(2)?,
+                last_error: row.get(3)?,
+                failed: row.get(4)?,
+            },
+        ))
+    })?;
+    rows.collect()
+}
+
+pub fn set_outbox_state(name: &str, state: &OutboxState) -> Result<()> {
+    let conn = Connection::open(db_path())?;
+    conn.execute(
+        "INSERT INTO outbox_attempts (name, attempts, next_attempt, last_error, failed)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(name) DO UPDATE SET attempts = excluded.attempts,
+            next_attempt = excluded.next_attempt, last_error = excluded.last_error,
+            failed = excluded.failed",
+        rusqlite::params![
+            name,
+            state.attempts,
+            state.next_attempt,
+            state.last_error,
+            state.failed
+        ],
+    )?;
+    Ok(())
+}
+
+// Nach Versand, Abbruch oder manuellem "Erneut versuchen"
+pub fn clear_outbox_state(name: &str) -> Result<()> {
+    let conn = Connection::open(db_path())?;
+    conn.execute("DELETE FROM outbox_attempts WHERE name = ?1", [name])?;
+    Ok(())
+}
+
 pub fn parse_from(from: &str) -> (String, String) {
     if let Some(start) = from.find('<') {
         if let Some(end) = from.find('>') {
@@ -0,0 +1,98 @@
+use crate::attachments;
+use crate::composer::MailDraft;
+use crate::maildir;
+use crate::markdown;
+use crate::mime;
+use crate::msmtprc;
+use mailparse::MailHeaderMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub fn drafts_dir() -> PathBuf {
+    dirs::home_dir().unwrap().join(".Mail").join("Drafts")
+}
+
+// Entwurf als vollständige Mail in Drafts/cur mit den Flags D (Entwurf) und S (gelesen,
+// zählt nicht als ungelesen). Bcc bleibt im Entwurf erhalten. `previous` wird ersetzt,
+// damit pro Composer nur eine Datei existiert.
+pub fn save_draft(draft: &MailDraft, from: &str, previous: Option<&Path>) -> io::Result<PathBuf> {
+    let mut headers = mime::header_line("From", from);
+    for (name, value) in [
+        ("To", &draft.to),
+        ("Cc", &draft.cc),
+        ("Bcc", &draft.bcc),
+        ("Subject", &draft.subject),
+    ] {
+        if !value.trim().is_empty() {
+            headers.push_str(&mime::header_line(name, value));
+        }
+    }
+    headers.push_str(&mime::header_line("Date", &mime::rfc5322_now()));
+    if !draft.in_reply_to.is_empty() {
+        headers.push_str(&mime::header_line("In-Reply-To", &draft.in_reply_to));
+    }
+    if !draft.references.is_empty() {
+        headers.push_str(&mime::header_line("References", &draft.references));
+    }
+    // Markdown-Entwürfe behalten ihren HTML-Teil, daran erkennt load_draft den Modus
+    let html = draft.markdown.then(|| markdown::to_html(&draft.body));
+    let raw_mail = mime::build_message(&headers, &draft.body, html.as_deref(), &draft.attachments);
+
+    let cur_path = maildir::deliver(&drafts_dir(), raw_mail.as_bytes(), Some("DS"))?;
+
+    if let Some(prev) = previous {
+        delete_draft(prev);
+    }
+    Ok(cur_path)
+}
+
+pub fn delete_draft(path: &Path) {
+    if let Err(e) = std::fs::remove_file(path)
+        && e.kind() != io::ErrorKind::NotFound
+    {
+        eprintln!("Fehler beim Löschen des Entwurfs {:?}: {}", path, e);
+    }
+}
+
+// Gespeicherten Entwurf mit allen Feldern und Anhängen wieder in einen MailDraft laden
+pub fn load_draft(path: &Path) -> Result<MailDraft, String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    let parsed = mailparse::parse_mail(&data).map_err(|e| e.to_string())?;
+    let headers = parsed.get_headers();
+    let header = |name: &str| headers.get_first_value(name).unwrap_or_default();
+
+    // Der Mailtext ist bei build_message immer der erste Teil, bei Markdown innerhalb
+    // von multipart/alternative
+    let mut text_part = &parsed;
+    let mut markdown = false;
+    while let Some(first) = text_part.subparts.first() {
+        markdown |= text_part
+            .ctype
+            .mimetype
+            .eq_ignore_ascii_case("multipart/alternative");
+        text_part = first;
+    }
+    let body = mime::plain_text_body(text_part);
+
+    let attachments = attachments::collect_attachments(&parsed)
+        .into_iter()
+        .map(|a| mime::OutgoingAttachment {
+            filename: a.filename,
+            content_type: a.content_type,
+            data: a.data,
+        })
+        .collect();
+
+    Ok(MailDraft {
+        to: header("To"),
+        cc: header("Cc"),
+        bcc: header("Bcc"),
+        subject: header("Subject"),
+        body: body.replace("\r\n", "\n"),
+        in_reply_to: header("In-Reply-To"),
+        references: header("References"),
+        attachments,
+        account: msmtprc::account_for_address(&header("From")),
+        markdown,
+    })
+}
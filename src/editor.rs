@@ -0,0 +1,117 @@
+use crate::composer::MailDraft;
+use gtk4::gio;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+// Terminal, in dem der Editor läuft; der Befehl muss das Programm als letzte Argumente
+// annehmen (z.B. "foot", "kitty", "alacritty -e")
+const DEFAULT_TERMINAL: &str = "xterm -e";
+
+// Kopfzeilen, die in der Datei für den Editor stehen
+const EDITABLE_HEADERS: [&str; 4] = ["To", "Cc", "Bcc", "Subject"];
+
+fn editor_command() -> Vec<String> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    editor.split_whitespace().map(str::to_string).collect()
+}
+
+fn terminal_command() -> Vec<String> {
+    let terminal =
+        std::env::var("NOXMAIL_TERMINAL").unwrap_or_else(|_| DEFAULT_TERMINAL.to_string());
+    terminal.split_whitespace().map(str::to_string).collect()
+}
+
+fn temp_path() -> PathBuf {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    // .eml, damit vim & Co. den Dateityp "mail" erkennen
+    std::env::temp_dir().join(format!("noxmail-{}-{}.eml", std::process::id(), millis))
+}
+
+// Kopf und Text wie eine Mail, aber unkodiert: "To: …", Leerzeile, Text
+fn write_file(path: &Path, draft: &MailDraft) -> std::io::Result<()> {
+    let mut content = String::new();
+    for (name, value) in
+        EDITABLE_HEADERS
+            .iter()
+            .zip([&draft.to, &draft.cc, &draft.bcc, &draft.subject])
+    {
+        content.push_str(&format!("{}: {}\n", name, value));
+    }
+    content.push('\n');
+    content.push_str(&draft.body);
+    std::fs::write(path, content)
+}
+
+// Gegenstück zu write_file. Fehlende Kopfzeilen leeren das Feld, unbekannte werden
+// ignoriert, eingerückte Folgezeilen gehören zur vorherigen Kopfzeile.
+fn parse_file(content: &str, mut draft: MailDraft) -> MailDraft {
+    let (head, body) = match content.split_once("\n\n") {
+        Some((head, body)) => (head, body),
+        None => (content.trim_end_matches('\n'), ""),
+    };
+
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in head.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let header = |wanted: &str| {
+        headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(wanted))
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default()
+    };
+
+    draft.to = header("To");
+    draft.cc = header("Cc");
+    draft.bcc = header("Bcc");
+    draft.subject = header("Subject");
+    draft.body = body.to_string();
+    draft
+}
+
+// Kopf und Text in $VISUAL bzw. $EDITOR (im Terminal aus $NOXMAIL_TERMINAL) bearbeiten.
+// Kehrt sofort zurück; `on_done` läuft im GTK-Hauptthread, sobald der Editor beendet ist.
+pub fn edit_in_terminal(
+    draft: &MailDraft,
+    on_done: impl FnOnce(Result<MailDraft, String>) + 'static,
+) -> Result<(), String> {
+    let path = temp_path();
+    write_file(&path, draft).map_err(|e| format!("Temporäre Datei: {}", e))?;
+
+    let mut argv: Vec<OsString> = terminal_command().into_iter().map(OsString::from).collect();
+    argv.extend(editor_command().into_iter().map(OsString::from));
+    argv.push(path.clone().into_os_string());
+    let argv_refs: Vec<&std::ffi::OsStr> = argv.iter().map(|a| a.as_os_str()).collect();
+
+    let process = match gio::Subprocess::newv(&argv_refs, gio::SubprocessFlags::NONE) {
+        Ok(process) => process,
+        Err(e) => {
+            let _ = std::fs::remove_file(&path);
+            return Err(format!("Terminal konnte nicht gestartet werden: {}", e));
+        }
+    };
+
+    let draft = draft.clone();
+    process.wait_check_async(None::<&gio::Cancellable>, move |result| {
+        let edited = result
+            .map_err(|e| format!("Editor mit Fehler beendet: {}", e))
+            .and_then(|_| std::fs::read_to_string(&path).map_err(|e| e.to_string()))
+            .map(|content| parse_file(&content, draft));
+        let _ = std::fs::remove_file(&path);
+        on_done(edited);
+    });
+    Ok(())
+}
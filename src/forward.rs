@@ -0,0 +1,101 @@
+use crate::attachments;
+use crate::composer::MailDraft;
+use crate::mime::OutgoingAttachment;
+use crate::msmtprc;
+use mailparse::MailHeaderMap;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ForwardMode {
+    // Kopfzeilen und Text im Mailtext, Anhänge werden übernommen
+    Inline,
+    // Originaldatei unverändert als message/rfc822
+    Attached,
+}
+
+// Trennzeile vor einer im Text weitergeleiteten Mail
+pub const FORWARD_MARKER: &str = "---------- Weitergeleitete Nachricht ----------";
+
+// Kopfzeilen, die beim Weiterleiten im Text erscheinen: (Beschriftung, Header)
+const FORWARD_HEADERS: [(&str, &str); 5] = [
+    ("Von", "From"),
+    ("Datum", "Date"),
+    ("Betreff", "Subject"),
+    ("An", "To"),
+    ("Cc", "Cc"),
+];
+
+pub fn forward_draft(path: &Path, mode: ForwardMode) -> Result<MailDraft, String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    let parsed = mailparse::parse_mail(&data).map_err(|e| e.to_string())?;
+    let headers = parsed.get_headers();
+    let original_subject = headers.get_first_value("Subject").unwrap_or_default();
+
+    let (body, attachments) = match mode {
+        ForwardMode::Inline => {
+            let mut body = format!("\n\n{}\n", FORWARD_MARKER);
+            for (label, name) in FORWARD_HEADERS {
+                let value = headers.get_first_value(name).unwrap_or_default();
+                if !value.trim().is_empty() {
+                    body.push_str(&format!("{}: {}\n", label, value.trim()));
+                }
+            }
+            body.push('\n');
+            body.push_str(crate::extract_best_body(&parsed).trim_end());
+            body.push('\n');
+
+            let originals = attachments::collect_attachments(&parsed)
+                .into_iter()
+                .map(|a| OutgoingAttachment {
+                    filename: a.filename,
+                    content_type: a.content_type,
+                    data: a.data,
+                })
+                .collect();
+            (body, originals)
+        }
+        ForwardMode::Attached => {
+            let attachment = OutgoingAttachment {
+                filename: eml_filename(&original_subject),
+                content_type: "message/rfc822".to_string(),
+                data: data.clone(),
+            };
+            (String::new(), vec![attachment])
+        }
+    };
+
+    Ok(MailDraft {
+        subject: forward_subject(&original_subject),
+        body,
+        attachments,
+        // Weiterleiten über das Konto, an das die Mail ging
+        account: msmtprc::account_for_mail(&parsed.headers),
+        ..Default::default()
+    })
+}
+
+// "Fwd: " nur voranstellen, wenn die Mail nicht schon weitergeleitet wurde
+fn forward_subject(subject: &str) -> String {
+    let subject = subject.trim();
+    let lower = subject.to_lowercase();
+    if lower.starts_with("fwd:") || lower.starts_with("wg:") {
+        subject.to_string()
+    } else {
+        format!("Fwd: {}", subject)
+    }
+}
+
+// Dateiname aus dem Betreff, ohne Zeichen, die in Dateinamen stören
+fn eml_filename(subject: &str) -> String {
+    let name: String = subject
+        .chars()
+        .filter(|c| c.is_alphanumeric() || " -_.,".contains(*c))
+        .take(60)
+        .collect();
+    let name = name.trim();
+    if name.is_empty() {
+        "weitergeleitet.eml".to_string()
+    } else {
+        format!("{}.eml", name)
+    }
+}
@@ -47,8 +47,22 @@ pub fn show_help_window(app: &Application) {
         ("d", "Auswahl löschen (in TRASH)"),
         ("v", "Auswahl verifizieren (Toggle INBOX / Quarantäne)"),
         ("m", "Auswahl verschieben (Interaktiver Ordner-Dialog)"),
+        ("r", "Antworten (Reply-To bzw. From)"),
+        ("R", "Allen antworten (ohne eigene Adressen)"),
+        ("L", "An die Mailingliste antworten (List-Post)"),
+        ("f / F", "Weiterleiten (im Text / als Anhang)"),
+        ("o", "Anhang öffnen (gewählter bzw. erster)"),
+        ("s / S", "Anhang / alle Anhänge speichern"),
+        ("t", "Konversationsansicht (Threads) ein/aus"),
+        ("h / l", "Thread zuklappen / aufklappen"),
+        (
+            "Enter",
+            "Thread umschalten (Suchtreffer im Ordner, Entwurf im Composer öffnen)",
+        ),
         ("/", "Sucheingabe fokussieren"),
+        ("Entf", "Gespeicherte Suche in der Seitenleiste löschen"),
         ("Esc", "Suche abbrechen / Fokus zurück zur Liste"),
+        ("w", "Postausgang (Erneut versuchen / Abbrechen)"),
         ("?", "Diese Hilfe anzeigen"),
     ];
 
@@ -103,6 +117,96 @@ pub fn show_help_window(app: &Application) {
     }
     content_box.append(&grid_ab);
 
+    // --- Sektion: Composer ---
+    let composer_title = Label::builder()
+        .label("<span size='large' weight='bold'>Neue Mail</span>")
+        .use_markup(true)
+        .halign(gtk4::Align::Start)
+        .margin_top(10)
+        .build();
+    content_box.append(&composer_title);
+
+    let grid_composer = Grid::builder().row_spacing(10).column_spacing(20).build();
+
+    let shortcuts_composer = [
+        ("↑ / ↓, Enter / Tab", "Adressvorschlag wählen / übernehmen"),
+        ("Entf", "Gewählten Anhang entfernen"),
+        (
+            "Strg+E",
+            "Im externen Editor ($VISUAL / $EDITOR) bearbeiten",
+        ),
+        ("Strg+P", "Markdown-Vorschau ein-/ausblenden"),
+    ];
+
+    for (i, &(key, desc)) in shortcuts_composer.iter().enumerate() {
+        let key_label = Label::builder()
+            .label(format!("<tt><b>{}</b></tt>", key))
+            .use_markup(true)
+            .halign(gtk4::Align::End)
+            .build();
+        let desc_label = Label::builder()
+            .label(desc)
+            .halign(gtk4::Align::Start)
+            .build();
+
+        grid_composer.attach(&key_label, 0, i as i32, 1, 1);
+        grid_composer.attach(&desc_label, 1, i as i32, 1, 1);
+    }
+    content_box.append(&grid_composer);
+
+    // --- Sektion: Suchsyntax ---
+    let search_title = Label::builder()
+        .label("<span size='large' weight='bold'>Suche</span>")
+        .use_markup(true)
+        .halign(gtk4::Align::Start)
+        .margin_top(10)
+        .build();
+    content_box.append(&search_title);
+
+    let grid_search = Grid::builder().row_spacing(10).column_spacing(20).build();
+
+    let search_syntax = [
+        (
+            "from: to: subject:",
+            "Absender / Empfänger (inkl. Cc) / Betreff enthält",
+        ),
+        ("body:", "Mailtext enthält (Volltext-Index)"),
+        ("folder: list:", "Ordnername / List-Id enthält"),
+        (
+            "before: after:",
+            "Datum: 2024-03-01, 01.03.2024 oder relativ 7d, 2w, 3m, 1y",
+        ),
+        ("is:", "unread, read, verified"),
+        ("has:", "attachment, key"),
+        (
+            "\"…\"",
+            "Phrase mit Leerzeichen, z.B. subject:\"Offerte 2024\"",
+        ),
+        (
+            "AND OR NOT -",
+            "Verknüpfen (ohne Operator: AND), Klammern erlaubt",
+        ),
+    ];
+
+    for (i, &(key, desc)) in search_syntax.iter().enumerate() {
+        let key_label = Label::builder()
+            .label(format!(
+                "<tt><b>{}</b></tt>",
+                gtk4::glib::markup_escape_text(key)
+            ))
+            .use_markup(true)
+            .halign(gtk4::Align::End)
+            .build();
+        let desc_label = Label::builder()
+            .label(desc)
+            .halign(gtk4::Align::Start)
+            .build();
+
+        grid_search.attach(&key_label, 0, i as i32, 1, 1);
+        grid_search.attach(&desc_label, 1, i as i32, 1, 1);
+    }
+    content_box.append(&grid_search);
+
     scroll.set_child(Some(&content_box));
     vbox.append(&scroll);
 
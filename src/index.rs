@@ -0,0 +1,314 @@
+use crate::MailEntry;
+use crate::db;
+use crate::search;
+use crate::threading;
+use mailparse::MailHeaderMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+pub fn folder_path(folder_name: &str) -> PathBuf {
+    let mail_dir = dirs::home_dir().unwrap().join(".Mail");
+    if folder_name == "INBOX" && mail_dir.join("cur").exists() {
+        mail_dir
+    } else {
+        mail_dir.join(folder_name)
+    }
+}
+
+// Lädt einen Ordner über den Nachrichten-Index: Nur neue oder veränderte Dateien
+// (mtime/Grösse) werden geparst, der Rest kommt aus ~/.noxmail.db
+pub fn load_folder(folder_name: &str) -> Vec<MailEntry> {
+    reconcile(folder_name, true)
+}
+
+// Hintergrund-Abgleich aller Ordner beim Start, damit die Volltextsuche auch Ordner
+// findet, die noch nie geöffnet wurden. Die Quarantäne bleibt dabei unangetastet.
+pub fn index_all_folders(folders: &[String]) {
+    for folder in folders {
+        reconcile(folder, false);
+    }
+}
+
+// Alle indexierten Mails aller Ordner, z.B. als Grundlage für die Suche über alle Ordner
+pub fn load_all_folders() -> Vec<MailEntry> {
+    match db::get_all_indexed_messages() {
+        Ok(messages) => messages
+            .iter()
+            .map(|(folder, msg)| to_mail_entry(folder, msg))
+            .collect(),
+        Err(e) => {
+            eprintln!("Fehler beim Lesen des Nachrichten-Index: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// Inhalt eines virtuellen Ordners: gespeicherte Suche über alle indexierten Ordner
+pub fn load_saved_search(query: &str, now: i64, utc_offset: i64) -> Vec<MailEntry> {
+    match search::CompiledSearch::compile(query, true, now, utc_offset) {
+        Ok(compiled) => compiled.filter(&load_all_folders()),
+        Err(e) => {
+            eprintln!("Ungültige gespeicherte Suche '{}': {}", query, e);
+            Vec::new()
+        }
+    }
+}
+
+// Trefferzahlen aller gespeicherten Suchen in einem Durchgang
+pub fn count_saved_searches(queries: &[String], now: i64, utc_offset: i64) -> Vec<usize> {
+    let all = load_all_folders();
+    queries
+        .iter()
+        .map(
+            |query| match search::CompiledSearch::compile(query, true, now, utc_offset) {
+                Ok(compiled) => compiled.filter(&all).len(),
+                Err(_) => 0,
+            },
+        )
+        .collect()
+}
+
+fn reconcile(folder_name: &str, quarantine: bool) -> Vec<MailEntry> {
+    let md = maildir::Maildir::from(folder_path(folder_name));
+    let cached = db::get_folder_index(folder_name).unwrap_or_default();
+
+    let mut new_entries = Vec::new();
+    let mut upserts = Vec::new();
+    let mut seen = HashSet::new();
+    let mut quarantined = Vec::new();
+    // Bereits indexierte Mails, die in die Quarantäne wandern: (Pfad im Index, neuer Pfad)
+    let mut relocated = Vec::new();
+    let mut db_contacts: HashMap<String, (String, Option<String>)> = HashMap::new();
+
+    let verified_senders = db::get_verified_emails().unwrap_or_default();
+
+    for entry in md.list_new().chain(md.list_cur()) {
+        let Ok(mail) = entry else { continue };
+        let path = mail.path().to_path_buf();
+
+        let Ok(meta) = std::fs::metadata(&path) else {
+            continue;
+        };
+        let mtime = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        let size = meta.len() as i64;
+        let unique_name = db::maildir_unique_name(&path);
+        let flags = db::maildir_flags(&path);
+
+        let (indexed, changed) = match cached.get(&unique_name) {
+            Some(c) if c.mtime == mtime && c.size == size => {
+                let changed = c.path != path || c.flags != flags;
+                let mut updated = c.clone();
+                updated.path = path.clone();
+                updated.flags = flags;
+                (updated, changed)
+            }
+            _ => {
+                let Some((parsed, name, email, pub_key)) =
+                    parse_message(&path, unique_name.clone(), mtime, size, flags)
+                else {
+                    continue;
+                };
+
+                if !email.is_empty() {
+                    let entry = db_contacts
+                        .entry(email)
+                        .or_insert_with(|| (name.clone(), pub_key.clone()));
+                    if entry.0.is_empty() && !name.is_empty() {
+                        entry.0 = name;
+                    }
+                    if entry.1.is_none() && pub_key.is_some() {
+                        entry.1 = pub_key;
+                    }
+                }
+
+                (parsed, true)
+            }
+        };
+
+        if quarantine
+            && folder_name == "INBOX"
+            && !verified_senders.contains(&indexed.return_path)
+            && let Some(new_path) = crate::move_mail_file(&path, "Quarantäne")
+        {
+            // Vorhandene Zeile samt Volltext-Eintrag mitnehmen statt neu anzulegen; der
+            // gecachte Eintrag hat keinen Text mehr, aus dem sich der Volltext neu bauen liesse
+            if let Some(c) = cached.get(&unique_name) {
+                seen.insert(unique_name);
+                relocated.push((c.path.clone(), new_path.clone()));
+            }
+            if changed {
+                let mut moved = indexed;
+                moved.path = new_path;
+                quarantined.push(moved);
+            }
+            continue;
+        }
+
+        seen.insert(unique_name);
+        new_entries.push(to_mail_entry(folder_name, &indexed));
+        if changed {
+            upserts.push(indexed);
+        }
+    }
+
+    let removed: Vec<String> = cached
+        .keys()
+        .filter(|k| !seen.contains(*k))
+        .cloned()
+        .collect();
+
+    if let Err(e) = db::sync_folder_index(folder_name, &upserts, &removed) {
+        eprintln!("Fehler beim Aktualisieren des Nachrichten-Index: {}", e);
+    }
+    for (old_path, new_path) in &relocated {
+        if let Err(e) = db::relocate_message(old_path, new_path, "Quarantäne") {
+            eprintln!("Fehler beim Aktualisieren des Nachrichten-Index: {}", e);
+        }
+    }
+    if !quarantined.is_empty()
+        && let Err(e) = db::sync_folder_index("Quarantäne", &quarantined, &[])
+    {
+        eprintln!("Fehler beim Aktualisieren des Nachrichten-Index: {}", e);
+    }
+
+    if let Err(e) = db::bulk_upsert(&db_contacts) {
+        eprintln!("Fehler beim Speichern der Kontakte: {}", e);
+    }
+
+    new_entries
+}
+
+// Parst die Header einer Datei; liefert zusätzlich Name, E-Mail und Autocrypt-Key für das Adressbuch
+fn parse_message(
+    path: &Path,
+    unique_name: String,
+    mtime: i64,
+    size: i64,
+    flags: String,
+) -> Option<(db::IndexedMessage, String, String, Option<String>)> {
+    let data = std::fs::read(path).ok()?;
+    let parsed = mailparse::parse_mail(&data).ok()?;
+    let headers = parsed.get_headers();
+
+    let subject = headers
+        .get_first_value("Subject")
+        .unwrap_or_else(|| "Kein Betreff".to_string());
+    let from = headers
+        .get_first_value("From")
+        .unwrap_or_else(|| "Unbekannt".to_string());
+    let return_path_raw = headers
+        .get_first_value("Return-Path")
+        .unwrap_or_else(|| "".to_string());
+    let date_str = headers.get_first_value("Date").unwrap_or_default();
+    let timestamp = mailparse::dateparse(&date_str).unwrap_or(0);
+
+    let mut pub_key = None;
+    if let Some(ac_val) = headers.get_first_value("Autocrypt") {
+        if let Some(idx) = ac_val.find("keydata=") {
+            pub_key = Some(ac_val[idx + 8..].trim().to_string());
+        }
+    }
+
+    let mut list_unsubscribe = None;
+    if let Some(lu_val) = headers.get_first_value("List-Unsubscribe") {
+        if let Some(start) = lu_val.find('<') {
+            if let Some(end) = lu_val[start..].find('>') {
+                list_unsubscribe = Some(lu_val[start + 1..start + end].to_string());
+            }
+        }
+    }
+
+    let to = headers.get_all_values("To").join(", ");
+    let cc = headers.get_all_values("Cc").join(", ");
+    let list_id = headers.get_first_value("List-Id");
+
+    let message_id = headers
+        .get_first_value("Message-ID")
+        .and_then(|v| threading::parse_message_ids(&v).into_iter().next())
+        .unwrap_or_default();
+    let in_reply_to = headers
+        .get_first_value("In-Reply-To")
+        .and_then(|v| threading::parse_message_ids(&v).into_iter().next())
+        .unwrap_or_default();
+    let refs = headers
+        .get_first_value("References")
+        .map(|v| threading::parse_message_ids(&v).join(" "))
+        .unwrap_or_default();
+
+    let (name, email) = db::parse_from(&from);
+    let body = crate::extract_best_body(&parsed);
+    let has_attachment = has_attachment(&parsed);
+
+    let mut return_path_clean = return_path_raw.replace(['<', '>'], "").trim().to_string();
+    if return_path_clean.is_empty() {
+        return_path_clean = email.clone();
+    }
+
+    let msg = db::IndexedMessage {
+        unique_name,
+        path: path.to_path_buf(),
+        mtime,
+        size,
+        flags,
+        from,
+        subject,
+        date: date_str,
+        timestamp,
+        return_path: return_path_clean,
+        list_unsubscribe,
+        to,
+        cc,
+        list_id,
+        has_attachment,
+        message_id,
+        in_reply_to,
+        refs,
+        body: Some(body),
+    };
+    Some((msg, name, email, pub_key))
+}
+
+fn has_attachment(parsed: &mailparse::ParsedMail) -> bool {
+    if !parsed.subparts.is_empty() {
+        return parsed.subparts.iter().any(has_attachment);
+    }
+    let disposition = parsed.get_content_disposition();
+    disposition.disposition == mailparse::DispositionType::Attachment
+        || disposition.params.contains_key("filename")
+        || parsed.ctype.params.contains_key("name")
+}
+
+pub fn to_mail_entry(folder: &str, msg: &db::IndexedMessage) -> MailEntry {
+    let date_short = gtk4::glib::DateTime::from_unix_local(msg.timestamp)
+        .map(|dt| {
+            dt.format("%d.%m.%y %H:%M")
+                .unwrap_or(msg.date.clone().into())
+                .to_string()
+        })
+        .unwrap_or_else(|_| msg.date.clone());
+
+    MailEntry {
+        folder: folder.to_string(),
+        path: msg.path.clone(),
+        timestamp: msg.timestamp,
+        date_short,
+        date_full: msg.date.clone(),
+        from: msg.from.clone(),
+        return_path: msg.return_path.clone(),
+        subject: msg.subject.clone(),
+        is_read: msg.flags.contains('S'),
+        list_unsubscribe: msg.list_unsubscribe.clone(),
+        to: msg.to.clone(),
+        cc: msg.cc.clone(),
+        list_id: msg.list_id.clone(),
+        has_attachment: msg.has_attachment,
+        message_id: msg.message_id.clone(),
+        in_reply_to: msg.in_reply_to.clone(),
+        references: msg.refs.split_whitespace().map(str::to_string).collect(),
+    }
+}
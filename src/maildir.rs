@@ -0,0 +1,71 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Zustellung nach Maildir-Regeln für alle Mails, die nox selbst schreibt (Postausgang,
+// Entwürfe, Gesendet): zuerst vollständig nach tmp/, auf die Platte bringen, dann per
+// rename nach new/ bzw. cur/. Ein Leser sieht die Datei so entweder ganz oder gar nicht.
+
+static DELIVERY_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// "Zeit.PID_Zähler.Host"; der Zähler trennt Zustellungen derselben Sekunde
+pub fn unique_name() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    // "/" und ":" sind im Hostnamen nicht erlaubt, weil sie Pfad bzw. Flags trennen
+    let host = gtk4::glib::host_name()
+        .replace('/', "\\057")
+        .replace(':', "\\072");
+    format!(
+        "{}.{}_{}.{}",
+        secs,
+        std::process::id(),
+        DELIVERY_COUNTER.fetch_add(1, Ordering::Relaxed),
+        host
+    )
+}
+
+// Stellt `data` unter dem Namen `unique` in den Maildir-Ordner `folder` zu. Ohne Flags
+// landet die Mail in new/, mit Flags (z.B. "S") als "unique:2,S" in cur/.
+pub fn deliver_as(
+    folder: &Path,
+    unique: &str,
+    data: &[u8],
+    flags: Option<&str>,
+) -> io::Result<PathBuf> {
+    for sub in ["tmp", "new", "cur"] {
+        std::fs::create_dir_all(folder.join(sub))?;
+    }
+
+    let tmp_path = folder.join("tmp").join(unique);
+    let target = match flags {
+        Some(flags) => folder.join("cur").join(format!("{}:2,{}", unique, flags)),
+        None => folder.join("new").join(unique),
+    };
+
+    let write = || -> io::Result<()> {
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, &target)?;
+        // Der rename selbst ist erst sicher, wenn das Verzeichnis geschrieben ist
+        if let Some(dir) = target.parent() {
+            std::fs::File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    };
+    if let Err(e) = write() {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+    Ok(target)
+}
+
+pub fn deliver(folder: &Path, data: &[u8], flags: Option<&str>) -> io::Result<PathBuf> {
+    deliver_as(folder, &unique_name(), data, flags)
+}
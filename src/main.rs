@@ -5,22 +5,43 @@ use gtk4::{
     ScrolledWindow, SearchEntry, SelectionMode, Spinner, TextView, ToggleButton,
 };
 use mailparse::MailHeaderMap;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 mod addressbook;
+mod attachments;
+mod completion;
 mod composer;
 mod db;
+mod drafts;
+mod editor;
+mod forward;
 mod help;
+mod index;
+mod maildir;
+mod markdown;
+mod mime;
+mod msmtprc;
+mod outbox;
+mod queue;
+mod quote;
+mod reply;
+mod search;
+mod send_checks;
+mod signature;
+mod smtp;
 mod status; // NEU
+mod threading;
 
 const APP_ID: &str = "app.noxmail.Nox";
 
 #[derive(Clone)]
 struct MailEntry {
+    folder: String,
     path: PathBuf,
     timestamp: i64,
     date_short: String,
@@ -30,6 +51,30 @@ struct MailEntry {
     subject: String,
     is_read: bool,
     list_unsubscribe: Option<String>,
+    to: String,
+    cc: String,
+    list_id: Option<String>,
+    has_attachment: bool,
+    message_id: String,
+    in_reply_to: String,
+    references: Vec<String>,
+}
+
+// Thread-Zugehörigkeit einer Zeile in der Konversationsansicht
+#[derive(Clone)]
+struct ThreadRow {
+    // Schlüssel zum Auf-/Zuklappen: Message-ID (sonst Pfad) der ersten Mail des Threads
+    key: String,
+    // Alle Mails des Threads als Indizes in current_mail_entries, chronologisch
+    members: Rc<Vec<usize>>,
+    depth: usize,
+}
+
+// Eintrag der Seitenleiste: physischer Maildir-Ordner oder gespeicherte Suche
+#[derive(Clone, PartialEq)]
+enum FolderRef {
+    Maildir(String),
+    Saved { name: String, query: String },
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -39,20 +84,15 @@ enum SortCol {
     Subject,
 }
 
-fn perform_search(entries: &[MailEntry], query: &str) -> Vec<MailEntry> {
-    if query.trim().is_empty() {
-        return entries.to_vec();
+// Bei einer fehlerhaften Anfrage bleibt die Liste ungefiltert, der Fehler steht in der Statusleiste
+fn perform_search(
+    entries: &[MailEntry],
+    search: &Result<search::CompiledSearch, search::ParseError>,
+) -> Vec<MailEntry> {
+    match search {
+        Ok(compiled) => compiled.filter(entries),
+        Err(_) => entries.to_vec(),
     }
-    let q = query.to_lowercase();
-    entries
-        .iter()
-        .filter(|e| {
-            e.subject.to_lowercase().contains(&q)
-                || e.from.to_lowercase().contains(&q)
-                || e.date_full.to_lowercase().contains(&q)
-        })
-        .cloned()
-        .collect()
 }
 
 fn move_mail_file(old_path: &PathBuf, target_folder: &str) -> Option<PathBuf> {
@@ -72,6 +112,9 @@ fn move_mail_file(old_path: &PathBuf, target_folder: &str) -> Option<PathBuf> {
 
     let new_path = target_dir.join(file_name);
     if fs::rename(old_path, &new_path).is_ok() {
+        if let Err(e) = db::relocate_message(old_path, &new_path, target_folder) {
+            eprintln!("Fehler beim Aktualisieren des Nachrichten-Index: {}", e);
+        }
         Some(new_path)
     } else {
         None
@@ -83,6 +126,20 @@ fn main() -> gtk4::glib::ExitCode {
         eprintln!("Fehler bei der DB-Initialisierung: {}", e);
     }
 
+    // `noxmail send-queue`: fällige Mails aus dem Postausgang senden, ohne GUI (Cronjob)
+    if std::env::args().nth(1).as_deref() == Some("send-queue") {
+        let report = outbox::send_due_mails();
+        println!("{} Mail(s) gesendet", report.sent);
+        for (subject, error) in &report.errors {
+            eprintln!("Nicht gesendet: {}: {}", subject, error);
+        }
+        return if report.errors.is_empty() {
+            gtk4::glib::ExitCode::SUCCESS
+        } else {
+            gtk4::glib::ExitCode::FAILURE
+        };
+    }
+
     let app = Application::builder().application_id(APP_ID).build();
     app.connect_activate(build_ui);
     app.run()
@@ -111,6 +168,15 @@ fn build_ui(app: &Application) {
         .build();
 
     let folders = get_maildir_folders();
+
+    // Volltext-Index im Hintergrund für alle Ordner aktuell halten
+    let folders_to_index = folders.clone();
+    let (index_done_tx, index_done_rx) = std::sync::mpsc::channel::<()>();
+    std::thread::spawn(move || {
+        index::index_all_folders(&folders_to_index);
+        let _ = index_done_tx.send(());
+    });
+
     for folder in &folders {
         let label = Label::builder()
             .label(folder)
@@ -123,6 +189,79 @@ fn build_ui(app: &Application) {
         folder_list.append(&label);
     }
 
+    // Virtuelle Ordner (gespeicherte Suchen) folgen nach den Maildir-Ordnern
+    let saved_searches = Rc::new(RefCell::new(Vec::<(String, String, Label)>::new()));
+    for (name, query) in db::get_saved_searches().unwrap_or_default() {
+        let (row_box, count_label) = build_saved_search_row(&name);
+        folder_list.append(&row_box);
+        saved_searches.borrow_mut().push((name, query, count_label));
+    }
+
+    // Trefferzahlen der virtuellen Ordner im Hintergrund neu berechnen
+    let counts_busy = Rc::new(Cell::new(false));
+    let do_refresh_counts = {
+        let saved = saved_searches.clone();
+        let busy = counts_busy.clone();
+
+        Rc::new(move || {
+            if busy.get() || saved.borrow().is_empty() {
+                return;
+            }
+            busy.set(true);
+
+            let queries: Vec<String> = saved.borrow().iter().map(|s| s.1.clone()).collect();
+            let (now, utc_offset) = gtk4::glib::DateTime::now_local()
+                .map(|dt| (dt.to_unix(), dt.utc_offset().as_seconds()))
+                .unwrap_or((0, 0));
+            let (sender, receiver) = std::sync::mpsc::channel();
+
+            std::thread::spawn(move || {
+                let counts = index::count_saved_searches(&queries, now, utc_offset);
+                let _ = sender.send((queries, counts));
+            });
+
+            let saved_recv = saved.clone();
+            let busy_recv = busy.clone();
+            gtk4::glib::timeout_add_local(std::time::Duration::from_millis(100), move || {
+                match receiver.try_recv() {
+                    Ok((queries, counts)) => {
+                        for (_, query, count_label) in saved_recv.borrow().iter() {
+                            if let Some(pos) = queries.iter().position(|q| q == query) {
+                                count_label.set_label(&counts[pos].to_string());
+                            }
+                        }
+                        busy_recv.set(false);
+                        gtk4::glib::ControlFlow::Break
+                    }
+                    Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                        busy_recv.set(false);
+                        gtk4::glib::ControlFlow::Break
+                    }
+                    Err(std::sync::mpsc::TryRecvError::Empty) => gtk4::glib::ControlFlow::Continue,
+                }
+            });
+        })
+    };
+
+    let refresh_after_index = do_refresh_counts.clone();
+    gtk4::glib::timeout_add_local(
+        std::time::Duration::from_millis(500),
+        move || match index_done_rx.try_recv() {
+            Err(std::sync::mpsc::TryRecvError::Empty) => gtk4::glib::ControlFlow::Continue,
+            _ => {
+                refresh_after_index();
+                gtk4::glib::ControlFlow::Break
+            }
+        },
+    );
+
+    // Neue Mails (mbsync) und Lesestatus ändern die Zahlen auch ohne eigenes Zutun
+    let refresh_periodic = do_refresh_counts.clone();
+    gtk4::glib::timeout_add_seconds_local(60, move || {
+        refresh_periodic();
+        gtk4::glib::ControlFlow::Continue
+    });
+
     let folder_scroll = ScrolledWindow::builder()
         .child(&folder_list)
         .hscrollbar_policy(gtk4::PolicyType::Never)
@@ -163,6 +302,7 @@ fn build_ui(app: &Application) {
 
     let mail_list = ListBox::builder()
         .selection_mode(SelectionMode::Multiple)
+        .activate_on_single_click(false)
         .build();
 
     // NEU: Klick-Verhalten fixen (Nur bei STRG/SHIFT mehrfach markieren)
@@ -234,7 +374,20 @@ fn build_ui(app: &Application) {
         .build();
     viewer_header_box.append(&btn_unsubscribe);
 
+    // Anhänge der gewählten Mail (o: öffnen, s: speichern, S: alle speichern)
+    let attachment_list = ListBox::builder()
+        .selection_mode(SelectionMode::Single)
+        .visible(false)
+        .margin_top(5)
+        .build();
+    viewer_header_box.append(&attachment_list);
+    let current_attachments = Rc::new(RefCell::new(Vec::<attachments::Attachment>::new()));
+
     let text_buffer = gtk4::TextBuffer::new(None);
+    text_buffer.create_tag(
+        Some("conv-header"),
+        &[("weight", &700i32), ("pixels-above-lines", &6i32)],
+    );
     let mail_viewer = TextView::builder()
         .buffer(&text_buffer)
         .editable(false)
@@ -260,8 +413,19 @@ fn build_ui(app: &Application) {
     let sort_state = Rc::new(RefCell::new((SortCol::Date, true)));
     let selected_mail = Rc::new(RefCell::new(None::<MailEntry>));
     let current_search_query = Rc::new(RefCell::new(String::new()));
-
-    let (status_box, status_label) = status::build_status_bar();
+    let compiled_search = Rc::new(RefCell::new(Ok::<_, search::ParseError>(
+        search::CompiledSearch::empty(),
+    )));
+    // Volltext-Modus: current_mail_entries enthält Treffer aus allen Ordnern (nach Relevanz)
+    let fulltext_active = Rc::new(Cell::new(false));
+    let current_folder = Rc::new(RefCell::new(None::<FolderRef>));
+    let pending_selection = Rc::new(RefCell::new(None::<PathBuf>));
+    // Konversationsansicht: pro angezeigter Zeile der zugehörige Thread
+    let thread_mode = Rc::new(Cell::new(false));
+    let collapsed_threads = Rc::new(RefCell::new(HashSet::<String>::new()));
+    let displayed_threads = Rc::new(RefCell::new(Vec::<ThreadRow>::new()));
+
+    let (status_box, status_label, undo_send_button) = status::build_status_bar();
     let status_label_rc = Rc::new(status_label);
 
     let do_sort_and_render = {
@@ -269,29 +433,88 @@ fn build_ui(app: &Application) {
         let all_entries = current_mail_entries.clone();
         let disp_entries = displayed_mail_entries.clone();
         let state = sort_state.clone();
-        let search_query = current_search_query.clone();
+        let compiled = compiled_search.clone();
         let status_lbl = status_label_rc.clone();
+        let fulltext = fulltext_active.clone();
+        let current_folder = current_folder.clone();
+        let threaded = thread_mode.clone();
+        let collapsed = collapsed_threads.clone();
+        let disp_threads = displayed_threads.clone();
 
         Rc::new(move || {
-            let query = search_query.borrow().clone();
+            // Ordner-Spalte, wenn die Liste Mails aus mehreren Ordnern enthält
+            let multi_folder =
+                fulltext.get() || matches!(*current_folder.borrow(), Some(FolderRef::Saved { .. }));
 
-            let mut display_list = perform_search(&all_entries.borrow(), &query);
+            let mut display_list = if fulltext.get() {
+                // Treffer der Volltextsuche bleiben in Relevanz-Reihenfolge
+                all_entries.borrow().clone()
+            } else {
+                perform_search(&all_entries.borrow(), &compiled.borrow())
+            };
+
+            if !fulltext.get() {
+                let (col, desc) = *state.borrow();
+                display_list.sort_by(|a, b| {
+                    let cmp = match col {
+                        SortCol::Date => a.timestamp.cmp(&b.timestamp),
+                        SortCol::Sender => a.from.to_lowercase().cmp(&b.from.to_lowercase()),
+                        SortCol::Subject => a.subject.to_lowercase().cmp(&b.subject.to_lowercase()),
+                    };
+                    if desc { cmp.reverse() } else { cmp }
+                });
+            }
 
-            let (col, desc) = *state.borrow();
-            display_list.sort_by(|a, b| {
-                let cmp = match col {
-                    SortCol::Date => a.timestamp.cmp(&b.timestamp),
-                    SortCol::Sender => a.from.to_lowercase().cmp(&b.from.to_lowercase()),
-                    SortCol::Subject => a.subject.to_lowercase().cmp(&b.subject.to_lowercase()),
-                };
-                if desc { cmp.reverse() } else { cmp }
-            });
+            // Konversationsansicht: Threads in der Reihenfolge ihrer ersten Mail in der Liste
+            let mut thread_rows = Vec::new();
+            if threaded.get() {
+                let all_ref = all_entries.borrow();
+                let positions: HashMap<&PathBuf, usize> = all_ref
+                    .iter()
+                    .enumerate()
+                    .map(|(i, e)| (&e.path, i))
+                    .collect();
+                let collapsed_ref = collapsed.borrow();
+                let mut threaded_list = Vec::new();
+
+                for thread in threading::build_threads(&display_list) {
+                    let head = &display_list[thread.messages[0].0];
+                    let key = if head.message_id.is_empty() {
+                        head.path.to_string_lossy().to_string()
+                    } else {
+                        head.message_id.clone()
+                    };
+
+                    let mut members: Vec<usize> = thread
+                        .messages
+                        .iter()
+                        .filter_map(|(i, _)| positions.get(&display_list[*i].path).copied())
+                        .collect();
+                    members.sort_by_key(|&i| all_ref[i].timestamp);
+                    let members = Rc::new(members);
+
+                    let visible = if collapsed_ref.contains(&key) {
+                        &thread.messages[..1]
+                    } else {
+                        &thread.messages[..]
+                    };
+                    for &(i, depth) in visible {
+                        threaded_list.push(display_list[i].clone());
+                        thread_rows.push(ThreadRow {
+                            key: key.clone(),
+                            members: members.clone(),
+                            depth,
+                        });
+                    }
+                }
+                display_list = threaded_list;
+            }
 
             while let Some(child) = list_box.first_child() {
                 list_box.remove(&child);
             }
 
-            for entry in display_list.iter() {
+            for (row_idx, entry) in display_list.iter().enumerate() {
                 let hbox = gtk4::Box::builder()
                     .orientation(Orientation::Horizontal)
                     .spacing(10)
@@ -330,15 +553,61 @@ fn build_ui(app: &Application) {
 
                 hbox.append(&lbl_date);
                 hbox.append(&lbl_from);
+                if multi_folder {
+                    let lbl_folder = Label::builder()
+                        .label(&entry.folder)
+                        .xalign(0.0)
+                        .css_classes(["dim-label"])
+                        .build();
+                    hbox.append(&lbl_folder);
+                }
+
+                if let Some(thread_row) = thread_rows.get(row_idx) {
+                    lbl_subj.set_margin_start(thread_row.depth as i32 * 16);
+
+                    // Erste Zeile eines Threads: Anzahl Mails und Klapp-Zustand
+                    let is_head = row_idx == 0 || thread_rows[row_idx - 1].key != thread_row.key;
+                    if is_head && thread_row.members.len() > 1 {
+                        let marker = if collapsed.borrow().contains(&thread_row.key) {
+                            "▸"
+                        } else {
+                            "▾"
+                        };
+                        let lbl_count = Label::builder()
+                            .label(format!("{} {}", marker, thread_row.members.len()))
+                            .xalign(0.0)
+                            .css_classes(["dim-label"])
+                            .build();
+                        hbox.append(&lbl_count);
+                    }
+                }
                 hbox.append(&lbl_subj);
                 list_box.append(&hbox);
             }
 
             *disp_entries.borrow_mut() = display_list;
+            *disp_threads.borrow_mut() = thread_rows;
+
+            if let Err(e) = &*compiled.borrow() {
+                status_lbl.set_label(&format!("Ungültige Suche: {}", e));
+                return;
+            }
+
+            if fulltext.get() {
+                status_lbl.set_label(&format!(
+                    "{} Treffer in allen Ordnern",
+                    disp_entries.borrow().len()
+                ));
+                return;
+            }
 
             let all_ref = all_entries.borrow();
             let total = all_ref.len();
             let unread = all_ref.iter().filter(|e| !e.is_read).count();
+            if let Some(FolderRef::Saved { name, .. }) = &*current_folder.borrow() {
+                status_lbl.set_label(&format!("{}: {} Mails, {} ungelesen", name, total, unread));
+                return;
+            }
             status_lbl.set_label(&format!("{} Mails, {} ungelesen", total, unread));
         })
     };
@@ -396,11 +665,86 @@ fn build_ui(app: &Application) {
     btn_reply.set_tooltip_text(Some("Antworten (Wähle eine Mail aus)"));
     header_bar.pack_start(&btn_reply);
 
+    // Allen antworten / an die Liste antworten: gleiche Sperre wie "Antworten"
+    let btn_reply_all = Button::from_icon_name("mail-reply-all-symbolic");
+    btn_reply_all.set_tooltip_text(Some("Allen antworten (R)"));
+    header_bar.pack_start(&btn_reply_all);
+
+    let btn_reply_list = Button::from_icon_name("system-users-symbolic");
+    btn_reply_list.set_tooltip_text(Some("An Mailingliste antworten (L)"));
+    header_bar.pack_start(&btn_reply_list);
+
+    let btn_forward = Button::from_icon_name("mail-forward-symbolic");
+    btn_forward.set_sensitive(false);
+    btn_forward.set_tooltip_text(Some("Weiterleiten (f, als Anhang: F)"));
+    header_bar.pack_start(&btn_forward);
+
+    for btn in [&btn_reply_all, &btn_reply_list] {
+        btn_reply
+            .bind_property("sensitive", btn, "sensitive")
+            .sync_create()
+            .build();
+    }
+
     let btn_archive = Button::from_icon_name("folder-symbolic");
     btn_archive.set_sensitive(false);
     btn_archive.set_tooltip_text(Some("Archivieren (a)"));
     header_bar.pack_start(&btn_archive);
 
+    let btn_threads = ToggleButton::builder()
+        .icon_name("format-indent-more-symbolic")
+        .tooltip_text("Konversationsansicht (t)")
+        .build();
+    header_bar.pack_start(&btn_threads);
+
+    let thread_mode_toggle = thread_mode.clone();
+    let render_threads = do_sort_and_render.clone();
+    btn_threads.connect_toggled(move |btn| {
+        thread_mode_toggle.set(btn.is_active());
+        render_threads();
+    });
+
+    // Thread der gewählten Zeile auf-/zuklappen (None = umschalten)
+    let do_toggle_thread = {
+        let list_box = mail_list.clone();
+        let disp_threads = displayed_threads.clone();
+        let collapsed = collapsed_threads.clone();
+        let render = do_sort_and_render.clone();
+
+        Rc::new(move |collapse: Option<bool>| {
+            let Some(row) = list_box.selected_rows().last().cloned() else {
+                return;
+            };
+            let Some(key) = disp_threads
+                .borrow()
+                .get(row.index() as usize)
+                .map(|t| t.key.clone())
+            else {
+                return;
+            };
+
+            let is_collapsed = collapsed.borrow().contains(&key);
+            let collapse = collapse.unwrap_or(!is_collapsed);
+            if collapse == is_collapsed {
+                return;
+            }
+            if collapse {
+                collapsed.borrow_mut().insert(key.clone());
+            } else {
+                collapsed.borrow_mut().remove(&key);
+            }
+            render();
+
+            // Auswahl bleibt auf der ersten Zeile des Threads
+            let pos = disp_threads.borrow().iter().position(|t| t.key == key);
+            if let Some(r) = pos.and_then(|i| list_box.row_at_index(i as i32)) {
+                list_box.unselect_all();
+                list_box.select_row(Some(&r));
+                r.grab_focus();
+            }
+        })
+    };
+
     let spinner = Spinner::builder().spinning(false).visible(false).build();
     header_bar.pack_end(&spinner);
 
@@ -410,6 +754,20 @@ fn build_ui(app: &Application) {
         .build();
     header_bar.pack_end(&search_entry);
 
+    let btn_search_all = ToggleButton::builder()
+        .icon_name("folder-saved-search-symbolic")
+        .tooltip_text("In allen Ordnern suchen (inkl. Mailtext)")
+        .visible(false)
+        .build();
+    header_bar.pack_end(&btn_search_all);
+
+    let btn_save_search = Button::builder()
+        .icon_name("document-save-symbolic")
+        .tooltip_text("Suche als virtuellen Ordner speichern")
+        .visible(false)
+        .build();
+    header_bar.pack_end(&btn_save_search);
+
     let btn_search = ToggleButton::builder()
         .icon_name("system-search-symbolic")
         .tooltip_text("Suchen")
@@ -420,13 +778,21 @@ fn build_ui(app: &Application) {
     btn_help.set_tooltip_text(Some("Hilfe & Shortcuts (?)")); // NEU
     header_bar.pack_end(&btn_help); // NEU
 
+    let btn_queue = Button::from_icon_name("mail-send-symbolic");
+    btn_queue.set_tooltip_text(Some("Postausgang (w)"));
+    header_bar.pack_end(&btn_queue);
+
     let btn_addressbook = Button::from_icon_name("avatar-default-symbolic");
     btn_addressbook.set_tooltip_text(Some("Adressbuch"));
     header_bar.pack_end(&btn_addressbook);
 
     let search_entry_clone = search_entry.clone();
+    let btn_search_all_clone = btn_search_all.clone();
+    let btn_save_search_clone = btn_save_search.clone();
     btn_search.connect_toggled(move |btn| {
         search_entry_clone.set_visible(btn.is_active());
+        btn_search_all_clone.set_visible(btn.is_active());
+        btn_save_search_clone.set_visible(btn.is_active());
         if btn.is_active() {
             search_entry_clone.grab_focus();
         } else {
@@ -441,208 +807,548 @@ fn build_ui(app: &Application) {
         mail_list_focus.grab_focus();
     });
 
-    let q_clone = current_search_query.clone();
-    let render_s_clone = do_sort_and_render.clone();
-    search_entry.connect_search_changed(move |entry| {
-        *q_clone.borrow_mut() = entry.text().to_string();
-        render_s_clone();
-    });
-
-    let app_clone_ab = app.clone();
-    btn_addressbook.connect_clicked(move |_| {
-        addressbook::open_addressbook_window(&app_clone_ab);
-    });
-
-    // NEU: Hilfe Klick Event
-    let app_clone_help = app.clone();
-    btn_help.connect_clicked(move |_| {
-        help::show_help_window(&app_clone_help);
-    });
-
-    let folders_clone = folders.clone();
-    let entries_clone = current_mail_entries.clone();
-    let render_clone = do_sort_and_render.clone();
-    let text_buffer_clone = text_buffer.clone();
-    let btn_reply_clone1 = btn_reply.clone();
-    let btn_archive_clone1 = btn_archive.clone();
-    let spinner_clone = spinner.clone();
-    let btn_search_reset = btn_search.clone();
-    let search_entry_reset = search_entry.clone();
-
-    folder_list.connect_row_activated(move |_, row| {
-        let idx = row.index() as usize;
-        if let Some(folder_name) = folders_clone.get(idx).cloned() {
-            text_buffer_clone.set_text("");
-            entries_clone.borrow_mut().clear();
-            btn_reply_clone1.set_sensitive(false);
-            btn_archive_clone1.set_sensitive(false);
+    let do_load_folder = {
+        let entries = current_mail_entries.clone();
+        let render = do_sort_and_render.clone();
+        let text_buf = text_buffer.clone();
+        let btn_reply_state = btn_reply.clone();
+        let btn_archive_state = btn_archive.clone();
+        let spinner = spinner.clone();
+        let current_folder = current_folder.clone();
+        let pending_selection = pending_selection.clone();
+        let list_box = mail_list.clone();
+        let disp_entries = displayed_mail_entries.clone();
+        let refresh_counts = do_refresh_counts.clone();
 
-            btn_search_reset.set_active(false);
-            search_entry_reset.set_text("");
+        Rc::new(move |folder: FolderRef| {
+            *current_folder.borrow_mut() = Some(folder.clone());
+            text_buf.set_text("");
+            entries.borrow_mut().clear();
+            btn_reply_state.set_sensitive(false);
+            btn_archive_state.set_sensitive(false);
 
-            render_clone();
-            spinner_clone.set_spinning(true);
-            spinner_clone.set_visible(true);
+            render();
+            spinner.set_spinning(true);
+            spinner.set_visible(true);
 
             let (sender, receiver) = std::sync::mpsc::channel();
 
+            let (now, utc_offset) = gtk4::glib::DateTime::now_local()
+                .map(|dt| (dt.to_unix(), dt.utc_offset().as_seconds()))
+                .unwrap_or((0, 0));
+
             std::thread::spawn(move || {
-                let maildir_path = if folder_name == "INBOX"
-                    && dirs::home_dir().unwrap().join(".Mail/cur").exists()
-                {
-                    dirs::home_dir().unwrap().join(".Mail")
-                } else {
-                    dirs::home_dir().unwrap().join(".Mail").join(&folder_name)
+                let new_entries = match folder {
+                    FolderRef::Maildir(folder_name) => index::load_folder(&folder_name),
+                    FolderRef::Saved { query, .. } => {
+                        index::load_saved_search(&query, now, utc_offset)
+                    }
                 };
+                let _ = sender.send(new_entries);
+            });
+
+            let entries_recv = entries.clone();
+            let render_recv = render.clone();
+            let spinner_recv = spinner.clone();
+            let pending_recv = pending_selection.clone();
+            let list_box_recv = list_box.clone();
+            let disp_recv = disp_entries.clone();
+            let refresh_recv = refresh_counts.clone();
 
-                let md = maildir::Maildir::from(maildir_path);
-                let mut new_entries = Vec::new();
-                let mut db_contacts: std::collections::HashMap<String, (String, Option<String>)> =
-                    std::collections::HashMap::new();
-
-                let mut verified_senders = std::collections::HashSet::new();
-                if let Ok(conn) = rusqlite::Connection::open(db::db_path()) {
-                    if let Ok(mut stmt) =
-                        conn.prepare("SELECT email FROM contacts WHERE is_verified = 1")
-                    {
-                        if let Ok(rows) = stmt.query_map([], |row| row.get::<_, String>(0)) {
-                            for email in rows.flatten() {
-                                verified_senders.insert(email);
+            gtk4::glib::timeout_add_local(std::time::Duration::from_millis(50), move || {
+                match receiver.try_recv() {
+                    Ok(new_entries) => {
+                        *entries_recv.borrow_mut() = new_entries;
+                        render_recv();
+                        spinner_recv.set_spinning(false);
+                        spinner_recv.set_visible(false);
+                        // Beim Laden neu indexierte Mails können virtuelle Ordner betreffen
+                        refresh_recv();
+
+                        // Sprungziel aus der Volltextsuche markieren
+                        if let Some(path) = pending_recv.borrow_mut().take() {
+                            let pos = disp_recv.borrow().iter().position(|e| e.path == path);
+                            if let Some(row) =
+                                pos.and_then(|i| list_box_recv.row_at_index(i as i32))
+                            {
+                                list_box_recv.unselect_all();
+                                list_box_recv.select_row(Some(&row));
+                                row.grab_focus();
                             }
                         }
+                        gtk4::glib::ControlFlow::Break
+                    }
+                    Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                        spinner_recv.set_spinning(false);
+                        spinner_recv.set_visible(false);
+                        gtk4::glib::ControlFlow::Break
                     }
+                    Err(std::sync::mpsc::TryRecvError::Empty) => gtk4::glib::ControlFlow::Continue,
+                }
+            });
+        })
+    };
+
+    // Suche über alle Ordner: Index laden und Volltext abfragen dauert, deshalb in einem
+    // Thread und erst, wenn 300 ms nicht mehr getippt wurde. Ergebnisse überholter
+    // Anfragen werden verworfen.
+    let search_generation = Rc::new(Cell::new(0u64));
+    let do_search = {
+        let all_entries = current_mail_entries.clone();
+        let search_query = current_search_query.clone();
+        let compiled_state = compiled_search.clone();
+        let fulltext = fulltext_active.clone();
+        let render = do_sort_and_render.clone();
+        let btn_all = btn_search_all.clone();
+        let current_folder = current_folder.clone();
+        let load_folder = do_load_folder.clone();
+        let spinner = spinner.clone();
+        let generation = search_generation.clone();
+
+        // Ergebnis anzeigen: Some = Treffer aus allen Ordnern, None = aktueller Ordner
+        type SearchResult =
+            Result<(search::CompiledSearch, Option<Vec<MailEntry>>), search::ParseError>;
+        let show: Rc<dyn Fn(SearchResult)> = Rc::new(move |result: SearchResult| {
+            let (compiled, results) = match result {
+                Ok(r) => r,
+                Err(e) => {
+                    *compiled_state.borrow_mut() = Err(e);
+                    render();
+                    return;
+                }
+            };
+
+            if let Some(results) = results {
+                *all_entries.borrow_mut() = results;
+                *compiled_state.borrow_mut() = Ok(compiled);
+                fulltext.set(true);
+                render();
+                return;
+            }
+
+            *compiled_state.borrow_mut() = Ok(compiled);
+            if fulltext.get() {
+                // Volltext verlassen: zuletzt geöffneten Ordner wieder laden
+                fulltext.set(false);
+                if let Some(folder) = current_folder.borrow().clone() {
+                    load_folder(folder);
+                } else {
+                    all_entries.borrow_mut().clear();
+                    render();
                 }
+            } else {
+                render();
+            }
+        });
 
-                for entry in md.list_new().chain(md.list_cur()) {
-                    if let Ok(mail) = entry {
-                        let path = mail.path().to_path_buf();
-
-                        let path_str = path.to_string_lossy();
-                        let is_read = path_str.contains(":2,") && path_str.contains('S');
-
-                        if let Ok(data) = std::fs::read(&path) {
-                            if let Ok(parsed) = mailparse::parse_mail(&data) {
-                                let headers = parsed.get_headers();
-                                let subject = headers
-                                    .get_first_value("Subject")
-                                    .unwrap_or_else(|| "Kein Betreff".to_string());
-                                let from = headers
-                                    .get_first_value("From")
-                                    .unwrap_or_else(|| "Unbekannt".to_string());
-                                let return_path_raw = headers
-                                    .get_first_value("Return-Path")
-                                    .unwrap_or_else(|| "".to_string());
-                                let date_str = headers.get_first_value("Date").unwrap_or_default();
-                                let timestamp = mailparse::dateparse(&date_str).unwrap_or(0);
-
-                                let mut pub_key = None;
-                                if let Some(ac_val) = headers.get_first_value("Autocrypt") {
-                                    if let Some(idx) = ac_val.find("keydata=") {
-                                        pub_key = Some(ac_val[idx + 8..].trim().to_string());
-                                    }
-                                }
-
-                                let mut list_unsubscribe = None;
-                                if let Some(lu_val) = headers.get_first_value("List-Unsubscribe") {
-                                    if let Some(start) = lu_val.find('<') {
-                                        if let Some(end) = lu_val[start..].find('>') {
-                                            list_unsubscribe =
-                                                Some(lu_val[start + 1..start + end].to_string());
-                                        }
-                                    }
-                                }
-
-                                let date_short = gtk4::glib::DateTime::from_unix_local(timestamp)
-                                    .map(|dt| {
-                                        dt.format("%d.%m.%y %H:%M")
-                                            .unwrap_or(date_str.clone().into())
-                                            .to_string()
-                                    })
-                                    .unwrap_or_else(|_| date_str.clone());
-
-                                let (name, email) = db::parse_from(&from);
-
-                                let mut return_path_clean =
-                                    return_path_raw.replace(['<', '>'], "").trim().to_string();
-                                if return_path_clean.is_empty() {
-                                    return_path_clean = email.clone();
-                                }
-
-                                if folder_name == "INBOX"
-                                    && !verified_senders.contains(&return_path_clean)
-                                {
-                                    if move_mail_file(&path, "Quarantäne").is_some() {
-                                        continue;
-                                    }
-                                }
-
-                                if !email.is_empty() {
-                                    let entry = db_contacts
-                                        .entry(email)
-                                        .or_insert_with(|| (name.clone(), pub_key.clone()));
-                                    if entry.0.is_empty() && !name.is_empty() {
-                                        entry.0 = name;
-                                    }
-                                    if entry.1.is_none() && pub_key.is_some() {
-                                        entry.1 = pub_key;
-                                    }
-                                }
-
-                                new_entries.push(MailEntry {
-                                    path,
-                                    timestamp,
-                                    date_short,
-                                    date_full: date_str,
-                                    from,
-                                    return_path: return_path_clean,
-                                    subject,
-                                    is_read,
-                                    list_unsubscribe,
-                                });
+        Rc::new(move || {
+            let query = search_query.borrow().clone();
+            let (now, utc_offset) = gtk4::glib::DateTime::now_local()
+                .map(|dt| (dt.to_unix(), dt.utc_offset().as_seconds()))
+                .unwrap_or((0, 0));
+            let all_folders = btn_all.is_active();
+            let this_search = generation.get() + 1;
+            generation.set(this_search);
+
+            if !all_folders {
+                show(
+                    search::CompiledSearch::compile(&query, false, now, utc_offset)
+                        .map(|c| (c, None)),
+                );
+                return;
+            }
+
+            let generation = generation.clone();
+            let show = show.clone();
+            let spinner = spinner.clone();
+            gtk4::glib::timeout_add_local_once(std::time::Duration::from_millis(300), move || {
+                if generation.get() != this_search {
+                    return;
+                }
+                spinner.set_spinning(true);
+                spinner.set_visible(true);
+
+                let (sender, receiver) = std::sync::mpsc::channel();
+                std::thread::spawn(move || {
+                    let result = search::CompiledSearch::compile(&query, true, now, utc_offset)
+                        .map(|compiled| {
+                            if compiled.is_empty() {
+                                return (compiled, None);
                             }
+                            let mut results = compiled.filter(&index::load_all_folders());
+                            results.sort_by(|a, b| {
+                                let rank_a = compiled.rank(a).unwrap_or(usize::MAX);
+                                let rank_b = compiled.rank(b).unwrap_or(usize::MAX);
+                                rank_a.cmp(&rank_b).then(b.timestamp.cmp(&a.timestamp))
+                            });
+                            (compiled, Some(results))
+                        });
+                    let _ = sender.send(result);
+                });
+
+                gtk4::glib::timeout_add_local(std::time::Duration::from_millis(50), move || {
+                    match receiver.try_recv() {
+                        Ok(result) => {
+                            if generation.get() == this_search {
+                                spinner.set_spinning(false);
+                                spinner.set_visible(false);
+                                show(result);
+                            }
+                            gtk4::glib::ControlFlow::Break
+                        }
+                        Err(std::sync::mpsc::TryRecvError::Empty) => {
+                            gtk4::glib::ControlFlow::Continue
                         }
+                        Err(_) => gtk4::glib::ControlFlow::Break,
                     }
-                }
+                });
+            });
+        })
+    };
+
+    let q_clone = current_search_query.clone();
+    let search_changed_clone = do_search.clone();
+    search_entry.connect_search_changed(move |entry| {
+        *q_clone.borrow_mut() = entry.text().to_string();
+        search_changed_clone();
+    });
+
+    let search_all_clone = do_search.clone();
+    let search_entry_all = search_entry.clone();
+    btn_search_all.connect_toggled(move |_| {
+        search_all_clone();
+        search_entry_all.grab_focus();
+    });
+
+    let q_save = current_search_query.clone();
+    let saved_save = saved_searches.clone();
+    let folder_list_save = folder_list.clone();
+    let status_save = status_label_rc.clone();
+    let refresh_save = do_refresh_counts.clone();
+    btn_save_search.connect_clicked(move |btn| {
+        let query = q_save.borrow().trim().to_string();
+        if query.is_empty() {
+            status_save.set_label("Keine Suche zum Speichern");
+            return;
+        }
+        if let Err(e) = search::parse(&query) {
+            status_save.set_label(&format!("Ungültige Suche: {}", e));
+            return;
+        }
 
-                if let Err(e) = db::bulk_upsert(&db_contacts) {
-                    eprintln!("Fehler beim Speichern der Kontakte: {}", e);
+        let popover = gtk4::Popover::builder()
+            .position(gtk4::PositionType::Bottom)
+            .build();
+        popover.set_parent(btn);
+        popover.connect_closed(|p| p.unparent());
+
+        let name_entry = gtk4::Entry::builder()
+            .placeholder_text("Name des virtuellen Ordners")
+            .width_request(250)
+            .build();
+        popover.set_child(Some(&name_entry));
+
+        let popover_c = popover.clone();
+        let saved_c = saved_save.clone();
+        let folder_list_c = folder_list_save.clone();
+        let status_c = status_save.clone();
+        let refresh_c = refresh_save.clone();
+        name_entry.connect_activate(move |e| {
+            let name = e.text().trim().to_string();
+            if name.is_empty() {
+                return;
+            }
+            match db::save_search(&name, &query) {
+                Ok(()) => {
+                    let mut saved = saved_c.borrow_mut();
+                    if let Some(existing) = saved.iter_mut().find(|s| s.0 == name) {
+                        existing.1 = query.clone();
+                    } else {
+                        let (row_box, count_label) = build_saved_search_row(&name);
+                        folder_list_c.append(&row_box);
+                        saved.push((name.clone(), query.clone(), count_label));
+                    }
+                    drop(saved);
+                    status_c.set_label(&format!("Suche als '{}' gespeichert", name));
+                    refresh_c();
                 }
+                Err(e) => status_c.set_label(&format!("Fehler beim Speichern der Suche: {}", e)),
+            }
+            popover_c.popdown();
+        });
 
-                let _ = sender.send(new_entries);
-            });
+        popover.popup();
+        name_entry.grab_focus();
+    });
 
-            let entries_recv = entries_clone.clone();
-            let render_recv = render_clone.clone();
-            let spinner_recv = spinner_clone.clone();
+    let app_clone_ab = app.clone();
+    btn_addressbook.connect_clicked(move |_| {
+        addressbook::open_addressbook_window(&app_clone_ab);
+    });
 
-            gtk4::glib::timeout_add_local(std::time::Duration::from_millis(50), move || {
+    let app_clone_queue = app.clone();
+    btn_queue.connect_clicked(move |_| {
+        queue::open_queue_window(&app_clone_queue);
+    });
+
+    // Versand im Hintergrund: fällige Mails aus Outbox/new an den Sendmail-Befehl.
+    // Der Composer löst ihn über die Aktion "app.send-queue" direkt nach dem Ablegen aus.
+    let sending = Rc::new(Cell::new(false));
+    let do_send_queue = {
+        let sending = sending.clone();
+        let status_lbl = status_label_rc.clone();
+        Rc::new(move || {
+            if sending.get() {
+                return;
+            }
+            sending.set(true);
+
+            let (sender, receiver) = std::sync::mpsc::channel();
+            std::thread::spawn(move || {
+                let _ = sender.send(outbox::send_due_mails());
+            });
+
+            let sending_recv = sending.clone();
+            let status_recv = status_lbl.clone();
+            gtk4::glib::timeout_add_local(std::time::Duration::from_millis(200), move || {
                 match receiver.try_recv() {
-                    Ok(new_entries) => {
-                        *entries_recv.borrow_mut() = new_entries;
-                        render_recv();
-                        spinner_recv.set_spinning(false);
-                        spinner_recv.set_visible(false);
+                    Ok(report) => {
+                        if let Some((subject, error)) = report.errors.first() {
+                            status_recv.set_label(&format!(
+                                "Versand fehlgeschlagen ({} Mail(s)): {}: {} – Postausgang mit w",
+                                report.errors.len(),
+                                subject,
+                                error
+                            ));
+                        } else if report.sent > 0 {
+                            status_recv.set_label(&format!("{} Mail(s) gesendet", report.sent));
+                        }
+                        sending_recv.set(false);
                         gtk4::glib::ControlFlow::Break
                     }
                     Err(std::sync::mpsc::TryRecvError::Disconnected) => {
-                        spinner_recv.set_spinning(false);
-                        spinner_recv.set_visible(false);
+                        sending_recv.set(false);
                         gtk4::glib::ControlFlow::Break
                     }
                     Err(std::sync::mpsc::TryRecvError::Empty) => gtk4::glib::ControlFlow::Continue,
                 }
             });
+        })
+    };
+
+    let send_queue_action = gtk4::gio::SimpleAction::new("send-queue", None);
+    let send_queue_activate = do_send_queue.clone();
+    send_queue_action.connect_activate(move |_, _| send_queue_activate());
+    app.add_action(&send_queue_action);
+
+    // Nach dem Senden: bis zum Ablauf der Frist holt "Rückgängig" die Mail zurück in den
+    // Composer, danach wird sie sofort verschickt. Geplante Mails bekommen nur einen Hinweis.
+    // Jede Mail in der Frist hat einen eigenen Eintrag (Name, Timer); "Rückgängig" holt die
+    // zuletzt gesendete zurück.
+    let undo_pending = Rc::new(RefCell::new(Vec::<(String, gtk4::glib::SourceId)>::new()));
+    let mail_queued_action = gtk4::gio::SimpleAction::new(
+        "mail-queued",
+        Some(gtk4::glib::VariantTy::new("(sx)").unwrap()),
+    );
+    let status_queued = status_label_rc.clone();
+    let undo_btn_queued = undo_send_button.clone();
+    let undo_pending_queued = undo_pending.clone();
+    let app_queued = app.clone();
+    mail_queued_action.connect_activate(move |_, param| {
+        let Some((name, send_at)) = param.and_then(|p| p.get::<(String, i64)>()) else {
+            return;
+        };
+        let delay = send_at - outbox::now_secs();
+        if delay > outbox::undo_delay() {
+            let when = gtk4::glib::DateTime::from_unix_local(send_at)
+                .and_then(|dt| dt.format("%d.%m.%Y %H:%M"))
+                .map(|s| s.to_string())
+                .unwrap_or_default();
+            status_queued.set_label(&format!("Versand geplant für {} (Postausgang mit w)", when));
+            return;
+        }
+
+        status_queued.set_label(&format!("Mail wird in {} s gesendet", delay.max(0)));
+        undo_btn_queued.set_visible(true);
+
+        let undo_btn_timeout = undo_btn_queued.clone();
+        let pending_timeout = undo_pending_queued.clone();
+        let app_timeout = app_queued.clone();
+        let name_timeout = name.clone();
+        let source =
+            gtk4::glib::timeout_add_seconds_local_once(delay.max(0) as u32 + 1, move || {
+                let mut pending = pending_timeout.borrow_mut();
+                pending.retain(|(n, _)| *n != name_timeout);
+                undo_btn_timeout.set_visible(!pending.is_empty());
+                drop(pending);
+                app_timeout.activate_action("send-queue", None);
+            });
+        undo_pending_queued.borrow_mut().push((name, source));
+    });
+    app.add_action(&mail_queued_action);
+
+    let status_undo = status_label_rc.clone();
+    let app_undo = app.clone();
+    undo_send_button.connect_clicked(move |btn| {
+        let Some((name, source)) = undo_pending.borrow_mut().pop() else {
+            return;
+        };
+        source.remove();
+        btn.set_visible(!undo_pending.borrow().is_empty());
+        match outbox::take_back(&name) {
+            Ok(draft) => {
+                status_undo.set_label("Versand abgebrochen");
+                composer::open_composer_window(&app_undo, draft);
+            }
+            Err(e) => status_undo.set_label(&format!("Rückgängig nicht möglich: {}", e)),
         }
     });
 
+    // Wiederholungen nach Fehlschlägen und Mails, die ein anderes Programm abgelegt hat
+    let send_queue_periodic = do_send_queue.clone();
+    gtk4::glib::timeout_add_seconds_local(30, move || {
+        if outbox::has_due_mails() {
+            send_queue_periodic();
+        }
+        gtk4::glib::ControlFlow::Continue
+    });
+    if outbox::has_due_mails() {
+        do_send_queue();
+    }
+
+    // NEU: Hilfe Klick Event
+    let app_clone_help = app.clone();
+    btn_help.connect_clicked(move |_| {
+        help::show_help_window(&app_clone_help);
+    });
+
+    let folders_clone = folders.clone();
+    let saved_clone = saved_searches.clone();
+    let load_folder_clone = do_load_folder.clone();
+    let fulltext_reset = fulltext_active.clone();
+    let btn_search_reset = btn_search.clone();
+    let search_entry_reset = search_entry.clone();
+
+    folder_list.connect_row_activated(move |_, row| {
+        let idx = row.index() as usize;
+        let folder = if let Some(folder_name) = folders_clone.get(idx) {
+            FolderRef::Maildir(folder_name.clone())
+        } else if let Some((name, query, _)) = saved_clone.borrow().get(idx - folders_clone.len()) {
+            FolderRef::Saved {
+                name: name.clone(),
+                query: query.clone(),
+            }
+        } else {
+            return;
+        };
+
+        fulltext_reset.set(false);
+        btn_search_reset.set_active(false);
+        search_entry_reset.set_text("");
+
+        load_folder_clone(folder);
+    });
+
+    // Entf auf einem virtuellen Ordner löscht die gespeicherte Suche
+    let folder_key_ctrl = gtk4::EventControllerKey::new();
+    let folders_del = folders.clone();
+    let saved_del = saved_searches.clone();
+    let folder_list_del = folder_list.clone();
+    let status_del = status_label_rc.clone();
+    folder_key_ctrl.connect_key_pressed(move |_, keyval, _, _| {
+        if keyval != gdk::Key::Delete {
+            return gtk4::glib::Propagation::Proceed;
+        }
+        let Some(row) = folder_list_del.selected_row() else {
+            return gtk4::glib::Propagation::Proceed;
+        };
+        let idx = row.index() as usize;
+        if idx < folders_del.len() {
+            return gtk4::glib::Propagation::Proceed;
+        }
+
+        let saved_idx = idx - folders_del.len();
+        let name = saved_del.borrow().get(saved_idx).map(|s| s.0.clone());
+        if let Some(name) = name {
+            if let Err(e) = db::delete_saved_search(&name) {
+                status_del.set_label(&format!("Fehler beim Löschen der Suche: {}", e));
+            } else {
+                saved_del.borrow_mut().remove(saved_idx);
+                folder_list_del.remove(&row);
+                status_del.set_label(&format!("Gespeicherte Suche '{}' gelöscht", name));
+            }
+        }
+        gtk4::glib::Propagation::Stop
+    });
+    folder_list.add_controller(folder_key_ctrl);
+
+    // Treffer der Volltextsuche oder eines virtuellen Ordners aktivieren (Enter/Doppelklick):
+    // in den echten Ordner springen. Im Ordner Drafts wird der Entwurf im Composer geöffnet.
+    let folders_jump = folders.clone();
+    let folder_list_jump = folder_list.clone();
+    let disp_jump = displayed_mail_entries.clone();
+    let fulltext_jump = fulltext_active.clone();
+    let current_folder_jump = current_folder.clone();
+    let pending_jump = pending_selection.clone();
+    let btn_search_jump = btn_search.clone();
+    let load_folder_jump = do_load_folder.clone();
+    let thread_mode_jump = thread_mode.clone();
+    let toggle_thread_jump = do_toggle_thread.clone();
+    let app_draft = app.clone();
+    let status_draft = status_label_rc.clone();
+    mail_list.connect_row_activated(move |_, row| {
+        let in_saved_search =
+            matches!(*current_folder_jump.borrow(), Some(FolderRef::Saved { .. }));
+        let in_drafts = matches!(
+            &*current_folder_jump.borrow(),
+            Some(FolderRef::Maildir(name)) if name == "Drafts"
+        );
+        if !fulltext_jump.get() && in_drafts {
+            let Some(entry) = disp_jump.borrow().get(row.index() as usize).cloned() else {
+                return;
+            };
+            if let Err(e) = composer::open_draft_window(&app_draft, &entry.path) {
+                status_draft.set_label(&format!("Entwurf konnte nicht geöffnet werden: {}", e));
+            }
+            return;
+        }
+        if !fulltext_jump.get() && !in_saved_search {
+            // Im normalen Ordner klappt Enter/Doppelklick den Thread auf bzw. zu
+            if thread_mode_jump.get() {
+                toggle_thread_jump(None);
+            }
+            return;
+        }
+        let Some(entry) = disp_jump.borrow().get(row.index() as usize).cloned() else {
+            return;
+        };
+
+        fulltext_jump.set(false);
+        btn_search_jump.set_active(false);
+
+        if let Some(idx) = folders_jump.iter().position(|f| *f == entry.folder)
+            && let Some(folder_row) = folder_list_jump.row_at_index(idx as i32)
+        {
+            folder_list_jump.select_row(Some(&folder_row));
+        }
+        *pending_jump.borrow_mut() = Some(entry.path);
+        load_folder_jump(FolderRef::Maildir(entry.folder));
+    });
+
     let entries_clone2 = displayed_mail_entries.clone();
     let current_entries_for_read = current_mail_entries.clone();
     let text_buffer_clone2 = text_buffer.clone();
+    let mail_viewer_conv = mail_viewer.clone();
+    let thread_mode_read = thread_mode.clone();
+    let threads_read = displayed_threads.clone();
+    let attachment_list_read = attachment_list.clone();
+    let attachments_read = current_attachments.clone();
     let lbl_subj_clone = lbl_viewer_subj.clone();
     let lbl_date_clone = lbl_viewer_date.clone();
     let lbl_return_clone = lbl_viewer_return.clone();
     let btn_reply_clone2 = btn_reply.clone();
     let btn_archive_clone2 = btn_archive.clone();
+    let btn_forward_clone2 = btn_forward.clone();
     let selected_mail_clone = selected_mail.clone();
     let btn_unsubscribe_clone = btn_unsubscribe.clone();
     let status_lbl_read = status_label_rc.clone();
@@ -670,6 +1376,7 @@ fn build_ui(app: &Application) {
                 }
 
                 *selected_mail_clone.borrow_mut() = Some(entry.clone());
+                btn_forward_clone2.set_sensitive(true);
 
                 let now = SystemTime::now()
                     .duration_since(UNIX_EPOCH)
@@ -680,7 +1387,7 @@ fn build_ui(app: &Application) {
 
                 if age_secs >= required_age {
                     btn_reply_clone2.set_sensitive(true);
-                    btn_reply_clone2.set_tooltip_text(Some("Antworten"));
+                    btn_reply_clone2.set_tooltip_text(Some("Antworten (r)"));
                 } else {
                     btn_reply_clone2.set_sensitive(false);
                     let hours_left = 24 - (age_secs / 3600);
@@ -716,6 +1423,7 @@ fn build_ui(app: &Application) {
                         if let Err(e) = std::fs::rename(&old_path, &new_path) {
                             eprintln!("Konnte Mail nicht als gelesen markieren: {}", e);
                         } else {
+                            let _ = db::rename_message(&old_path, &new_path);
                             entry.path = new_path.clone();
 
                             if let Some(global_entry) = current_entries_for_read
@@ -750,10 +1458,37 @@ fn build_ui(app: &Application) {
             }
 
             if let Some(path) = file_path_to_read {
-                if let Ok(data) = std::fs::read(&path) {
-                    if let Ok(parsed) = mailparse::parse_mail(&data) {
-                        let body = extract_best_body(&parsed);
-                        text_buffer_clone2.set_text(&body);
+                let members = if thread_mode_read.get() {
+                    threads_read.borrow().get(idx).map(|t| t.members.clone())
+                } else {
+                    None
+                };
+
+                let data = std::fs::read(&path).unwrap_or_default();
+                let parsed = mailparse::parse_mail(&data).ok();
+
+                let found = parsed
+                    .as_ref()
+                    .map(attachments::collect_attachments)
+                    .unwrap_or_default();
+                attachments::fill_attachment_list(&attachment_list_read, &found);
+                *attachments_read.borrow_mut() = found;
+
+                match members {
+                    Some(members) if members.len() > 1 => show_conversation(
+                        &text_buffer_clone2,
+                        &mail_viewer_conv,
+                        &current_entries_for_read.borrow(),
+                        &members,
+                        &path,
+                    ),
+                    _ => {
+                        clear_message_marks(&text_buffer_clone2);
+                        if let Some(parsed) = &parsed {
+                            let body = extract_best_body(parsed);
+                            text_buffer_clone2.set_text(&body);
+                            quote::apply_quote_tags(&text_buffer_clone2);
+                        }
                     }
                 }
             }
@@ -771,6 +1506,9 @@ fn build_ui(app: &Application) {
             btn_unsubscribe_clone.set_visible(false);
             btn_reply_clone2.set_sensitive(false);
             btn_archive_clone2.set_sensitive(true);
+            btn_forward_clone2.set_sensitive(false);
+            attachments::fill_attachment_list(&attachment_list_read, &[]);
+            attachments_read.borrow_mut().clear();
             *selected_mail_clone.borrow_mut() = None;
         } else {
             // Nichts ausgewählt
@@ -781,13 +1519,109 @@ fn build_ui(app: &Application) {
             btn_unsubscribe_clone.set_visible(false);
             btn_reply_clone2.set_sensitive(false);
             btn_archive_clone2.set_sensitive(false);
+            btn_forward_clone2.set_sensitive(false);
+            attachments::fill_attachment_list(&attachment_list_read, &[]);
+            attachments_read.borrow_mut().clear();
             *selected_mail_clone.borrow_mut() = None;
         }
     });
 
+    // Gewählter Anhang, sonst der erste
+    let do_open_attachment = {
+        let list = attachment_list.clone();
+        let atts = current_attachments.clone();
+        let status = status_label_rc.clone();
+
+        Rc::new(move || {
+            let idx = list.selected_row().map(|r| r.index() as usize).unwrap_or(0);
+            let Some(att) = atts.borrow().get(idx).cloned() else {
+                status.set_label("Keine Anhänge");
+                return;
+            };
+            match attachments::open_attachment(&att) {
+                Ok(()) => status.set_label(&format!("Öffne {}", att.filename)),
+                Err(e) => {
+                    status.set_label(&format!("Fehler beim Öffnen von {}: {}", att.filename, e))
+                }
+            }
+        })
+    };
+
+    let open_attachment_row = do_open_attachment.clone();
+    attachment_list.connect_row_activated(move |_, _| {
+        open_attachment_row();
+    });
+
+    // Gewählten (bzw. ersten) oder alle Anhänge in einen Ordner speichern
+    let last_attachment_dir = Rc::new(RefCell::new(None::<PathBuf>));
+    let do_save_attachments = {
+        let list = attachment_list.clone();
+        let atts = current_attachments.clone();
+        let status = status_label_rc.clone();
+        let last_dir = last_attachment_dir.clone();
+
+        Rc::new(move |all: bool| {
+            let to_save: Vec<attachments::Attachment> = if all {
+                atts.borrow().clone()
+            } else {
+                let idx = list.selected_row().map(|r| r.index() as usize).unwrap_or(0);
+                atts.borrow().get(idx).cloned().into_iter().collect()
+            };
+            if to_save.is_empty() {
+                status.set_label("Keine Anhänge");
+                return;
+            }
+
+            let parent = list.root().and_downcast::<gtk4::Window>();
+            let dialog = gtk4::FileChooserDialog::new(
+                Some("Zielordner für Anhänge"),
+                parent.as_ref(),
+                gtk4::FileChooserAction::SelectFolder,
+                &[
+                    ("Abbrechen", gtk4::ResponseType::Cancel),
+                    ("Speichern", gtk4::ResponseType::Accept),
+                ],
+            );
+            dialog.set_modal(true);
+            if let Some(dir) = last_dir.borrow().clone().or_else(dirs::download_dir) {
+                let _ = dialog.set_current_folder(Some(&gtk4::gio::File::for_path(dir)));
+            }
+
+            let status_c = status.clone();
+            let last_dir_c = last_dir.clone();
+            dialog.connect_response(move |d, response| {
+                if response == gtk4::ResponseType::Accept
+                    && let Some(dir) = d.file().and_then(|f| f.path())
+                {
+                    let mut errors = Vec::new();
+                    for att in &to_save {
+                        if let Err(e) = attachments::save_attachment(att, &dir) {
+                            errors.push(format!("{}: {}", att.filename, e));
+                        }
+                    }
+
+                    if errors.is_empty() {
+                        let what = if to_save.len() == 1 {
+                            to_save[0].filename.clone()
+                        } else {
+                            format!("{} Anhänge", to_save.len())
+                        };
+                        status_c.set_label(&format!("{} gespeichert in {}", what, dir.display()));
+                    } else {
+                        status_c
+                            .set_label(&format!("Fehler beim Speichern: {}", errors.join(", ")));
+                    }
+                    *last_dir_c.borrow_mut() = Some(dir);
+                }
+                d.destroy();
+            });
+            dialog.present();
+        })
+    };
+
     let app_clone1 = app.clone();
     btn_new_mail.connect_clicked(move |_| {
-        composer::open_composer_window(&app_clone1, None, None, None);
+        composer::open_composer_window(&app_clone1, composer::MailDraft::default());
     });
 
     let selected_mail_for_unsub = selected_mail.clone();
@@ -804,9 +1638,10 @@ fn build_ui(app: &Application) {
                         .to_string();
                     composer::open_composer_window(
                         &app_clone_unsub,
-                        Some(&to_clean),
-                        Some("Unsubscribe"),
-                        None,
+                        composer::MailDraft {
+                            subject: "Unsubscribe".to_string(),
+                            ..composer::MailDraft::to(&to_clean)
+                        },
                     );
                 } else if link.starts_with("http") {
                     if let Err(e) = gtk4::gio::AppInfo::launch_default_for_uri(
@@ -820,19 +1655,42 @@ fn build_ui(app: &Application) {
         }
     });
 
-    let app_clone2 = app.clone();
-    let selected_mail_for_reply = selected_mail.clone();
-    let text_buffer_for_reply = text_buffer.clone();
+    let do_reply = {
+        let app_clone2 = app.clone();
+        let selected_mail_for_reply = selected_mail.clone();
+        let text_buffer_for_reply = text_buffer.clone();
+        let status_reply = status_label_rc.clone();
+
+        Rc::new(move |mode: reply::ReplyMode| {
+            let selected = selected_mail_for_reply.borrow();
+            let Some(mail) = selected.as_ref() else {
+                return;
+            };
+            // Empfänger und Threading-Header aus der Originaldatei, nicht aus dem Return-Path
+            let target = match reply::reply_target(&mail.path, mode) {
+                Ok(t) => t,
+                Err(e) => {
+                    status_reply.set_label(&e);
+                    return;
+                }
+            };
 
-    btn_reply.connect_clicked(move |_| {
-        if let Some(ref mail) = *selected_mail_for_reply.borrow() {
-            let to = &mail.return_path;
             let mut subj = mail.subject.clone();
             if !subj.to_lowercase().starts_with("re:") {
                 subj = format!("Re: {}", subj);
             }
 
-            let (mut start, mut end) = text_buffer_for_reply.bounds();
+            // In der Konversationsansicht nur die gewählte Mail zitieren
+            let (mut start, mut end) = match (
+                text_buffer_for_reply.mark("msg-start"),
+                text_buffer_for_reply.mark("msg-end"),
+            ) {
+                (Some(s), Some(e)) => (
+                    text_buffer_for_reply.iter_at_mark(&s),
+                    text_buffer_for_reply.iter_at_mark(&e),
+                ),
+                _ => text_buffer_for_reply.bounds(),
+            };
             if let Some((s, e)) = text_buffer_for_reply.selection_bounds() {
                 start = s;
                 end = e;
@@ -840,23 +1698,61 @@ fn build_ui(app: &Application) {
 
             let raw_text = text_buffer_for_reply.text(&start, &end, false);
 
-            let mut quote = format!("--- Am {} schrieb {} :\n", mail.date_full, mail.from);
-            for line in raw_text.lines() {
-                quote.push_str("> ");
-                quote.push_str(line);
-                quote.push('\n');
-            }
+            let mut quote =
+                quote::attribution(&target.account, &mail.from, mail.timestamp, &mail.subject);
+            quote.push('\n');
+            quote.push_str(&quote::quote_body(&raw_text));
             quote.push('\n');
 
-            composer::open_composer_window(&app_clone2, Some(to), Some(&subj), Some(&quote));
-        }
-    });
+            composer::open_composer_window(
+                &app_clone2,
+                composer::MailDraft {
+                    to: target.to,
+                    cc: target.cc,
+                    subject: subj,
+                    body: quote,
+                    in_reply_to: target.in_reply_to,
+                    references: target.references,
+                    account: target.account,
+                    ..Default::default()
+                },
+            );
+        })
+    };
+
+    let reply_sender = do_reply.clone();
+    btn_reply.connect_clicked(move |_| reply_sender(reply::ReplyMode::Sender));
+    let reply_all = do_reply.clone();
+    btn_reply_all.connect_clicked(move |_| reply_all(reply::ReplyMode::All));
+    let reply_list = do_reply.clone();
+    btn_reply_list.connect_clicked(move |_| reply_list(reply::ReplyMode::List));
+
+    let do_forward = {
+        let app_forward = app.clone();
+        let selected_mail_for_forward = selected_mail.clone();
+        let status_forward = status_label_rc.clone();
+
+        Rc::new(move |mode: forward::ForwardMode| {
+            let selected = selected_mail_for_forward.borrow();
+            let Some(mail) = selected.as_ref() else {
+                return;
+            };
+            match forward::forward_draft(&mail.path, mode) {
+                Ok(draft) => composer::open_composer_window(&app_forward, draft),
+                Err(e) => status_forward.set_label(&format!("Weiterleiten fehlgeschlagen: {}", e)),
+            }
+        })
+    };
+
+    let forward_inline = do_forward.clone();
+    btn_forward.connect_clicked(move |_| forward_inline(forward::ForwardMode::Inline));
 
     // ÄNDERUNG: Schleifen über alle gewählten Reihen für die Aktionen
     let do_archive = {
         let disp_entries = displayed_mail_entries.clone();
         let all_entries = current_mail_entries.clone();
         let render = do_sort_and_render.clone();
+        let refresh_counts = do_refresh_counts.clone();
         let text_buf = text_buffer.clone();
         let list_box = mail_list.clone();
         let btn_archive_state = btn_archive.clone();
@@ -889,6 +1785,7 @@ fn build_ui(app: &Application) {
                     .borrow_mut()
                     .retain(|e| !paths_to_remove.contains(&e.path));
                 render();
+                refresh_counts();
                 text_buf.set_text("");
                 *selected_mail_state.borrow_mut() = None;
                 btn_archive_state.set_sensitive(false);
@@ -906,6 +1803,7 @@ fn build_ui(app: &Application) {
         let disp_entries = displayed_mail_entries.clone();
         let all_entries = current_mail_entries.clone();
         let render = do_sort_and_render.clone();
+        let refresh_counts = do_refresh_counts.clone();
         let text_buf = text_buffer.clone();
         let list_box = mail_list.clone();
         let btn_archive_state = btn_archive.clone();
@@ -938,6 +1836,7 @@ fn build_ui(app: &Application) {
                     .borrow_mut()
                     .retain(|e| !paths_to_remove.contains(&e.path));
                 render();
+                refresh_counts();
                 text_buf.set_text("");
                 *selected_mail_state.borrow_mut() = None;
                 btn_archive_state.set_sensitive(false);
@@ -950,6 +1849,7 @@ fn build_ui(app: &Application) {
         let disp_entries = displayed_mail_entries.clone();
         let all_entries = current_mail_entries.clone();
         let render = do_sort_and_render.clone();
+        let refresh_counts = do_refresh_counts.clone();
         let text_buf = text_buffer.clone();
         let list_box = mail_list.clone();
         let btn_archive_state = btn_archive.clone();
@@ -985,6 +1885,7 @@ fn build_ui(app: &Application) {
                     .borrow_mut()
                     .retain(|e| !paths_to_remove.contains(&e.path));
                 render();
+                refresh_counts();
                 text_buf.set_text("");
                 *selected_mail_state.borrow_mut() = None;
                 btn_archive_state.set_sensitive(false);
@@ -997,6 +1898,7 @@ fn build_ui(app: &Application) {
         let disp_entries = displayed_mail_entries.clone();
         let all_entries = current_mail_entries.clone();
         let render = do_sort_and_render.clone();
+        let refresh_counts = do_refresh_counts.clone();
         let text_buf = text_buffer.clone();
         let list_box = mail_list.clone();
         let btn_archive_state = btn_archive.clone();
@@ -1090,6 +1992,7 @@ fn build_ui(app: &Application) {
 
             let all_entries_c = all_entries.clone();
             let render_c = render.clone();
+            let refresh_c = refresh_counts.clone();
             let text_buf_c = text_buf.clone();
             let btn_arc_c = btn_archive_state.clone();
             let btn_rep_c = btn_reply_state.clone();
@@ -1113,6 +2016,7 @@ fn build_ui(app: &Application) {
                                 .borrow_mut()
                                 .retain(|e| !paths_to_remove.contains(&e.path));
                             render_c();
+                            refresh_c();
                             text_buf_c.set_text("");
                             *sel_mail_c.borrow_mut() = None;
                             btn_arc_c.set_sensitive(false);
@@ -1170,6 +2074,14 @@ fn build_ui(app: &Application) {
     let trash_shortcut_clone = do_trash.clone();
     let btn_search_shortcut = btn_search.clone();
     let app_clone_help_key = app.clone();
+    let app_clone_queue_key = app.clone();
+    let btn_threads_shortcut = btn_threads.clone();
+    let open_attachment_shortcut = do_open_attachment.clone();
+    let save_attachments_shortcut = do_save_attachments.clone();
+    let toggle_thread_shortcut = do_toggle_thread.clone();
+    let reply_shortcut = do_reply.clone();
+    let forward_shortcut = do_forward.clone();
+    let btn_reply_shortcut = btn_reply.clone();
 
     // ÄNDERUNG: Ctrl+A / Shift usw. dürfen nicht von uns verschluckt werden
     key_controller.connect_key_pressed(move |_, keyval, _, state| {
@@ -1232,6 +2144,52 @@ fn build_ui(app: &Application) {
                 btn_search_shortcut.set_active(true);
                 gtk4::glib::Propagation::Stop
             }
+            gdk::Key::o => {
+                open_attachment_shortcut();
+                gtk4::glib::Propagation::Stop
+            }
+            gdk::Key::s => {
+                save_attachments_shortcut(false);
+                gtk4::glib::Propagation::Stop
+            }
+            gdk::Key::S => {
+                save_attachments_shortcut(true);
+                gtk4::glib::Propagation::Stop
+            }
+            gdk::Key::t => {
+                btn_threads_shortcut.set_active(!btn_threads_shortcut.is_active());
+                gtk4::glib::Propagation::Stop
+            }
+            gdk::Key::h => {
+                toggle_thread_shortcut(Some(true));
+                gtk4::glib::Propagation::Stop
+            }
+            gdk::Key::l => {
+                toggle_thread_shortcut(Some(false));
+                gtk4::glib::Propagation::Stop
+            }
+            gdk::Key::r | gdk::Key::R | gdk::Key::L => {
+                if btn_reply_shortcut.is_sensitive() {
+                    reply_shortcut(match keyval {
+                        gdk::Key::R => reply::ReplyMode::All,
+                        gdk::Key::L => reply::ReplyMode::List,
+                        _ => reply::ReplyMode::Sender,
+                    });
+                }
+                gtk4::glib::Propagation::Stop
+            }
+            gdk::Key::f => {
+                forward_shortcut(forward::ForwardMode::Inline);
+                gtk4::glib::Propagation::Stop
+            }
+            gdk::Key::F => {
+                forward_shortcut(forward::ForwardMode::Attached);
+                gtk4::glib::Propagation::Stop
+            }
+            gdk::Key::w => {
+                queue::open_queue_window(&app_clone_queue_key);
+                gtk4::glib::Propagation::Stop
+            }
             gdk::Key::question => {
                 // NEU: Shortcut ? für Hilfe
                 help::show_help_window(&app_clone_help_key);
@@ -1255,6 +2213,93 @@ fn build_ui(app: &Application) {
     window.present();
 }
 
+fn build_saved_search_row(name: &str) -> (gtk4::Box, Label) {
+    let row_box = gtk4::Box::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(6)
+        .margin_start(10)
+        .margin_end(10)
+        .margin_top(5)
+        .margin_bottom(5)
+        .tooltip_text("Gespeicherte Suche (Entf zum Löschen)")
+        .build();
+    let icon = gtk4::Image::from_icon_name("folder-saved-search-symbolic");
+    let name_label = Label::builder()
+        .label(name)
+        .halign(gtk4::Align::Start)
+        .hexpand(true)
+        .ellipsize(gtk4::pango::EllipsizeMode::End)
+        .build();
+    let count_label = Label::builder().css_classes(["dim-label"]).build();
+
+    row_box.append(&icon);
+    row_box.append(&name_label);
+    row_box.append(&count_label);
+    (row_box, count_label)
+}
+
+// Konversationsansicht: alle Mails eines Threads nacheinander, die gewählte wird angesprungen.
+// Die Marken "msg-start"/"msg-end" begrenzen die gewählte Mail für das Zitat beim Antworten.
+fn show_conversation(
+    buffer: &gtk4::TextBuffer,
+    view: &TextView,
+    entries: &[MailEntry],
+    members: &[usize],
+    selected: &Path,
+) {
+    clear_message_marks(buffer);
+    buffer.set_text("");
+    let mut iter = buffer.end_iter();
+
+    for (n, &i) in members.iter().enumerate() {
+        let Some(entry) = entries.get(i) else {
+            continue;
+        };
+        if n > 0 {
+            buffer.insert(&mut iter, "\n\n");
+        }
+
+        let (name, email) = db::parse_from(&entry.from);
+        let who = if name.is_empty() { email } else { name };
+        buffer.insert_with_tags_by_name(
+            &mut iter,
+            &format!("{} – {}\n", who, entry.date_full),
+            &["conv-header"],
+        );
+
+        let body = fs::read(&entry.path)
+            .ok()
+            .and_then(|data| {
+                mailparse::parse_mail(&data)
+                    .ok()
+                    .map(|parsed| extract_best_body(&parsed))
+            })
+            .unwrap_or_default();
+
+        if entry.path == selected {
+            buffer.create_mark(Some("msg-start"), &iter, true);
+            buffer.insert(&mut iter, body.trim_end());
+            buffer.create_mark(Some("msg-end"), &iter, true);
+        } else {
+            buffer.insert(&mut iter, body.trim_end());
+        }
+    }
+
+    quote::apply_quote_tags(buffer);
+
+    if let Some(mark) = buffer.mark("msg-start") {
+        view.scroll_to_mark(&mark, 0.0, true, 0.0, 0.0);
+    }
+}
+
+fn clear_message_marks(buffer: &gtk4::TextBuffer) {
+    for name in ["msg-start", "msg-end"] {
+        if let Some(mark) = buffer.mark(name) {
+            buffer.delete_mark(&mark);
+        }
+    }
+}
+
 fn get_maildir_folders() -> Vec<String> {
     let mut folders = Vec::new();
     if let Some(mut path) = dirs::home_dir() {
@@ -1274,6 +2319,21 @@ fn get_maildir_folders() -> Vec<String> {
             let _ = fs::create_dir_all(outbox_path.join("tmp"));
         }
 
+        let sent_path = path.join("Sent");
+        if !sent_path.exists() {
+            let _ = fs::create_dir_all(sent_path.join("cur"));
+            let _ = fs::create_dir_all(sent_path.join("new"));
+            let _ = fs::create_dir_all(sent_path.join("tmp"));
+        }
+        outbox::migrate_old_sent();
+
+        let drafts_path = path.join("Drafts");
+        if !drafts_path.exists() {
+            let _ = fs::create_dir_all(drafts_path.join("cur"));
+            let _ = fs::create_dir_all(drafts_path.join("new"));
+            let _ = fs::create_dir_all(drafts_path.join("tmp"));
+        }
+
         let quarantine_path = path.join("Quarantäne");
         if !quarantine_path.exists() {
             let _ = fs::create_dir_all(quarantine_path.join("cur"));
@@ -1317,11 +2377,11 @@ fn extract_best_body(parsed: &mailparse::ParsedMail) -> String {
         .unwrap_or_default()
         .to_lowercase();
     if parsed.subparts.is_empty() {
-        let body = parsed.get_body().unwrap_or_default();
         if content_type.contains("text/html") {
-            return strip_html_tags(&body);
+            return strip_html_tags(&parsed.get_body().unwrap_or_default());
         }
-        return body;
+        // format=flowed wird zu langen Zeilen, die das Textfeld selbst umbricht
+        return mime::plain_text_body(parsed);
     }
     for part in &parsed.subparts {
         let ct = part
@@ -1330,7 +2390,7 @@ fn extract_best_body(parsed: &mailparse::ParsedMail) -> String {
             .unwrap_or_default()
             .to_lowercase();
         if ct.contains("text/plain") {
-            return part.get_body().unwrap_or_default();
+            return mime::plain_text_body(part);
         }
     }
     for part in &parsed.subparts {
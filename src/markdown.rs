@@ -0,0 +1,723 @@
+// Kleiner Markdown-Umsetzer für den Composer: der Text bleibt als text/plain lesbar,
+// dazu kommt ein text/html-Teil mit Inline-Styles (Mailprogramme ignorieren <style>)
+// und für die Vorschau dieselbe Darstellung als Pango-Markup.
+//
+// Unterstützt: Überschriften (#), Absätze, **fett**, *kursiv*, `Code`, Codeblöcke (```),
+// Zitate (>), Listen (-, *, +, 1.), Links [Text](URL) und nackte URLs, Trennlinien (---).
+// Anders als bei Markdown üblich bleiben einfache Zeilenumbrüche erhalten, wie man es
+// von einer Mail erwartet. Ab der Zeile "-- " folgt die Signatur unverändert.
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Html,
+    Pango,
+}
+
+#[derive(Debug, PartialEq)]
+enum Block {
+    Heading(usize, String),
+    Paragraph(Vec<String>),
+    Code(Vec<String>),
+    Quote(Vec<Block>),
+    List(Option<usize>, Vec<Vec<String>>),
+    Rule,
+    Signature(Vec<String>),
+}
+
+#[derive(Debug, PartialEq)]
+enum Inline {
+    Text(String),
+    Code(String),
+    Strong(Vec<Inline>),
+    Em(Vec<Inline>),
+    Link(Vec<Inline>, String),
+}
+
+const BODY_STYLE: &str =
+    "font-family: sans-serif; font-size: 14px; line-height: 1.5; color: #222222;";
+const P_STYLE: &str = "margin: 0 0 1em 0;";
+const PRE_STYLE: &str = "margin: 0 0 1em 0; padding: 8px; background: #f5f5f5; border-radius: 4px; \
+     font-family: monospace; font-size: 13px; white-space: pre-wrap;";
+const CODE_STYLE: &str =
+    "padding: 1px 4px; background: #f0f0f0; border-radius: 3px; font-family: monospace;";
+const QUOTE_STYLE: &str =
+    "margin: 0 0 1em 0; padding-left: 10px; border-left: 3px solid #cccccc; color: #555555;";
+const LIST_STYLE: &str = "margin: 0 0 1em 0; padding-left: 25px;";
+const LINK_STYLE: &str = "color: #1a5fb4;";
+const HR_STYLE: &str = "border: none; border-top: 1px solid #cccccc; margin: 1em 0;";
+const SIG_STYLE: &str = "color: #777777; font-size: 13px;";
+const HEADING_SIZES: [&str; 6] = ["24px", "20px", "18px", "16px", "14px", "14px"];
+const PANGO_HEADING_SIZES: [&str; 6] =
+    ["xx-large", "x-large", "large", "medium", "medium", "medium"];
+
+// Vollständiges HTML-Dokument für den text/html-Teil
+pub fn to_html(text: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n</head>\n\
+         <body style=\"{}\">\n{}</body>\n</html>\n",
+        BODY_STYLE,
+        render_blocks(&parse_blocks(text), Format::Html)
+    )
+}
+
+// Pango-Markup für die Vorschau in einem Label
+pub fn to_pango(text: &str) -> String {
+    render_blocks(&parse_blocks(text), Format::Pango)
+        .trim_end()
+        .to_string()
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if rest.is_empty() {
+        return Some((level, ""));
+    }
+    rest.strip_prefix(' ')
+        .map(|title| (level, title.trim().trim_end_matches('#').trim_end()))
+}
+
+fn is_rule(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.len() >= 3
+        && ["-", "*", "_"]
+            .iter()
+            .any(|m| compact.chars().all(|c| c.to_string() == *m))
+}
+
+// Listenpunkt: (Nummer bei nummerierten Listen, Text)
+fn list_item(line: &str) -> Option<(Option<usize>, &str)> {
+    let trimmed = line.trim_start();
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(rest) = trimmed.strip_prefix(marker) {
+            return Some((None, rest));
+        }
+    }
+    let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0
+        && digits < 10
+        && let Some(rest) = trimmed[digits..].strip_prefix(". ")
+    {
+        return Some((trimmed[..digits].parse().ok(), rest));
+    }
+    None
+}
+
+fn starts_block(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.starts_with("```")
+        || trimmed.starts_with('>')
+        || heading(trimmed).is_some()
+        || is_rule(trimmed)
+        || list_item(line).is_some()
+}
+
+fn parse_blocks(text: &str) -> Vec<Block> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut blocks = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+
+        if line == "-- " || line == "--" {
+            blocks.push(Block::Signature(
+                lines[i + 1..].iter().map(|l| l.to_string()).collect(),
+            ));
+            break;
+        }
+        if trimmed.is_empty() {
+            i += 1;
+            continue;
+        }
+
+        // Sprachangabe nach ``` wird ignoriert
+        if trimmed.starts_with("```") {
+            let mut code = Vec::new();
+            i += 1;
+            while i < lines.len() && !lines[i].trim_start().starts_with("```") {
+                code.push(lines[i].to_string());
+                i += 1;
+            }
+            // Schliessende ``` überspringen (fehlen sie, reicht der Block bis zum Ende)
+            i += 1;
+            blocks.push(Block::Code(code));
+            continue;
+        }
+
+        if let Some((level, title)) = heading(trimmed) {
+            blocks.push(Block::Heading(level, title.to_string()));
+            i += 1;
+            continue;
+        }
+
+        if is_rule(trimmed) {
+            blocks.push(Block::Rule);
+            i += 1;
+            continue;
+        }
+
+        if trimmed.starts_with('>') {
+            let mut quoted = Vec::new();
+            while i < lines.len() && lines[i].trim_start().starts_with('>') {
+                let inner = &lines[i].trim_start()[1..];
+                quoted.push(inner.strip_prefix(' ').unwrap_or(inner));
+                i += 1;
+            }
+            blocks.push(Block::Quote(parse_blocks(&quoted.join("\n"))));
+            continue;
+        }
+
+        if let Some((start, _)) = list_item(line) {
+            let mut items: Vec<Vec<String>> = Vec::new();
+            while i < lines.len() {
+                let current = lines[i];
+                if let Some((number, item)) = list_item(current) {
+                    // Wechsel zwischen - und 1. beginnt eine neue Liste
+                    if number.is_some() != start.is_some() {
+                        break;
+                    }
+                    items.push(vec![item.to_string()]);
+                } else if !current.trim().is_empty()
+                    && current.starts_with([' ', '\t'])
+                    && let Some(item) = items.last_mut()
+                {
+                    // Eingerückte Folgezeile gehört zum vorherigen Punkt
+                    item.push(current.trim().to_string());
+                } else {
+                    break;
+                }
+                i += 1;
+            }
+            blocks.push(Block::List(start, items));
+            continue;
+        }
+
+        let mut paragraph = Vec::new();
+        while i < lines.len() {
+            let current = lines[i];
+            if current.trim().is_empty()
+                || current == "-- "
+                || current == "--"
+                || (!paragraph.is_empty() && starts_block(current))
+            {
+                break;
+            }
+            paragraph.push(current.trim_end().to_string());
+            i += 1;
+        }
+        blocks.push(Block::Paragraph(paragraph));
+    }
+    blocks
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_blocks(blocks: &[Block], format: Format) -> String {
+    let html = format == Format::Html;
+    let mut out = String::new();
+    for block in blocks {
+        match block {
+            Block::Heading(level, title) => {
+                let content = render_inline(&parse_inline(title), format);
+                if html {
+                    out.push_str(&format!(
+                        "<h{0} style=\"margin: 0 0 0.5em 0; font-size: {1};\">{2}</h{0}>\n",
+                        level,
+                        HEADING_SIZES[level - 1],
+                        content
+                    ));
+                } else {
+                    out.push_str(&format!(
+                        "<span size=\"{}\" weight=\"bold\">{}</span>\n\n",
+                        PANGO_HEADING_SIZES[level - 1],
+                        content
+                    ));
+                }
+            }
+            Block::Paragraph(lines) => {
+                let separator = if html { "<br>\n" } else { "\n" };
+                let content = lines
+                    .iter()
+                    .map(|l| render_inline(&parse_inline(l), format))
+                    .collect::<Vec<_>>()
+                    .join(separator);
+                if html {
+                    out.push_str(&format!("<p style=\"{}\">{}</p>\n", P_STYLE, content));
+                } else {
+                    out.push_str(&format!("{}\n\n", content));
+                }
+            }
+            Block::Code(lines) => {
+                let content = escape(&lines.join("\n"));
+                if html {
+                    out.push_str(&format!("<pre style=\"{}\">{}</pre>\n", PRE_STYLE, content));
+                } else {
+                    out.push_str(&format!("<tt>{}</tt>\n\n", content));
+                }
+            }
+            Block::Quote(inner) => {
+                let content = render_blocks(inner, format);
+                if html {
+                    out.push_str(&format!(
+                        "<blockquote style=\"{}\">\n{}</blockquote>\n",
+                        QUOTE_STYLE, content
+                    ));
+                } else {
+                    let quoted = content
+                        .trim_end()
+                        .lines()
+                        .map(|l| format!("│ {}", l))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    out.push_str(&format!(
+                        "<span foreground=\"#777777\">{}</span>\n\n",
+                        quoted
+                    ));
+                }
+            }
+            Block::List(start, items) => {
+                let rendered: Vec<String> = items
+                    .iter()
+                    .map(|item| render_inline(&parse_inline(&item.join(" ")), format))
+                    .collect();
+                if html {
+                    let tag = if start.is_some() { "ol" } else { "ul" };
+                    let start_attr = match start {
+                        Some(n) if *n != 1 => format!(" start=\"{}\"", n),
+                        _ => String::new(),
+                    };
+                    out.push_str(&format!(
+                        "<{}{} style=\"{}\">\n",
+                        tag, start_attr, LIST_STYLE
+                    ));
+                    for item in rendered {
+                        out.push_str(&format!("<li>{}</li>\n", item));
+                    }
+                    out.push_str(&format!("</{}>\n", tag));
+                } else {
+                    for (n, item) in rendered.iter().enumerate() {
+                        match start {
+                            Some(first) => out.push_str(&format!("  {}. {}\n", first + n, item)),
+                            None => out.push_str(&format!("  • {}\n", item)),
+                        }
+                    }
+                    out.push('\n');
+                }
+            }
+            Block::Rule => {
+                if html {
+                    out.push_str(&format!("<hr style=\"{}\">\n", HR_STYLE));
+                } else {
+                    out.push_str("<span foreground=\"#999999\">――――――――――</span>\n\n");
+                }
+            }
+            Block::Signature(lines) => {
+                let content = lines
+                    .iter()
+                    .map(|l| render_inline(&parse_inline(l), format))
+                    .collect::<Vec<_>>();
+                if html {
+                    out.push_str(&format!(
+                        "<div style=\"{}\">--&nbsp;<br>\n{}</div>\n",
+                        SIG_STYLE,
+                        content.join("<br>\n")
+                    ));
+                } else {
+                    out.push_str(&format!(
+                        "<span foreground=\"#777777\">-- \n{}</span>\n",
+                        content.join("\n")
+                    ));
+                }
+            }
+        }
+    }
+    out
+}
+
+fn render_inline(inlines: &[Inline], format: Format) -> String {
+    let html = format == Format::Html;
+    let mut out = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Text(text) => out.push_str(&escape(text)),
+            Inline::Code(code) if html => out.push_str(&format!(
+                "<code style=\"{}\">{}</code>",
+                CODE_STYLE,
+                escape(code)
+            )),
+            Inline::Code(code) => out.push_str(&format!("<tt>{}</tt>", escape(code))),
+            Inline::Strong(inner) if html => out.push_str(&format!(
+                "<strong>{}</strong>",
+                render_inline(inner, format)
+            )),
+            Inline::Strong(inner) => {
+                out.push_str(&format!("<b>{}</b>", render_inline(inner, format)))
+            }
+            Inline::Em(inner) if html => {
+                out.push_str(&format!("<em>{}</em>", render_inline(inner, format)))
+            }
+            Inline::Em(inner) => out.push_str(&format!("<i>{}</i>", render_inline(inner, format))),
+            Inline::Link(text, url) if html => out.push_str(&format!(
+                "<a href=\"{}\" style=\"{}\">{}</a>",
+                escape(url),
+                LINK_STYLE,
+                render_inline(text, format)
+            )),
+            Inline::Link(text, url) => out.push_str(&format!(
+                "<a href=\"{}\">{}</a>",
+                escape(url),
+                render_inline(text, format)
+            )),
+        }
+    }
+    out
+}
+
+fn is_word_char(c: Option<&char>) -> bool {
+    c.is_some_and(|c| c.is_alphanumeric())
+}
+
+// Position des schliessenden Trenners `delim` ab `from`, nicht direkt nach einem Leerzeichen
+fn find_closing(chars: &[char], from: usize, delim: &[char]) -> Option<usize> {
+    let mut i = from;
+    while i + delim.len() <= chars.len() {
+        if chars[i] == '\\' {
+            i += 2;
+            continue;
+        }
+        if chars[i..i + delim.len()] == *delim
+            && i > from
+            && !chars[i - 1].is_whitespace()
+            // Einzelnes * bzw. _ darf nicht Teil eines doppelten sein
+            && (delim.len() > 1 || chars.get(i + 1) != Some(&delim[0]))
+        {
+            return Some(i);
+        }
+        i += 1;
+    }
+    None
+}
+
+fn parse_inline(text: &str) -> Vec<Inline> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = Vec::new();
+    let mut plain = String::new();
+    let mut i = 0;
+
+    let flush = |plain: &mut String, out: &mut Vec<Inline>| {
+        if !plain.is_empty() {
+            out.push(Inline::Text(std::mem::take(plain)));
+        }
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let prev = if i > 0 { chars.get(i - 1) } else { None };
+
+        if c == '\\'
+            && let Some(next) = chars.get(i + 1)
+            && next.is_ascii_punctuation()
+        {
+            plain.push(*next);
+            i += 2;
+            continue;
+        }
+
+        if c == '`'
+            && let Some(end) = chars[i + 1..].iter().position(|c| *c == '`')
+        {
+            flush(&mut plain, &mut out);
+            out.push(Inline::Code(chars[i + 1..i + 1 + end].iter().collect()));
+            i += end + 2;
+            continue;
+        }
+
+        if (c == '*' || c == '_') && !(c == '_' && is_word_char(prev)) {
+            let double = chars.get(i + 1) == Some(&c);
+            let delim: Vec<char> = if double { vec![c, c] } else { vec![c] };
+            let start = i + delim.len();
+            if chars.get(start).is_some_and(|n| !n.is_whitespace())
+                && let Some(end) = find_closing(&chars, start, &delim)
+                && !(c == '_' && is_word_char(chars.get(end + delim.len())))
+            {
+                flush(&mut plain, &mut out);
+                let inner = parse_inline(&chars[start..end].iter().collect::<String>());
+                out.push(if double {
+                    Inline::Strong(inner)
+                } else {
+                    Inline::Em(inner)
+                });
+                i = end + delim.len();
+                continue;
+            }
+        }
+
+        if c == '['
+            && let Some(close) = find_closing(&chars, i + 1, &[']'])
+            && chars.get(close + 1) == Some(&'(')
+            && let Some(paren) = chars[close + 2..].iter().position(|c| *c == ')')
+        {
+            let url: String = chars[close + 2..close + 2 + paren].iter().collect();
+            flush(&mut plain, &mut out);
+            let label = parse_inline(&chars[i + 1..close].iter().collect::<String>());
+            out.push(Inline::Link(label, url.trim().to_string()));
+            i = close + 3 + paren;
+            continue;
+        }
+
+        if c == '<'
+            && let Some(end) = chars[i + 1..].iter().position(|c| *c == '>')
+        {
+            let inner: String = chars[i + 1..i + 1 + end].iter().collect();
+            if inner.starts_with("http://") || inner.starts_with("https://") {
+                flush(&mut plain, &mut out);
+                out.push(Inline::Link(vec![Inline::Text(inner.clone())], inner));
+                i += end + 2;
+                continue;
+            }
+        }
+
+        let rest: String = if c == 'h' && !is_word_char(prev) {
+            chars[i..].iter().collect()
+        } else {
+            String::new()
+        };
+        if rest.starts_with("http://") || rest.starts_with("https://") {
+            let len = rest.find(|c: char| c.is_whitespace()).unwrap_or(rest.len());
+            // Satzzeichen am Ende gehören meist nicht zur URL
+            let url = rest[..len].trim_end_matches(['.', ',', ';', ':', '!', '?', ')']);
+            flush(&mut plain, &mut out);
+            out.push(Inline::Link(
+                vec![Inline::Text(url.to_string())],
+                url.to_string(),
+            ));
+            i += url.chars().count();
+            continue;
+        }
+
+        plain.push(c);
+        i += 1;
+    }
+    flush(&mut plain, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Inline {
+        Inline::Text(s.to_string())
+    }
+
+    fn lines(list: &[&str]) -> Vec<String> {
+        list.iter().map(|l| l.to_string()).collect()
+    }
+
+    // Wohlgeformtes Markup mit den Tags, die GtkLabel versteht: ausgeglichene Tags und
+    // nur gültige Entities
+    fn assert_valid_markup(markup: &str) {
+        let mut open = Vec::new();
+        let mut rest = markup;
+        while let Some(pos) = rest.find(['<', '&', '>']) {
+            let after = &rest[pos..];
+            if after.starts_with('>') {
+                panic!("'>' ausserhalb eines Tags: {:?}", markup);
+            }
+            if after.starts_with('&') {
+                let entity = ["&amp;", "&lt;", "&gt;", "&quot;"]
+                    .into_iter()
+                    .find(|e| after.starts_with(e))
+                    .unwrap_or_else(|| panic!("Ungültige Entity: {:?}", markup));
+                rest = &after[entity.len()..];
+                continue;
+            }
+            let end = after.find('>').expect("Tag nicht geschlossen");
+            let tag = &after[1..end];
+            assert!(!tag.contains('<'), "{:?}", markup);
+            if let Some(name) = tag.strip_prefix('/') {
+                assert_eq!(open.pop(), Some(name), "{:?}", markup);
+            } else {
+                let name = tag.split(' ').next().unwrap();
+                assert!(
+                    ["b", "i", "tt", "span", "a"].contains(&name),
+                    "{:?}",
+                    markup
+                );
+                open.push(name);
+            }
+            rest = &after[end + 1..];
+        }
+        assert!(open.is_empty(), "{:?}", markup);
+    }
+
+    #[test]
+    fn emphasis() {
+        assert_eq!(
+            parse_inline("**fett** und *kursiv*"),
+            [
+                Inline::Strong(vec![text("fett")]),
+                text(" und "),
+                Inline::Em(vec![text("kursiv")]),
+            ]
+        );
+        assert_eq!(
+            parse_inline("_kursiv_ mit **_beidem_**"),
+            [
+                Inline::Em(vec![text("kursiv")]),
+                text(" mit "),
+                Inline::Strong(vec![Inline::Em(vec![text("beidem")])]),
+            ]
+        );
+        assert_eq!(parse_inline("2 * 3 * 4"), [text("2 * 3 * 4")]);
+        assert_eq!(parse_inline("\\*kein\\*"), [text("*kein*")]);
+    }
+
+    #[test]
+    fn snake_case() {
+        assert_eq!(parse_inline("my_var_name"), [text("my_var_name")]);
+        assert_eq!(
+            parse_inline("snake_case und _kursiv_"),
+            [text("snake_case und "), Inline::Em(vec![text("kursiv")])]
+        );
+        assert_eq!(
+            parse_inline("__init__"),
+            [Inline::Strong(vec![text("init")])]
+        );
+        assert_eq!(parse_inline("a__b__c"), [text("a__b__c")]);
+    }
+
+    #[test]
+    fn code_spans() {
+        assert_eq!(
+            parse_inline("`**nicht fett**` und `a_b`"),
+            [
+                Inline::Code("**nicht fett**".to_string()),
+                text(" und "),
+                Inline::Code("a_b".to_string()),
+            ]
+        );
+        assert_eq!(
+            parse_inline("offenes ` Zeichen"),
+            [text("offenes ` Zeichen")]
+        );
+    }
+
+    #[test]
+    fn links() {
+        assert_eq!(
+            parse_inline("[**Seite**](https://example.org/a_b) lesen"),
+            [
+                Inline::Link(
+                    vec![Inline::Strong(vec![text("Seite")])],
+                    "https://example.org/a_b".to_string()
+                ),
+                text(" lesen"),
+            ]
+        );
+        assert_eq!(
+            parse_inline("Siehe https://example.org/x_y_z."),
+            [
+                text("Siehe "),
+                Inline::Link(
+                    vec![text("https://example.org/x_y_z")],
+                    "https://example.org/x_y_z".to_string()
+                ),
+                text("."),
+            ]
+        );
+        assert_eq!(
+            parse_inline("<https://example.org>"),
+            [Inline::Link(
+                vec![text("https://example.org")],
+                "https://example.org".to_string()
+            )]
+        );
+        assert_eq!(parse_inline("[nur](Klammer"), [text("[nur](Klammer")]);
+    }
+
+    #[test]
+    fn lists() {
+        assert_eq!(
+            parse_blocks("- eins\n- zwei\n  Fortsetzung\n\n3. drei\n4. vier"),
+            [
+                Block::List(
+                    None,
+                    vec![lines(&["eins"]), lines(&["zwei", "Fortsetzung"])]
+                ),
+                Block::List(Some(3), vec![lines(&["drei"]), lines(&["vier"])]),
+            ]
+        );
+        assert_eq!(
+            parse_blocks("Text\n- Punkt"),
+            [
+                Block::Paragraph(lines(&["Text"])),
+                Block::List(None, vec![lines(&["Punkt"])]),
+            ]
+        );
+    }
+
+    #[test]
+    fn blocks() {
+        assert_eq!(
+            parse_blocks("# Titel\n\nZeile 1\nZeile 2\n\n> zitiert\n\n```rust\n*x*\n```\n---"),
+            [
+                Block::Heading(1, "Titel".to_string()),
+                Block::Paragraph(lines(&["Zeile 1", "Zeile 2"])),
+                Block::Quote(vec![Block::Paragraph(lines(&["zitiert"]))]),
+                Block::Code(lines(&["*x*"])),
+                Block::Rule,
+            ]
+        );
+    }
+
+    #[test]
+    fn signature() {
+        assert_eq!(
+            parse_blocks("Gruss\n-- \n*Name*\n- keine Liste"),
+            [
+                Block::Paragraph(lines(&["Gruss"])),
+                Block::Signature(lines(&["*Name*", "- keine Liste"])),
+            ]
+        );
+        let html = to_html("Text\n-- \nName");
+        assert!(html.contains("--&nbsp;<br>\nName</div>"));
+        assert!(to_pango("Text\n--\nName").ends_with("-- \nName</span>"));
+    }
+
+    #[test]
+    fn html_escapes() {
+        let html = to_html("a < b & \"c\" [x](https://e.org/?a=1&b=2)");
+        assert!(html.contains("a &lt; b &amp; &quot;c&quot;"));
+        assert!(html.contains("href=\"https://e.org/?a=1&amp;b=2\""));
+    }
+
+    #[test]
+    fn pango_markup_is_valid() {
+        let samples = [
+            "# Titel mit <Klammern> & **fett**",
+            "Absatz mit *kursiv*, `<code>` und [Link](https://e.org/?a=1&b=2)\nzweite Zeile",
+            "> Zitat mit **fett**\n> > verschachtelt",
+            "- eins\n- *zwei*\n\n1. a\n2. b",
+            "```\n<html> & </html>\n```",
+            "---",
+            "**offen und *halb",
+            "Gruss\n-- \n<name@example.org>",
+            "",
+        ];
+        for sample in samples {
+            assert_valid_markup(&to_pango(sample));
+        }
+        assert_eq!(to_pango("**a** & <b>"), "<b>a</b> &amp; &lt;b&gt;");
+    }
+}
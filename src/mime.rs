@@ -0,0 +1,882 @@
+use base64::Engine;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static BOUNDARY_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Kopfzeilen mit Adresslisten: nur die Anzeigenamen werden nach RFC 2047 kodiert
+const ADDRESS_HEADERS: [&str; 6] = ["From", "To", "Cc", "Bcc", "Reply-To", "Sender"];
+// Kopfzeilen mit Message-IDs oder Datum bleiben unverändert
+const RAW_HEADERS: [&str; 5] = [
+    "Message-ID",
+    "In-Reply-To",
+    "References",
+    "Date",
+    "MIME-Version",
+];
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+// Signaturen am Dateianfang für Dateien ohne (bekannte) Endung
+const MAGIC_BYTES: [(&[u8], &str); 8] = [
+    (b"%PDF-", "application/pdf"),
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"-----BEGIN PGP", "application/pgp-keys"),
+];
+
+// Datei für einen Anhang im Composer
+#[derive(Clone, PartialEq)]
+pub struct OutgoingAttachment {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+impl OutgoingAttachment {
+    pub fn from_path(path: &Path) -> std::io::Result<Self> {
+        let data = std::fs::read(path)?;
+        let filename = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "anhang".to_string());
+        let content_type = detect_mime_type(path, &data).to_string();
+        Ok(OutgoingAttachment {
+            filename,
+            content_type,
+            data,
+        })
+    }
+}
+
+// MIME-Typ über die Dateiendung, sonst über die ersten Bytes; Text ohne Nullbytes wird
+// text/plain, alles andere application/octet-stream
+pub fn detect_mime_type(path: &Path, data: &[u8]) -> &'static str {
+    let by_extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .and_then(|e| crate::attachments::mime_for_extension(&e.to_lowercase()));
+    if let Some(mime) = by_extension {
+        return mime;
+    }
+
+    if let Some((_, mime)) = MAGIC_BYTES
+        .iter()
+        .find(|(magic, _)| data.starts_with(magic))
+    {
+        return mime;
+    }
+
+    if !data.contains(&0) && std::str::from_utf8(data).is_ok() {
+        "text/plain"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+// Höchstlänge eines kodierten Parameters bzw. einer Fortsetzung samt Namen
+const PARAM_WIDTH: usize = 60;
+
+// Parameter nach RFC 2231: ASCII bleibt lesbar, sonst utf-8''%XX mit Fortsetzungen
+// (filename*0*=, filename*1*= ...), damit keine Headerzeile zu lang wird
+pub fn encode_rfc2231_param(name: &str, value: &str) -> String {
+    let is_plain = value
+        .chars()
+        .all(|c| (c.is_ascii_graphic() && c != '"' && c != '\\') || c == ' ');
+    if is_plain && value.len() <= 60 {
+        return format!("{}=\"{}\"", name, value);
+    }
+
+    // Ein Eintrag pro Zeichen: viele Programme dekodieren jede Fortsetzung einzeln und
+    // dürfen deshalb kein halbes UTF-8-Zeichen bekommen
+    let mut encoded = Vec::new();
+    for c in value.chars() {
+        let mut buf = [0; 4];
+        let unit: String = c
+            .encode_utf8(&mut buf)
+            .bytes()
+            .map(|b| {
+                if b.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&b) {
+                    (b as char).to_string()
+                } else {
+                    format!("%{:02X}", b)
+                }
+            })
+            .collect();
+        encoded.push(unit);
+    }
+
+    let single = format!("{}*=utf-8''{}", name, encoded.concat());
+    if single.len() <= PARAM_WIDTH {
+        return single;
+    }
+
+    // Fortsetzungen nach kodierter Länge füllen
+    let mut params = Vec::new();
+    let mut current = format!("{}*0*=utf-8''", name);
+    let mut filled = false;
+    for unit in encoded {
+        if filled && current.len() + unit.len() > PARAM_WIDTH {
+            params.push(std::mem::take(&mut current));
+            current = format!("{}*{}*=", name, params.len());
+        }
+        current.push_str(&unit);
+        filled = true;
+    }
+    params.push(current);
+    params.join(";\r\n ")
+}
+
+// Base64 mit Zeilen zu 76 Zeichen (RFC 2045)
+pub fn base64_lines(data: &[u8]) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(data);
+    let mut out = String::with_capacity(encoded.len() + encoded.len() / 76 * 2 + 2);
+    for line in encoded.as_bytes().chunks(76) {
+        out.push_str(std::str::from_utf8(line).unwrap());
+        out.push_str("\r\n");
+    }
+    out
+}
+
+pub fn new_boundary() -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let seq = BOUNDARY_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("=_nox_{:x}_{:x}_{}", nanos, std::process::id(), seq)
+}
+
+// Eindeutige Message-ID mit der Domain des Absenders (sonst dem Hostnamen)
+pub fn new_message_id(from: &str) -> String {
+    let domain = mailparse::addrparse(from)
+        .ok()
+        .and_then(|list| list.extract_single_info())
+        .and_then(|info| info.addr.rsplit_once('@').map(|(_, d)| d.to_string()))
+        .filter(|d| !d.is_empty())
+        .unwrap_or_else(|| gtk4::glib::host_name().to_string());
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let seq = BOUNDARY_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("<{:x}.{:x}.{}@{}>", nanos, std::process::id(), seq, domain)
+}
+
+// Datum nach RFC 5322, unabhängig von der Locale: "Sat, 17 Oct 2026 14:03:00 +0200"
+pub fn rfc5322_date(unix: i64, utc_offset: i64) -> String {
+    let local = unix + utc_offset;
+    let days = local.div_euclid(86400);
+    let secs = local.rem_euclid(86400);
+    let (y, m, d) = civil_from_days(days);
+    // 1970-01-01 war ein Donnerstag
+    let weekday = WEEKDAYS[(days + 3).rem_euclid(7) as usize];
+    let sign = if utc_offset < 0 { '-' } else { '+' };
+    let offset_min = utc_offset.abs() / 60;
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} {}{:02}{:02}",
+        weekday,
+        d,
+        MONTHS[(m - 1) as usize],
+        y,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+        sign,
+        offset_min / 60,
+        offset_min % 60
+    )
+}
+
+// Aktuelle Zeit mit der lokalen Zeitzone
+pub fn rfc5322_now() -> String {
+    let (now, utc_offset) = gtk4::glib::DateTime::now_local()
+        .map(|dt| (dt.to_unix(), dt.utc_offset().as_seconds()))
+        .unwrap_or((0, 0));
+    rfc5322_date(now, utc_offset)
+}
+
+// Umkehrung von search::days_from_civil (Howard Hinnant)
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
+
+// Eine Kopfzeile inkl. CRLF: Nicht-ASCII als RFC 2047 encoded-words, lange Zeilen gefaltet
+pub fn header_line(name: &str, value: &str) -> String {
+    let value = value.trim();
+    let encoded = if RAW_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name)) {
+        value.to_string()
+    } else if ADDRESS_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name)) {
+        encode_address_list(value)
+    } else {
+        encode_unstructured(value)
+    };
+    fold_header(&format!("{}: {}", name, encoded))
+}
+
+// Freitext (z.B. Subject): Wörter mit Nicht-ASCII-Zeichen werden zu encoded-words
+pub fn encode_unstructured(value: &str) -> String {
+    if value.is_ascii() {
+        return value.to_string();
+    }
+
+    // Aufeinanderfolgende Nicht-ASCII-Wörter gemeinsam kodieren, damit der Leerraum
+    // dazwischen erhalten bleibt (zwischen encoded-words wird er ignoriert)
+    let words: Vec<&str> = value.split(' ').collect();
+    let mut out: Vec<String> = Vec::new();
+    let mut pending: Vec<&str> = Vec::new();
+    for word in words {
+        if word.is_ascii() && !word.contains("=?") {
+            if !pending.is_empty() {
+                out.push(encode_words(&pending.join(" ")));
+                pending.clear();
+            }
+            out.push(word.to_string());
+        } else {
+            pending.push(word);
+        }
+    }
+    if !pending.is_empty() {
+        out.push(encode_words(&pending.join(" ")));
+    }
+    out.join(" ")
+}
+
+// Text als Folge von =?utf-8?B?...?=, jedes höchstens 75 Zeichen lang (RFC 2047)
+fn encode_words(text: &str) -> String {
+    let mut words = Vec::new();
+    let mut chunk = String::new();
+    for c in text.chars() {
+        // 45 Bytes ergeben 60 Zeichen Base64, plus 12 Zeichen Rahmen
+        if chunk.len() + c.len_utf8() > 45 {
+            words.push(std::mem::take(&mut chunk));
+        }
+        chunk.push(c);
+    }
+    if !chunk.is_empty() {
+        words.push(chunk);
+    }
+
+    words
+        .iter()
+        .map(|w| {
+            format!(
+                "=?utf-8?B?{}?=",
+                base64::engine::general_purpose::STANDARD.encode(w.as_bytes())
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// "Jürg Müller <jm@example.ch>, info@example.ch": nur Anzeigenamen kodieren
+pub fn encode_address_list(value: &str) -> String {
+    let Ok(list) = mailparse::addrparse(value) else {
+        return encode_unstructured(value);
+    };
+
+    let format_single = |info: &mailparse::SingleInfo| match &info.display_name {
+        Some(name) if !name.is_empty() => {
+            format!("{} <{}>", encode_display_name(name), info.addr)
+        }
+        _ => info.addr.clone(),
+    };
+
+    list.iter()
+        .map(|addr| match addr {
+            mailparse::MailAddr::Single(info) => format_single(info),
+            mailparse::MailAddr::Group(group) => format!(
+                "{}: {};",
+                encode_display_name(&group.group_name),
+                group
+                    .addrs
+                    .iter()
+                    .map(format_single)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Lesbare Form für Eingabefelder: Name in Anführungszeichen, wenn er Sonderzeichen enthält
+pub fn display_address(name: &str, addr: &str) -> String {
+    if name.is_empty() {
+        addr.to_string()
+    } else if name.chars().any(|c| "()<>[]:;@\\,.\"".contains(c)) {
+        format!("\"{}\" <{}>", name.replace('"', "'"), addr)
+    } else {
+        format!("{} <{}>", name, addr)
+    }
+}
+
+// Reine Adressen einer Empfängerliste (für den Umschlag); Fehler nennt den ungültigen Teil
+pub fn envelope_addresses(value: &str) -> Result<Vec<String>, String> {
+    if value.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let list = mailparse::addrparse(value).map_err(|_| value.trim().to_string())?;
+
+    let mut addresses = Vec::new();
+    for addr in list.iter() {
+        let singles = match addr {
+            mailparse::MailAddr::Single(info) => vec![info],
+            mailparse::MailAddr::Group(group) => group.addrs.iter().collect(),
+        };
+        for info in singles {
+            let (local, domain) = info.addr.rsplit_once('@').unwrap_or(("", ""));
+            if local.is_empty() || domain.is_empty() || info.addr.contains(char::is_whitespace) {
+                return Err(info.addr.clone());
+            }
+            addresses.push(info.addr.clone());
+        }
+    }
+    Ok(addresses)
+}
+
+fn encode_display_name(name: &str) -> String {
+    if !name.is_ascii() {
+        return encode_words(name);
+    }
+    let needs_quotes = name.chars().any(|c| "()<>[]:;@\\,.\"".contains(c));
+    if needs_quotes {
+        format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        name.to_string()
+    }
+}
+
+// Faltet eine Kopfzeile vor Leerzeichen, sodass keine Zeile länger als 78 Zeichen wird
+pub fn fold_header(line: &str) -> String {
+    let mut out = String::new();
+    let mut current = String::new();
+
+    for (i, word) in line.split(' ').enumerate() {
+        if i > 0 && current.len() + 1 + word.len() > 78 && !current.trim().is_empty() {
+            out.push_str(&current);
+            out.push_str("\r\n");
+            current = String::new();
+        }
+        if i > 0 {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    out.push_str(&current);
+    out.push_str("\r\n");
+    out
+}
+
+// Mailtext für den Versand: CRLF-Zeilenenden; reines ASCII mit kurzen Zeilen bleibt 7bit,
+// sonst quoted-printable, damit die Mail 8-Bit-unsaubere MTAs und Signaturen übersteht
+pub fn encode_text_body(body: &str) -> (&'static str, String) {
+    let normalized = body.replace("\r\n", "\n").replace('\n', "\r\n");
+    let is_7bit = normalized.is_ascii() && normalized.split("\r\n").all(|l| l.len() <= 998);
+    if is_7bit {
+        ("7bit", normalized)
+    } else {
+        (
+            "quoted-printable",
+            quoted_printable::encode_to_str(normalized),
+        )
+    }
+}
+
+// Mailtext als text/plain mit format=flowed bzw. als text/html
+fn text_part(subtype: &str, text: &str) -> String {
+    let (content_type, text) = if subtype == "plain" {
+        (
+            "text/plain; charset=utf-8; format=flowed",
+            encode_flowed(text),
+        )
+    } else {
+        ("text/html; charset=utf-8", text.to_string())
+    };
+    let (transfer_encoding, text) = encode_text_body(&text);
+    format!(
+        "Content-Type: {}\r\nContent-Transfer-Encoding: {}\r\n\r\n{}",
+        content_type, transfer_encoding, text
+    )
+}
+
+// Vollständige Mail aus fertigen Kopfzeilen (mit CRLF abgeschlossen), Text und Anhängen.
+// Mit `html` (z.B. aus Markdown) wird der Text zu multipart/alternative aus text/plain und
+// text/html. Ohne Anhänge und HTML bleibt es eine einfache text/plain-Mail.
+pub fn build_message(
+    headers: &str,
+    body: &str,
+    html: Option<&str>,
+    attachments: &[OutgoingAttachment],
+) -> String {
+    // Die bevorzugte Darstellung steht bei multipart/alternative zuletzt (RFC 2046, 5.1.4)
+    let body_part = match html {
+        Some(html) => {
+            let boundary = new_boundary();
+            format!(
+                "Content-Type: multipart/alternative; boundary=\"{0}\"\r\n\r\n\
+                 --{0}\r\n{1}\r\n--{0}\r\n{2}\r\n--{0}--\r\n",
+                boundary,
+                text_part("plain", body),
+                text_part("html", html)
+            )
+        }
+        None => text_part("plain", body),
+    };
+
+    if attachments.is_empty() {
+        return format!("{}MIME-Version: 1.0\r\n{}", headers, body_part);
+    }
+
+    let boundary = new_boundary();
+    let mut mail = format!(
+        "{}MIME-Version: 1.0\r\nContent-Type: multipart/mixed; boundary=\"{}\"\r\n\r\n\
+         This is a multi-part message in MIME format.\r\n",
+        headers, boundary
+    );
+
+    mail.push_str(&format!("--{}\r\n{}\r\n", boundary, body_part));
+
+    for attachment in attachments {
+        // Weitergeleitete Mails bleiben lesbar eingebettet, base64 ist für
+        // message/rfc822 nicht erlaubt (RFC 2046, 5.2.1)
+        if attachment.content_type == "message/rfc822"
+            && let Ok(text) = std::str::from_utf8(&attachment.data)
+        {
+            let transfer_encoding = if text.is_ascii() { "7bit" } else { "8bit" };
+            let mut text = text.replace("\r\n", "\n").replace('\n', "\r\n");
+            if !text.ends_with("\r\n") {
+                text.push_str("\r\n");
+            }
+            mail.push_str(&format!(
+                "--{}\r\nContent-Type: message/rfc822\r\nContent-Disposition: attachment;\r\n {}\r\n\
+                 Content-Transfer-Encoding: {}\r\n\r\n{}",
+                boundary,
+                encode_rfc2231_param("filename", &attachment.filename),
+                transfer_encoding,
+                text
+            ));
+            continue;
+        }
+
+        // Eine Mail mit Binärdaten lässt sich als message/rfc822 weder in 8bit noch in
+        // base64 verschicken, sie geht deshalb als gewöhnliche Datei mit
+        let content_type = if attachment.content_type == "message/rfc822" {
+            "application/octet-stream".to_string()
+        } else if attachment.content_type.starts_with("text/")
+            && std::str::from_utf8(&attachment.data).is_ok()
+        {
+            // Andere Kodierungen sind unbekannt und bleiben ohne charset
+            format!("{}; charset=utf-8", attachment.content_type)
+        } else {
+            attachment.content_type.clone()
+        };
+        mail.push_str(&format!(
+            "--{}\r\nContent-Type: {}\r\nContent-Disposition: attachment;\r\n {}\r\n\
+             Content-Transfer-Encoding: base64\r\n\r\n{}",
+            boundary,
+            content_type,
+            encode_rfc2231_param("filename", &attachment.filename),
+            base64_lines(&attachment.data)
+        ));
+    }
+
+    mail.push_str(&format!("--{}--\r\n", boundary));
+    mail
+}
+
+// Zeilenlänge für format=flowed (RFC 3676 empfiehlt höchstens 78)
+const FLOWED_WIDTH: usize = 72;
+
+// Zitatebene und Text einer Zeile; "> > Text" und ">> Text" zählen beide als Ebene 2
+pub fn split_quote(line: &str) -> (usize, &str) {
+    let mut depth = 0;
+    let mut rest = line;
+    loop {
+        if let Some(r) = rest.strip_prefix('>') {
+            depth += 1;
+            rest = r;
+        } else if depth > 0
+            && let Some(r) = rest.strip_prefix(" >")
+        {
+            depth += 1;
+            rest = r;
+        } else {
+            break;
+        }
+    }
+    if depth > 0 {
+        rest = rest.strip_prefix(' ').unwrap_or(rest);
+    }
+    (depth, rest)
+}
+
+// Text mit harten Umbrüchen bei FLOWED_WIDTH Zeichen nach RFC 3676 (DelSp=no): lange
+// Zeilen enden an der Umbruchstelle mit einem Leerzeichen (weicher Umbruch), Leerzeichen
+// am Ende echter Zeilen fallen weg, Zeilen mit " ", ">" oder "From " am Anfang bekommen
+// ein zusätzliches Leerzeichen. Die Signaturtrennzeile "-- " bleibt unverändert.
+pub fn encode_flowed(text: &str) -> String {
+    let mut out = String::new();
+    for line in text.replace("\r\n", "\n").split('\n') {
+        if line == "-- " {
+            out.push_str("-- \n");
+            continue;
+        }
+        let (depth, content) = split_quote(line);
+        let content = content.trim_end_matches(' ');
+        let prefix = if depth > 0 {
+            // Bei Zitaten ist das Leerzeichen nach den ">" zugleich das Stuffing
+            format!("{} ", ">".repeat(depth))
+        } else if content.starts_with([' ', '>']) || content.starts_with("From ") {
+            " ".to_string()
+        } else {
+            String::new()
+        };
+        if content.is_empty() {
+            out.push_str(prefix.trim_end());
+            out.push('\n');
+            continue;
+        }
+
+        let width = FLOWED_WIDTH.saturating_sub(prefix.chars().count()).max(20);
+        let mut rest = content;
+        while rest.chars().count() > width {
+            // Letztes Leerzeichen innerhalb der Breite; ein überlanges Wort bleibt ganz
+            let limit = rest
+                .char_indices()
+                .nth(width)
+                .map(|(i, _)| i)
+                .unwrap_or(rest.len());
+            let break_at = rest[..limit]
+                .rfind(' ')
+                .filter(|i| *i > 0)
+                .or_else(|| rest[limit..].find(' ').map(|i| i + limit));
+            let Some(at) = break_at else {
+                break;
+            };
+            // Das Leerzeichen bleibt am Zeilenende und markiert den weichen Umbruch
+            out.push_str(&prefix);
+            out.push_str(&rest[..=at]);
+            out.push('\n');
+            rest = &rest[at + 1..];
+            // Führende Leerzeichen der Folgezeile würden als Stuffing gelesen
+            if depth == 0 && (rest.starts_with([' ', '>']) || rest.starts_with("From ")) {
+                out.push(' ');
+            }
+        }
+        out.push_str(&prefix);
+        out.push_str(rest);
+        out.push('\n');
+    }
+    // Der letzte Umbruch kommt von split, nicht aus dem Text
+    out.pop();
+    out
+}
+
+// Gegenstück zu encode_flowed: weich umbrochene Zeilen derselben Zitatebene werden wieder
+// zu einer Zeile zusammengesetzt, Zitate als "> " je Ebene ausgegeben. Mit `delsp`
+// (DelSp=yes) gehört das Leerzeichen vor dem Umbruch nicht zum Text.
+pub fn decode_flowed(text: &str, delsp: bool) -> String {
+    let mut out = String::new();
+    // (Zitatebene, bisheriger Text) einer noch offenen, weich umbrochenen Zeile
+    let mut open: Option<(usize, String)> = None;
+
+    let finish = |out: &mut String, depth: usize, content: &str| {
+        let line = format!("{}{}", "> ".repeat(depth), content);
+        out.push_str(if content.is_empty() {
+            line.trim_end()
+        } else {
+            &line
+        });
+        out.push('\n');
+    };
+
+    for line in text.replace("\r\n", "\n").lines() {
+        let depth = line.chars().take_while(|c| *c == '>').count();
+        let content = &line[depth..];
+        let content = content.strip_prefix(' ').unwrap_or(content);
+
+        let mut current = String::new();
+        if let Some((open_depth, open_text)) = open.take() {
+            if open_depth == depth {
+                current = open_text;
+            } else {
+                // Ebene wechselt mitten im Absatz: fehlerhaft, als harten Umbruch behandeln
+                finish(&mut out, open_depth, &open_text);
+            }
+        }
+        if content.ends_with(' ') && content != "-- " {
+            let content = if delsp {
+                &content[..content.len() - 1]
+            } else {
+                content
+            };
+            current.push_str(content);
+            open = Some((depth, current));
+        } else {
+            current.push_str(content);
+            finish(&mut out, depth, &current);
+        }
+    }
+    if let Some((depth, text)) = open {
+        finish(&mut out, depth, &text);
+    }
+    if !text.ends_with('\n') {
+        out.pop();
+    }
+    out
+}
+
+// Dekodierter Text eines text/plain-Teils, format=flowed wieder zu Absätzen zusammengesetzt
+pub fn plain_text_body(part: &mailparse::ParsedMail) -> String {
+    let body = part.get_body().unwrap_or_default();
+    let param_is = |name: &str, value: &str| {
+        part.ctype
+            .params
+            .get(name)
+            .is_some_and(|v| v.eq_ignore_ascii_case(value))
+    };
+    if param_is("format", "flowed") {
+        decode_flowed(&body, param_is("delsp", "yes"))
+    } else {
+        body
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(text: &str) {
+        let encoded = encode_flowed(text);
+        assert_eq!(decode_flowed(&encoded, false), text, "{:?}", encoded);
+    }
+
+    // Alle Zeilen höchstens 78 Zeichen, mit CRLF, Folgezeilen eingerückt
+    fn assert_folded(header: &str) {
+        let lines = header.strip_suffix("\r\n").unwrap().split("\r\n");
+        for (i, line) in lines.enumerate() {
+            assert!(line.len() <= 78, "{:?}", line);
+            assert!(!line.contains('\n'), "{:?}", line);
+            assert_eq!(i > 0, line.starts_with(' '), "{:?}", line);
+        }
+    }
+
+    fn decoded(header: &str) -> String {
+        let (parsed, _) = mailparse::parse_header(header.as_bytes()).unwrap();
+        parsed.get_value()
+    }
+
+    #[test]
+    fn encoded_words() {
+        let text = "ä€😀x".repeat(10);
+        let encoded = encode_words(&text);
+        let mut joined = Vec::new();
+        for word in encoded.split(' ') {
+            assert!(word.len() <= 75, "{:?}", word);
+            let b64 = word
+                .strip_prefix("=?utf-8?B?")
+                .and_then(|w| w.strip_suffix("?="))
+                .unwrap();
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(b64)
+                .unwrap();
+            // Jedes Wort für sich gültiges UTF-8: keine Zeichen zerschnitten
+            assert!(std::str::from_utf8(&bytes).is_ok());
+            joined.extend(bytes);
+        }
+        assert!(encoded.contains(' '));
+        assert_eq!(String::from_utf8(joined).unwrap(), text);
+    }
+
+    #[test]
+    fn unstructured() {
+        assert_eq!(encode_unstructured("Hallo Welt"), "Hallo Welt");
+        let encoded = encode_unstructured("Grüsse aus Zürich");
+        assert!(encoded.starts_with("=?utf-8?B?"));
+        assert!(encoded.contains(" aus "));
+        assert_eq!(
+            decoded(&format!("Subject: {}", encoded)),
+            "Grüsse aus Zürich"
+        );
+        // Leerzeichen zwischen zwei kodierten Wörtern bleibt erhalten
+        assert_eq!(
+            decoded(&format!("Subject: {}", encode_unstructured("Jürg Müller"))),
+            "Jürg Müller"
+        );
+    }
+
+    #[test]
+    fn header_lines() {
+        let subject = "Überweisung für die Rechnung Nr. 4711 vom März, bitte bis Ende Monat prüfen und bestätigen";
+        let line = header_line("Subject", subject);
+        assert_folded(&line);
+        assert_eq!(decoded(&line), subject);
+
+        let line = header_line(
+            "To",
+            "Jürg Müller <jm@example.ch>, \"Hans, Meier\" <hm@example.ch>",
+        );
+        assert_folded(&line);
+        let unfolded = line.replace("\r\n ", " ");
+        assert!(unfolded.contains("<jm@example.ch>"));
+        assert!(unfolded.contains("\"Hans, Meier\" <hm@example.ch>"));
+
+        assert_eq!(
+            header_line("Message-ID", " <a.b@example.ch> "),
+            "Message-ID: <a.b@example.ch>\r\n"
+        );
+    }
+
+    #[test]
+    fn folding() {
+        assert_eq!(fold_header("Subject: kurz"), "Subject: kurz\r\n");
+        let long = format!("Subject: {}", "Wort ".repeat(40).trim_end());
+        let folded = fold_header(&long);
+        assert_folded(&folded);
+        assert_eq!(folded.replace("\r\n ", " ").trim_end(), long);
+        // Ein überlanges Wort lässt sich nicht falten, es bekommt eine eigene Zeile
+        let word = "x".repeat(100);
+        assert_eq!(
+            fold_header(&format!("References: {}", word)),
+            format!("References:\r\n {}\r\n", word)
+        );
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(rfc5322_date(0, 0), "Thu, 01 Jan 1970 00:00:00 +0000");
+        assert_eq!(
+            rfc5322_date(1_709_208_000, 3600),
+            "Thu, 29 Feb 2024 13:00:00 +0100"
+        );
+        assert_eq!(rfc5322_date(0, -12600), "Wed, 31 Dec 1969 20:30:00 -0330");
+        assert_eq!(
+            rfc5322_date(1_798_761_599, 0),
+            "Thu, 31 Dec 2026 23:59:59 +0000"
+        );
+    }
+
+    #[test]
+    fn rfc2231_params() {
+        assert_eq!(
+            encode_rfc2231_param("filename", "Bericht 2024.pdf"),
+            "filename=\"Bericht 2024.pdf\""
+        );
+        assert_eq!(
+            encode_rfc2231_param("filename", "Prüfung.pdf"),
+            "filename*=utf-8''Pr%C3%BCfung.pdf"
+        );
+        assert_eq!(
+            encode_rfc2231_param("filename", "a\"b.txt"),
+            "filename*=utf-8''a%22b.txt"
+        );
+    }
+
+    #[test]
+    fn rfc2231_continuations() {
+        for value in [
+            "Überweisungsbestätigung für März und April ÄÖÜ.pdf".to_string(),
+            "ü".repeat(60),
+            format!("{}.txt", "x".repeat(100)),
+        ] {
+            let encoded = encode_rfc2231_param("filename", &value);
+            let params: Vec<&str> = encoded.split(";\r\n ").collect();
+            assert!(params.len() > 1, "{:?}", encoded);
+            for (i, param) in params.iter().enumerate() {
+                assert!(param.len() <= PARAM_WIDTH, "{:?}", param);
+                assert!(param.starts_with(&format!("filename*{}*=", i)));
+                // Kein angeschnittenes Zeichen am Ende
+                let tail: Vec<char> = param.chars().rev().take(2).collect();
+                assert!(!tail.contains(&'%'), "{:?}", param);
+            }
+            assert!(params[0].starts_with("filename*0*=utf-8''"));
+
+            let header = format!("attachment;\r\n {}", encoded);
+            let disposition = mailparse::parse_content_disposition(&header);
+            assert_eq!(disposition.params.get("filename"), Some(&value));
+        }
+    }
+
+    #[test]
+    fn short_lines() {
+        round_trip("Hallo,\n\nkurzer Text.\n");
+        round_trip("ohne Umbruch am Ende");
+        round_trip("");
+    }
+
+    #[test]
+    fn long_lines() {
+        let long = "Wort ".repeat(40);
+        let long = long.trim_end();
+        round_trip(long);
+        round_trip(&format!("{}\n\n{}\n", long, long));
+        round_trip(&format!("Anfang\n{}\nEnde\n", "x".repeat(120)));
+        round_trip(&format!("{} {}\n", "x".repeat(100), "ü".repeat(100)));
+        round_trip(&format!(
+            "{}  doppelt  {}\n",
+            "a ".repeat(35),
+            "b ".repeat(35).trim_end()
+        ));
+    }
+
+    #[test]
+    fn soft_breaks_end_with_space() {
+        let encoded = encode_flowed(&"Wort ".repeat(40));
+        let lines: Vec<&str> = encoded.lines().collect();
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|l| l.chars().count() <= FLOWED_WIDTH));
+        assert!(lines[..lines.len() - 1].iter().all(|l| l.ends_with(' ')));
+        assert!(!lines[lines.len() - 1].ends_with(' '));
+    }
+
+    #[test]
+    fn quoted_lines() {
+        round_trip("> zitiert\n> > doppelt\n\nAntwort\n");
+        round_trip(&format!(
+            "> {}\n>\n> > {}\n",
+            "lang ".repeat(30).trim_end(),
+            "tief ".repeat(30).trim_end()
+        ));
+        assert_eq!(encode_flowed(">> doppelt"), ">> doppelt");
+    }
+
+    #[test]
+    fn stuffed_lines() {
+        round_trip(" eingerückt\n  doppelt eingerückt\n");
+        round_trip("From Berlin\nFromage\n");
+        assert_eq!(encode_flowed("From Berlin"), " From Berlin");
+        assert_eq!(encode_flowed(" x"), "  x");
+        // ">" am Zeilenanfang wäre sonst ein Zitat
+        assert_eq!(
+            decode_flowed(&encode_flowed(" >kein Zitat"), false),
+            " >kein Zitat"
+        );
+        round_trip(&format!("{} From hier\n", "a ".repeat(35).trim_end()));
+        round_trip(&format!("{} >pfeil\n", "a ".repeat(35).trim_end()));
+    }
+
+    #[test]
+    fn signature_separator() {
+        round_trip("Text\n-- \nName\n");
+        round_trip(&format!("{}\n-- \nName\n", "Wort ".repeat(30).trim_end()));
+        assert_eq!(encode_flowed("Text\n-- \nName"), "Text\n-- \nName");
+    }
+}
@@ -0,0 +1,241 @@
+use mailparse::{MailHeader, MailHeaderMap};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+// Ein Konto aus der msmtp-Konfiguration
+#[derive(Clone, Default)]
+pub struct Account {
+    pub name: String,
+    pub from: String,
+    // Alle übrigen Einstellungen (host, port, tls, auth, user, passwordeval, …) inkl. defaults
+    pub settings: HashMap<String, String>,
+}
+
+impl Account {
+    pub fn setting(&self, key: &str) -> Option<&str> {
+        self.settings.get(key).map(String::as_str)
+    }
+
+    // Reine Adresse aus "from" (auch "Name <adresse>"), kleingeschrieben
+    pub fn address(&self) -> Option<String> {
+        addresses_in(&self.from).into_iter().next()
+    }
+}
+
+fn addresses_in(value: &str) -> Vec<String> {
+    let Ok(list) = mailparse::addrparse(value) else {
+        return Vec::new();
+    };
+    list.iter()
+        .flat_map(|addr| match addr {
+            mailparse::MailAddr::Single(info) => vec![info.addr.to_lowercase()],
+            mailparse::MailAddr::Group(group) => {
+                group.addrs.iter().map(|i| i.addr.to_lowercase()).collect()
+            }
+        })
+        .collect()
+}
+
+fn config_path() -> Option<PathBuf> {
+    let home_rc = dirs::home_dir()?.join(".msmtprc");
+    if home_rc.exists() {
+        return Some(home_rc);
+    }
+    let xdg = dirs::config_dir()?.join("msmtp").join("config");
+    xdg.exists().then_some(xdg)
+}
+
+// Liest ~/.msmtprc bzw. ~/.config/msmtp/config
+pub fn read_accounts() -> (Vec<Account>, Option<String>) {
+    match config_path().and_then(|p| std::fs::read_to_string(p).ok()) {
+        Some(content) => parse_accounts(&content),
+        None => (Vec::new(), None),
+    }
+}
+
+// Werte aus "defaults" gelten für alle folgenden Konten, "account work : base, extra" erbt
+// zusätzlich von den zuvor definierten Konten base und extra. Zweiter Wert ist der Name
+// des Standardkontos ("account default : x").
+fn parse_accounts(content: &str) -> (Vec<Account>, Option<String>) {
+    let mut accounts: Vec<Account> = Vec::new();
+    let mut defaults: HashMap<String, String> = HashMap::new();
+    let mut in_defaults = false;
+    let mut default_account = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = match line.split_once(char::is_whitespace) {
+            Some((k, v)) => (k, v.trim()),
+            None => (line, ""),
+        };
+
+        match key {
+            "defaults" => in_defaults = true,
+            "account" => {
+                in_defaults = false;
+                // "account default : icloud" ist ein Alias, kein eigenes Konto
+                if let Some((alias, target)) = value.split_once(':')
+                    && alias.trim() == "default"
+                {
+                    default_account = Some(target.trim().to_string());
+                    continue;
+                }
+                let (name, parents) = value.split_once(':').unwrap_or((value, ""));
+                let mut settings = defaults.clone();
+                for parent in parents.split(',').map(str::trim) {
+                    if let Some(parent) = accounts.iter().find(|a| a.name == parent) {
+                        settings.extend(parent.settings.clone());
+                    }
+                }
+                accounts.push(Account {
+                    name: name.trim().to_string(),
+                    from: settings.get("from").cloned().unwrap_or_default(),
+                    settings,
+                });
+            }
+            _ if in_defaults => {
+                defaults.insert(key.to_string(), value.to_string());
+            }
+            _ => {
+                if let Some(account) = accounts.last_mut() {
+                    if key == "from" {
+                        account.from = value.to_string();
+                    }
+                    account.settings.insert(key.to_string(), value.to_string());
+                }
+            }
+        }
+    }
+
+    (accounts, default_account)
+}
+
+// Absender-Identitäten für den Composer: alle Konten mit "from", das Standardkonto zuerst
+pub fn identities() -> Vec<Account> {
+    let (accounts, default_account) = read_accounts();
+    let default_name = default_account.as_deref().unwrap_or("default");
+    let mut identities: Vec<Account> = accounts
+        .into_iter()
+        .filter(|a| !a.from.is_empty())
+        .collect();
+    if let Some(pos) = identities.iter().position(|a| a.name == default_name) {
+        let default = identities.remove(pos);
+        identities.insert(0, default);
+    }
+    identities
+}
+
+// Konto mit der gegebenen Absenderadresse (z.B. From eines Entwurfs); leer, wenn keins passt
+pub fn account_for_address(value: &str) -> String {
+    let wanted = addresses_in(value);
+    identities()
+        .into_iter()
+        .find(|a| a.address().is_some_and(|addr| wanted.contains(&addr)))
+        .map(|a| a.name)
+        .unwrap_or_default()
+}
+
+// Konto, an dessen Adresse eine empfangene Mail ging: Delivered-To und X-Original-To
+// (vom eigenen Server gesetzt) vor To und Cc. Leer, wenn keine eigene Adresse vorkommt.
+pub fn account_for_mail(headers: &[MailHeader]) -> String {
+    let identities = identities();
+    ["Delivered-To", "X-Original-To", "To", "Cc"]
+        .iter()
+        .flat_map(|name| headers.get_all_values(name))
+        .flat_map(|value| addresses_in(&value))
+        .find_map(|addr| {
+            identities
+                .iter()
+                .find(|a| a.address().as_deref() == Some(addr.as_str()))
+        })
+        .map(|a| a.name.clone())
+        .unwrap_or_default()
+}
+
+// Alle eigenen Absenderadressen, kleingeschrieben (für "Allen antworten")
+pub fn own_addresses() -> Vec<String> {
+    read_accounts()
+        .0
+        .iter()
+        .flat_map(|a| addresses_in(&a.from))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "\
+# Kommentar
+defaults
+auth on
+tls on
+
+account base
+host mail.example.org
+port 587
+from Hans <hans@example.org>
+
+account extra
+tls_certcheck off
+
+account work : base, extra
+from hans@work.example
+user hans
+
+account plain
+host smtp.example.ch
+auth off
+
+account default : work
+";
+
+    #[test]
+    fn defaults_and_accounts() {
+        let (accounts, default) = parse_accounts(CONFIG);
+        let names: Vec<&str> = accounts.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["base", "extra", "work", "plain"]);
+        assert_eq!(default.as_deref(), Some("work"));
+
+        let base = &accounts[0];
+        assert_eq!(base.from, "Hans <hans@example.org>");
+        assert_eq!(base.address().as_deref(), Some("hans@example.org"));
+        assert_eq!(base.setting("auth"), Some("on"));
+        assert_eq!(base.setting("port"), Some("587"));
+
+        let plain = &accounts[3];
+        assert_eq!(plain.setting("auth"), Some("off"));
+        assert_eq!(plain.setting("tls"), Some("on"));
+        assert_eq!(plain.setting("port"), None);
+        assert!(plain.from.is_empty());
+    }
+
+    #[test]
+    fn inheritance() {
+        let (accounts, _) = parse_accounts(CONFIG);
+        let work = &accounts[2];
+        assert_eq!(work.name, "work");
+        assert_eq!(work.setting("host"), Some("mail.example.org"));
+        assert_eq!(work.setting("port"), Some("587"));
+        assert_eq!(work.setting("tls_certcheck"), Some("off"));
+        assert_eq!(work.setting("auth"), Some("on"));
+        assert_eq!(work.setting("user"), Some("hans"));
+        // Eigene Werte gehen den geerbten vor
+        assert_eq!(work.from, "hans@work.example");
+        assert_eq!(work.setting("from"), Some("hans@work.example"));
+    }
+
+    #[test]
+    fn inherited_from() {
+        let (accounts, _) = parse_accounts("account a\nfrom a@example.org\naccount b:a\nhost x\n");
+        assert_eq!(accounts[1].name, "b");
+        assert_eq!(accounts[1].from, "a@example.org");
+        // Unbekannte Eltern werden ignoriert
+        let (accounts, _) = parse_accounts("account c : fehlt\nhost y\n");
+        assert_eq!(accounts[0].name, "c");
+        assert_eq!(accounts[0].setting("host"), Some("y"));
+    }
+}
@@ -0,0 +1,660 @@
+use crate::composer::MailDraft;
+use crate::db;
+use crate::drafts;
+use crate::maildir;
+use crate::mime;
+use crate::msmtprc;
+use crate::smtp;
+use mailparse::MailHeaderMap;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+// ~/.Mail/Outbox: fertige Mails in new/, der Umschlag (alle Empfänger inkl. Bcc) liegt
+// unter gleichem Namen in envelope/, damit kein Bcc-Header in der Mail stehen muss.
+// Das gewählte msmtp-Konto steht ebenso unter gleichem Namen in account/.
+fn outbox_path() -> PathBuf {
+    dirs::home_dir().unwrap().join(".Mail").join("Outbox")
+}
+
+// Legt die Mail zum Versand ab und gibt den Pfad der Maildatei zurück. Ohne Konto
+// (leer) versendet msmtp über sein Standardkonto. Vor `send_at` (Unix-Zeit) gibt der
+// Worker die Mail nicht frei: Rückgängig-Frist oder geplanter Versand.
+pub fn enqueue(
+    raw_mail: &str,
+    envelope: &[String],
+    account: &str,
+    send_at: i64,
+) -> io::Result<PathBuf> {
+    let outbox = outbox_path();
+    let envelope_dir = outbox.join("envelope");
+    let account_dir = outbox.join("account");
+    std::fs::create_dir_all(&envelope_dir)?;
+    std::fs::create_dir_all(&account_dir)?;
+
+    let name = maildir::unique_name();
+
+    // Umschlag zuerst: ein Versand-Skript darf die Mail nie ohne ihre Bcc-Empfänger sehen
+    let mut envelope_text = envelope.join("\n");
+    envelope_text.push('\n');
+    std::fs::write(envelope_dir.join(&name), envelope_text)?;
+    if !account.is_empty() {
+        std::fs::write(account_dir.join(&name), format!("{}\n", account))?;
+    }
+
+    // Auch der Zeitpunkt muss stehen, bevor ein Worker die Mail in new/ findet
+    let state = db::OutboxState {
+        next_attempt: send_at,
+        ..Default::default()
+    };
+    db::set_outbox_state(&name, &state).map_err(io::Error::other)?;
+
+    maildir::deliver_as(&outbox, &name, raw_mail.as_bytes(), None)
+}
+
+// Sendmail-kompatibler Befehl für den Versand, überschreibbar mit $NOXMAIL_SENDMAIL.
+// Mit NOXMAIL_TRANSPORT=smtp versendet nox selbst über den Server aus der msmtprc.
+const DEFAULT_SENDMAIL: &str = "msmtp";
+// Rückgängig-Frist nach "Senden" in Sekunden, überschreibbar mit $NOXMAIL_UNDO_SECONDS
+const DEFAULT_UNDO_DELAY: i64 = 30;
+// Nach so vielen Fehlversuchen wird nicht mehr automatisch wiederholt
+const MAX_ATTEMPTS: i64 = 8;
+// Wartezeit nach dem ersten Fehlschlag; verdoppelt sich bis MAX_BACKOFF_SECS
+const BASE_BACKOFF_SECS: i64 = 60;
+const MAX_BACKOFF_SECS: i64 = 6 * 60 * 60;
+// Fehlertext gesendeter Mails, die nicht aus new/ entfernt werden konnten
+const ALREADY_SENT: &str = "Bereits gesendet, aber nicht abgelegt";
+// Ein hängender Sendmail-Befehl wird nach dieser Zeit abgebrochen
+const SUBMIT_TIMEOUT_SECS: u64 = 5 * 60;
+
+pub struct QueuedMail {
+    pub name: String,
+    pub path: PathBuf,
+    pub subject: String,
+    pub recipients: Vec<String>,
+    pub state: db::OutboxState,
+}
+
+#[derive(Default)]
+pub struct SendReport {
+    pub sent: usize,
+    // (Betreff, Fehler) der in diesem Durchlauf fehlgeschlagenen Mails
+    pub errors: Vec<(String, String)>,
+}
+
+fn sidecar(dir: &str, name: &str) -> PathBuf {
+    outbox_path().join(dir).join(name)
+}
+
+pub fn undo_delay() -> i64 {
+    std::env::var("NOXMAIL_UNDO_SECONDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_UNDO_DELAY)
+}
+
+pub fn now_secs() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+// Alle Mails in Outbox/new mit ihrem Versandstand, älteste zuerst
+pub fn queued_mails() -> Vec<QueuedMail> {
+    let Ok(entries) = std::fs::read_dir(outbox_path().join("new")) else {
+        return Vec::new();
+    };
+    let states = db::get_outbox_states().unwrap_or_default();
+
+    let mut mails: Vec<QueuedMail> = entries
+        .flatten()
+        .filter(|e| e.path().is_file())
+        .filter_map(|e| {
+            e.file_name()
+                .into_string()
+                .ok()
+                .map(|name| (name, e.path()))
+        })
+        .map(|(name, path)| {
+            let subject = std::fs::read(&path)
+                .ok()
+                .and_then(|data| {
+                    mailparse::parse_headers(&data)
+                        .ok()
+                        .and_then(|(headers, _)| headers.get_first_value("Subject"))
+                })
+                .unwrap_or_default();
+            let recipients = std::fs::read_to_string(sidecar("envelope", &name))
+                .map(|text| text.lines().map(str::to_string).collect())
+                .unwrap_or_default();
+            QueuedMail {
+                state: states.get(&name).cloned().unwrap_or_default(),
+                name,
+                path,
+                subject,
+                recipients,
+            }
+        })
+        .collect();
+    // Maildir-Namen beginnen mit der Zustellzeit; bei Gleichstand entscheidet der Zähler
+    let order = |name: &str| -> (u64, u64) {
+        let mut parts = name.split(['.', '_']);
+        let secs = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+        let seq = parts.nth(1).and_then(|s| s.parse().ok()).unwrap_or(0);
+        (secs, seq)
+    };
+    mails.sort_by_key(|m| (order(&m.name), m.name.clone()));
+    mails
+}
+
+// Gibt es etwas zu tun? Billig genug für einen Timer im Hauptthread.
+pub fn has_due_mails() -> bool {
+    let now = now_secs();
+    queued_mails()
+        .iter()
+        .any(|m| !m.state.failed && m.state.next_attempt <= now)
+}
+
+// Alle fälligen Mails versenden. Ein zweiter gleichzeitiger Lauf (App und
+// `noxmail send-queue`) findet die Sperrdatei und tut nichts.
+pub fn send_due_mails() -> SendReport {
+    let mut report = SendReport::default();
+    let Some(_lock) = QueueLock::acquire() else {
+        return report;
+    };
+
+    // Gesendete Mails, deren Ablage in Sent beim letzten Lauf scheiterte
+    migrate_old_sent();
+
+    let now = now_secs();
+    for mail in queued_mails() {
+        if mail.state.failed || mail.state.next_attempt > now {
+            continue;
+        }
+        match submit(&mail) {
+            Ok(()) => {
+                report.sent += 1;
+                // Zuerst raus aus new/, damit die Mail auf keinen Fall nochmals versendet wird
+                if let Err(e) = mark_sent(&mail) {
+                    eprintln!("Gesendete Mail {} nicht abgelegt: {}", mail.name, e);
+                    let state = db::OutboxState {
+                        attempts: mail.state.attempts,
+                        next_attempt: now,
+                        last_error: format!("{}: {}", ALREADY_SENT, e),
+                        failed: true,
+                    };
+                    if let Err(e) = db::set_outbox_state(&mail.name, &state) {
+                        eprintln!("Versandstatus nicht gespeichert: {}", e);
+                    }
+                }
+            }
+            Err(smtp::SmtpError { message, permanent }) => {
+                let state = failed_attempt(&mail.state, now, message.clone(), permanent);
+                if let Err(e) = db::set_outbox_state(&mail.name, &state) {
+                    eprintln!("Versandstatus nicht gespeichert: {}", e);
+                }
+                report.errors.push((mail.subject.clone(), message));
+            }
+        }
+    }
+    report
+}
+
+// Stand nach einem Fehlversuch: nächster Versuch mit wachsendem Abstand, aufgegeben nach
+// MAX_ATTEMPTS Versuchen oder sofort bei einem endgültigen Fehler
+fn failed_attempt(
+    previous: &db::OutboxState,
+    now: i64,
+    message: String,
+    permanent: bool,
+) -> db::OutboxState {
+    let attempts = previous.attempts + 1;
+    let backoff = (BASE_BACKOFF_SECS << (attempts - 1).min(16)).min(MAX_BACKOFF_SECS);
+    db::OutboxState {
+        attempts,
+        next_attempt: now + backoff,
+        last_error: message,
+        failed: permanent || attempts >= MAX_ATTEMPTS,
+    }
+}
+
+fn submit(mail: &QueuedMail) -> Result<(), smtp::SmtpError> {
+    if std::env::var("NOXMAIL_TRANSPORT").is_ok_and(|t| t == "smtp") {
+        return submit_smtp(mail);
+    }
+    // Fehler des Sendmail-Befehls gelten immer als vorübergehend
+    submit_sendmail(mail).map_err(|message| smtp::SmtpError {
+        message,
+        permanent: false,
+    })
+}
+
+// Direkt per SMTP über das gewählte Konto, sonst das Standardkonto
+fn submit_smtp(mail: &QueuedMail) -> Result<(), smtp::SmtpError> {
+    let permanent = |message: String| smtp::SmtpError {
+        message,
+        permanent: true,
+    };
+    let data = std::fs::read(&mail.path).map_err(|e| permanent(e.to_string()))?;
+
+    let account_name = std::fs::read_to_string(sidecar("account", &mail.name)).unwrap_or_default();
+    let identities = msmtprc::identities();
+    let account = identities
+        .iter()
+        .find(|a| a.name == account_name.trim())
+        .or(identities.first())
+        .ok_or_else(|| permanent("Kein Konto mit from in der msmtprc".to_string()))?;
+    let from = account
+        .address()
+        .ok_or_else(|| permanent(format!("Ungültige from-Adresse: {}", account.from)))?;
+
+    // Ohne Umschlag (ältere Mails) aus den Headern
+    let recipients = if mail.recipients.is_empty() {
+        let parsed = mailparse::parse_headers(&data)
+            .map_err(|e| permanent(e.to_string()))?
+            .0;
+        let mut recipients = Vec::new();
+        for name in ["To", "Cc", "Bcc"] {
+            for value in parsed.get_all_values(name) {
+                recipients.extend(mime::envelope_addresses(&value).map_err(permanent)?);
+            }
+        }
+        recipients
+    } else {
+        mail.recipients.clone()
+    };
+
+    smtp::send(account, &from, &recipients, &data)
+}
+
+// Mail an den Sendmail-Befehl übergeben. msmtp bekommt das gewählte Konto (-a), andere
+// Programme die Absenderadresse des Kontos (-f).
+fn submit_sendmail(mail: &QueuedMail) -> Result<(), String> {
+    let data = std::fs::read(&mail.path).map_err(|e| e.to_string())?;
+    let command =
+        std::env::var("NOXMAIL_SENDMAIL").unwrap_or_else(|_| DEFAULT_SENDMAIL.to_string());
+    let mut parts = command.split_whitespace();
+    let program = parts.next().ok_or("Kein Sendmail-Befehl konfiguriert")?;
+
+    let mut cmd = std::process::Command::new(program);
+    cmd.args(parts).arg("-i");
+
+    let account = std::fs::read_to_string(sidecar("account", &mail.name)).unwrap_or_default();
+    let account = account.trim();
+    if !account.is_empty() {
+        if Path::new(program).file_name().is_some_and(|n| n == "msmtp") {
+            cmd.args(["-a", account]);
+        } else if let Some(from) = msmtprc::identities()
+            .iter()
+            .find(|a| a.name == account)
+            .and_then(|a| a.address())
+        {
+            cmd.args(["-f", &from]);
+        }
+    }
+
+    // Ohne Umschlag (ältere Mails) liest der Befehl die Empfänger aus dem Header
+    if mail.recipients.is_empty() {
+        cmd.arg("-t");
+    } else {
+        cmd.arg("--").args(&mail.recipients);
+    }
+
+    let mut child = cmd
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("{} konnte nicht gestartet werden: {}", program, e))?;
+
+    // Schreiben und Lesen in eigenen Threads, damit ein hängender Befehl nach
+    // SUBMIT_TIMEOUT_SECS abgebrochen werden kann und die Sperre nicht ewig hält
+    let stdin = child.stdin.take();
+    let writer = std::thread::spawn(move || -> io::Result<()> {
+        match stdin {
+            Some(mut stdin) => stdin.write_all(&data),
+            None => Ok(()),
+        }
+    });
+    let stderr = child.stderr.take();
+    let reader = std::thread::spawn(move || {
+        let mut text = String::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_string(&mut text);
+        }
+        text
+    });
+
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(SUBMIT_TIMEOUT_SECS);
+    let status = loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break status,
+            None if std::time::Instant::now() < deadline => {
+                std::thread::sleep(std::time::Duration::from_millis(200));
+            }
+            None => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!(
+                    "{} nach {} s ohne Antwort abgebrochen",
+                    program, SUBMIT_TIMEOUT_SECS
+                ));
+            }
+        }
+    };
+    let written = writer.join().unwrap_or(Ok(()));
+    let stderr = reader.join().unwrap_or_default();
+    if status.success() {
+        return written.map_err(|e| e.to_string());
+    }
+
+    let message = stderr
+        .lines()
+        .rev()
+        .find(|l| !l.trim().is_empty())
+        .map(|l| l.trim().to_string())
+        .unwrap_or_else(|| status.to_string());
+    Err(message)
+}
+
+fn sent_dir() -> PathBuf {
+    dirs::home_dir().unwrap().join(".Mail").join("Sent")
+}
+
+// Gesendete Mail nach Sent/cur und den Index nachführen
+fn store_sent(path: &Path) -> io::Result<PathBuf> {
+    let target = file_sent(path, &sent_dir())?;
+    if let Err(e) = db::relocate_message(path, &target, "Sent") {
+        eprintln!("Fehler beim Aktualisieren des Nachrichten-Index: {}", e);
+    }
+    Ok(target)
+}
+
+// Legt die Datei unter einem Maildir-Namen mit Flag S (gelesen) in `sent`/cur ab
+fn file_sent(path: &Path, sent: &Path) -> io::Result<PathBuf> {
+    std::fs::create_dir_all(sent.join("cur"))?;
+    let unique = maildir::unique_name();
+    let renamed = sent.join("cur").join(format!("{}:2,S", unique));
+    // Die Datei ist bereits vollständig geschrieben; nur wenn Sent auf einem anderen
+    // Dateisystem liegt, wird sie neu zugestellt
+    match std::fs::rename(path, &renamed) {
+        Ok(()) => Ok(renamed),
+        Err(_) => {
+            let target = maildir::deliver_as(sent, &unique, &std::fs::read(path)?, Some("S"))?;
+            std::fs::remove_file(path)?;
+            Ok(target)
+        }
+    }
+}
+
+// Empfänger (To, Cc mit Anzeigenamen, Bcc aus dem Umschlag) ins Adressbuch
+fn harvest_recipients(path: &Path, envelope: &[String]) {
+    let mut contacts = std::collections::HashMap::new();
+    if let Ok(content) = std::fs::read(path)
+        && let Ok((headers, _)) = mailparse::parse_headers(&content)
+    {
+        for header in headers
+            .get_all_headers("To")
+            .into_iter()
+            .chain(headers.get_all_headers("Cc"))
+        {
+            let Ok(list) = mailparse::addrparse_header(header) else {
+                continue;
+            };
+            for addr in list.iter() {
+                let singles = match addr {
+                    mailparse::MailAddr::Single(info) => vec![info],
+                    mailparse::MailAddr::Group(group) => group.addrs.iter().collect(),
+                };
+                for info in singles {
+                    contacts.insert(
+                        info.addr.to_lowercase(),
+                        (info.display_name.clone().unwrap_or_default(), None),
+                    );
+                }
+            }
+        }
+    }
+    for rcpt in envelope {
+        contacts
+            .entry(rcpt.to_lowercase())
+            .or_insert_with(|| (String::new(), None));
+    }
+    if let Err(e) = db::bulk_upsert(&contacts) {
+        eprintln!("Fehler beim Speichern der Kontakte: {}", e);
+    }
+}
+
+// Gesendet: sofort nach Outbox/cur (gleiches Verzeichnis, kann kaum scheitern), dann
+// Empfänger merken, nach Sent/cur ablegen, Umschlag und Konto-Datei entfernen. Scheitert
+// die Ablage in Sent, holt migrate_old_sent sie später nach.
+fn mark_sent(mail: &QueuedMail) -> io::Result<()> {
+    let sent_path = outbox_path().join("cur").join(&mail.name);
+    std::fs::create_dir_all(outbox_path().join("cur"))?;
+    std::fs::rename(&mail.path, &sent_path)?;
+    let _ = db::clear_outbox_state(&mail.name);
+
+    harvest_recipients(&sent_path, &mail.recipients);
+    remove_sidecars(&mail.name);
+    if let Err(e) = store_sent(&sent_path) {
+        eprintln!("{} nicht nach Sent verschoben: {}", sent_path.display(), e);
+    }
+    Ok(())
+}
+
+// Gesendete Mails in Outbox/cur: nicht abgelegte aus mark_sent und die .nox-Dateien
+// früherer Versionen
+pub fn migrate_old_sent() {
+    for path in sent_leftovers(&outbox_path()) {
+        if let Err(e) = store_sent(&path) {
+            eprintln!("{} nicht nach Sent verschoben: {}", path.display(), e);
+        }
+    }
+}
+
+fn sent_leftovers(outbox: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(outbox.join("cur")) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .collect()
+}
+
+fn remove_sidecars(name: &str) {
+    let _ = std::fs::remove_file(sidecar("envelope", name));
+    let _ = std::fs::remove_file(sidecar("account", name));
+    let _ = db::clear_outbox_state(name);
+}
+
+// Fehlgeschlagene oder wartende Mail sofort wieder versuchen, mit frischem Backoff
+pub fn retry(name: &str) -> Result<(), String> {
+    let states = db::get_outbox_states().map_err(|e| e.to_string())?;
+    if states
+        .get(name)
+        .is_some_and(|s| s.last_error.starts_with(ALREADY_SENT))
+    {
+        return Err("Die Mail wurde bereits gesendet".to_string());
+    }
+    db::clear_outbox_state(name).map_err(|e| e.to_string())
+}
+
+// Rückgängig: die Mail verlässt den Postausgang wieder und kommt als MailDraft (mit Bcc
+// aus dem Umschlag und dem gewählten Konto) zurück in den Composer
+pub fn take_back(name: &str) -> Result<MailDraft, String> {
+    let _lock = QueueLock::acquire().ok_or("Der Versand läuft gerade, bitte nochmals versuchen")?;
+    let path = outbox_path().join("new").join(name);
+    if !path.exists() {
+        return Err("Die Mail wurde bereits gesendet".to_string());
+    }
+
+    let mut draft = drafts::load_draft(&path)?;
+    let visible: Vec<String> = [&draft.to, &draft.cc]
+        .iter()
+        .flat_map(|v| mime::envelope_addresses(v).unwrap_or_default())
+        .map(|a| a.to_lowercase())
+        .collect();
+    let envelope = std::fs::read_to_string(sidecar("envelope", name)).unwrap_or_default();
+    draft.bcc = envelope
+        .lines()
+        .filter(|a| !a.is_empty() && !visible.contains(&a.to_lowercase()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let account = std::fs::read_to_string(sidecar("account", name)).unwrap_or_default();
+    if !account.trim().is_empty() {
+        draft.account = account.trim().to_string();
+    }
+
+    std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+    remove_sidecars(name);
+    Ok(draft)
+}
+
+// Versand abbrechen: die Mail landet im Papierkorb statt im Versand
+pub fn cancel(name: &str) -> io::Result<()> {
+    // Nicht mitten in einem Versand: die Mail könnte gerade übertragen werden
+    let _lock = QueueLock::acquire()
+        .ok_or_else(|| io::Error::other("Der Versand läuft gerade, bitte nochmals versuchen"))?;
+    let source = outbox_path().join("new").join(name);
+    let trash_cur = dirs::home_dir()
+        .unwrap()
+        .join(".Mail")
+        .join("TRASH")
+        .join("cur");
+    std::fs::create_dir_all(&trash_cur)?;
+    let target = trash_cur.join(format!("{}:2,S", name));
+    std::fs::rename(&source, &target)?;
+    if let Err(e) = db::relocate_message(&source, &target, "TRASH") {
+        eprintln!("Fehler beim Aktualisieren des Nachrichten-Index: {}", e);
+    }
+    remove_sidecars(name);
+    Ok(())
+}
+
+// Sperre auf Outbox/.sending (flock). Das System gibt sie frei, sobald die Datei
+// geschlossen wird, auch wenn der Prozess abstürzt; eine verwaiste Sperre gibt es nicht.
+// Die Datei selbst bleibt liegen, sonst könnten zwei Läufe verschiedene Dateien sperren.
+struct QueueLock {
+    _file: std::fs::File,
+}
+
+impl QueueLock {
+    fn acquire() -> Option<QueueLock> {
+        let path = outbox_path().join(".sending");
+        let _ = std::fs::create_dir_all(outbox_path());
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .ok()?;
+        file.try_lock().ok()?;
+        Some(QueueLock { _file: file })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Eigenes Verzeichnis pro Test unter dem Temp-Verzeichnis
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nox-outbox-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn files_in(dir: &Path) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+            .map(|entries| entries.flatten().map(|e| e.path()).collect())
+            .unwrap_or_default();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn backoff_schedule() {
+        let now = 1_000_000;
+        let mut state = db::OutboxState::default();
+        let mut waits = Vec::new();
+        for attempt in 1..=MAX_ATTEMPTS {
+            state = failed_attempt(&state, now, format!("Fehler {}", attempt), false);
+            assert_eq!(state.attempts, attempt);
+            assert_eq!(state.last_error, format!("Fehler {}", attempt));
+            assert_eq!(state.failed, attempt == MAX_ATTEMPTS);
+            waits.push(state.next_attempt - now);
+        }
+        assert_eq!(waits, [60, 120, 240, 480, 960, 1920, 3840, 7680]);
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let previous = db::OutboxState {
+            attempts: 40,
+            ..Default::default()
+        };
+        let state = failed_attempt(&previous, 0, String::new(), false);
+        assert_eq!(state.next_attempt, MAX_BACKOFF_SECS);
+        assert!(state.failed);
+    }
+
+    #[test]
+    fn permanent_errors_give_up() {
+        let state = failed_attempt(&db::OutboxState::default(), 0, "550".to_string(), true);
+        assert_eq!(state.attempts, 1);
+        assert!(state.failed);
+    }
+
+    #[test]
+    fn sent_mails_move_to_sent() {
+        let outbox = temp_dir("migrate");
+        let sent = outbox.join("Sent");
+
+        // Gesendet und aus new/ genommen (wie mark_sent), dazu eine .nox-Datei älterer
+        // Versionen; die wartende Mail in new/ bleibt liegen
+        let queued =
+            maildir::deliver_as(&outbox, "1.1_1.host", b"Subject: wartet\n\n", None).unwrap();
+        let done =
+            maildir::deliver_as(&outbox, "1.1_2.host", b"Subject: gesendet\n\n", None).unwrap();
+        std::fs::rename(&done, outbox.join("cur").join("1.1_2.host")).unwrap();
+        std::fs::write(outbox.join("cur").join("alt.nox"), b"Subject: alt\n\n").unwrap();
+
+        let leftovers = sent_leftovers(&outbox);
+        assert_eq!(leftovers.len(), 2);
+        for path in &leftovers {
+            let target = file_sent(path, &sent).unwrap();
+            assert!(target.starts_with(sent.join("cur")));
+            assert!(target.to_string_lossy().ends_with(":2,S"));
+        }
+
+        assert!(sent_leftovers(&outbox).is_empty());
+        assert_eq!(files_in(&outbox.join("new")), [queued]);
+        let mut contents: Vec<Vec<u8>> = files_in(&sent.join("cur"))
+            .iter()
+            .map(|p| std::fs::read(p).unwrap())
+            .collect();
+        contents.sort();
+        assert_eq!(
+            contents,
+            [
+                b"Subject: alt\n\n".to_vec(),
+                b"Subject: gesendet\n\n".to_vec()
+            ]
+        );
+        assert!(files_in(&sent.join("tmp")).is_empty());
+
+        std::fs::remove_dir_all(&outbox).unwrap();
+    }
+
+    #[test]
+    fn missing_file_is_not_filed() {
+        let outbox = temp_dir("missing");
+        let sent = outbox.join("Sent");
+        assert!(file_sent(&outbox.join("cur").join("fehlt"), &sent).is_err());
+        assert!(files_in(&sent.join("cur")).is_empty());
+        std::fs::remove_dir_all(&outbox).unwrap();
+    }
+}
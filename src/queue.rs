@@ -0,0 +1,193 @@
+use crate::outbox;
+use gtk4::prelude::*;
+use gtk4::{
+    Application, ApplicationWindow, Box, Button, HeaderBar, Label, ListBox, Orientation,
+    ScrolledWindow,
+};
+use std::rc::Rc;
+
+// Postausgang: wartende und fehlgeschlagene Mails mit "Erneut versuchen" und "Abbrechen"
+pub fn open_queue_window(app: &Application) {
+    let list_box = ListBox::builder()
+        .selection_mode(gtk4::SelectionMode::None)
+        .build();
+    let status_label = Label::builder()
+        .halign(gtk4::Align::Start)
+        .margin_start(10)
+        .margin_end(10)
+        .margin_top(5)
+        .margin_bottom(5)
+        .build();
+
+    type RenderFn = Rc<dyn Fn()>;
+    let render: Rc<std::cell::RefCell<Option<RenderFn>>> = Rc::default();
+    let render_weak = Rc::downgrade(&render);
+
+    {
+        let list_box = list_box.clone();
+        let status_label = status_label.clone();
+        let app = app.clone();
+        *render.borrow_mut() = Some(Rc::new(move || {
+            while let Some(child) = list_box.first_child() {
+                list_box.remove(&child);
+            }
+
+            let mails = outbox::queued_mails();
+            let failed = mails.iter().filter(|m| m.state.failed).count();
+            status_label.set_label(&match (mails.len(), failed) {
+                (0, _) => "Postausgang ist leer".to_string(),
+                (n, 0) => format!("{} Mail(s) warten auf den Versand", n),
+                (n, f) => format!("{} Mail(s) im Postausgang, {} fehlgeschlagen", n, f),
+            });
+
+            for mail in mails {
+                let row_box = Box::builder()
+                    .orientation(Orientation::Horizontal)
+                    .spacing(10)
+                    .margin_start(10)
+                    .margin_end(10)
+                    .margin_top(5)
+                    .margin_bottom(5)
+                    .build();
+
+                let text_box = Box::builder()
+                    .orientation(Orientation::Vertical)
+                    .hexpand(true)
+                    .build();
+                let subject = if mail.subject.is_empty() {
+                    "(kein Betreff)"
+                } else {
+                    &mail.subject
+                };
+                text_box.append(
+                    &Label::builder()
+                        .label(subject)
+                        .xalign(0.0)
+                        .ellipsize(gtk4::pango::EllipsizeMode::End)
+                        .build(),
+                );
+                text_box.append(
+                    &Label::builder()
+                        .label(mail.recipients.join(", "))
+                        .xalign(0.0)
+                        .css_classes(["dim-label"])
+                        .ellipsize(gtk4::pango::EllipsizeMode::End)
+                        .build(),
+                );
+                text_box.append(&state_label(&mail.state));
+                row_box.append(&text_box);
+
+                let btn_retry = Button::from_icon_name("view-refresh-symbolic");
+                btn_retry.set_tooltip_text(Some("Erneut versuchen / jetzt senden"));
+                btn_retry.set_valign(gtk4::Align::Center);
+                let name_retry = mail.name.clone();
+                let app_retry = app.clone();
+                let status_retry = status_label.clone();
+                btn_retry.connect_clicked(move |_| {
+                    if let Err(e) = outbox::retry(&name_retry) {
+                        status_retry.set_label(&format!("Fehler: {}", e));
+                        return;
+                    }
+                    app_retry.activate_action("send-queue", None);
+                });
+                row_box.append(&btn_retry);
+
+                let btn_cancel = Button::from_icon_name("process-stop-symbolic");
+                btn_cancel.set_tooltip_text(Some("Versand abbrechen (Mail in den Papierkorb)"));
+                btn_cancel.set_valign(gtk4::Align::Center);
+                let name_cancel = mail.name.clone();
+                let status_cancel = status_label.clone();
+                let render_cancel = render_weak.clone();
+                btn_cancel.connect_clicked(move |_| {
+                    if let Err(e) = outbox::cancel(&name_cancel) {
+                        status_cancel.set_label(&format!("Abbrechen fehlgeschlagen: {}", e));
+                        return;
+                    }
+                    if let Some(render) = render_cancel.upgrade()
+                        && let Some(render) = render.borrow().as_ref()
+                    {
+                        render();
+                    }
+                });
+                row_box.append(&btn_cancel);
+
+                list_box.append(&row_box);
+            }
+        }));
+    }
+
+    if let Some(render) = render.borrow().as_ref() {
+        render();
+    }
+
+    let vbox = Box::builder().orientation(Orientation::Vertical).build();
+    vbox.append(
+        &ScrolledWindow::builder()
+            .child(&list_box)
+            .vexpand(true)
+            .build(),
+    );
+    vbox.append(&status_label);
+
+    let window = ApplicationWindow::builder()
+        .application(app)
+        .title("Postausgang")
+        .default_width(550)
+        .default_height(400)
+        .child(&vbox)
+        .build();
+    window.set_titlebar(Some(&HeaderBar::new()));
+
+    // Der Versand läuft im Hintergrund weiter; die Liste folgt ihm
+    let window_weak = window.downgrade();
+    gtk4::glib::timeout_add_seconds_local(3, move || {
+        if window_weak.upgrade().is_none() {
+            return gtk4::glib::ControlFlow::Break;
+        }
+        if let Some(render) = render.borrow().as_ref() {
+            render();
+        }
+        gtk4::glib::ControlFlow::Continue
+    });
+
+    window.present();
+}
+
+fn state_label(state: &crate::db::OutboxState) -> Label {
+    let (text, css) = if state.failed {
+        (
+            format!(
+                "Fehlgeschlagen (Versuch {}): {}",
+                state.attempts, state.last_error
+            ),
+            "error",
+        )
+    } else if state.attempts > 0 {
+        let next = gtk4::glib::DateTime::from_unix_local(state.next_attempt)
+            .and_then(|dt| dt.format("%H:%M"))
+            .map(|s| s.to_string())
+            .unwrap_or_default();
+        (
+            format!(
+                "Versuch {} fehlgeschlagen ({}), nächster um {}",
+                state.attempts, state.last_error, next
+            ),
+            "warning",
+        )
+    } else if state.next_attempt > outbox::now_secs() {
+        // Später senden oder noch innerhalb der Frist für "Rückgängig"
+        let at = gtk4::glib::DateTime::from_unix_local(state.next_attempt)
+            .and_then(|dt| dt.format("%d.%m. %H:%M"))
+            .map(|s| s.to_string())
+            .unwrap_or_default();
+        (format!("Geplant für {}", at), "dim-label")
+    } else {
+        ("Wartet auf den Versand".to_string(), "dim-label")
+    };
+    Label::builder()
+        .label(text)
+        .xalign(0.0)
+        .wrap(true)
+        .css_classes([css])
+        .build()
+}
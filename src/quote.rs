@@ -0,0 +1,107 @@
+use crate::mime;
+use crate::signature;
+use gtk4::prelude::*;
+
+// Zeile über dem Zitat einer Antwort. Eigene Vorlagen stehen als "attribution" in
+// ~/.config/noxmail/signatures (pro Konto oder im defaults-Block), z.B.
+//
+//   attribution On {date} at {time}, {name} wrote:
+//
+// Platzhalter: {name} (sonst die Adresse), {address}, {date} (Datum im Format der
+// Locale), {time}, {subject}; \n für einen Zeilenumbruch.
+const DEFAULT_ATTRIBUTION: &str = "Am {date} um {time} schrieb {name}:";
+
+// Farben der Zitatebenen im Leser, ab der vierten Ebene wiederholt
+const QUOTE_COLOURS: [&str; 3] = ["#3465a4", "#4e9a06", "#ad7fa8"];
+
+pub fn attribution(account: &str, from: &str, timestamp: i64, subject: &str) -> String {
+    let template =
+        signature::attribution_for(account).unwrap_or_else(|| DEFAULT_ATTRIBUTION.to_string());
+    let (name, address) = crate::db::parse_from(from);
+    let name = if name.is_empty() { &address } else { &name };
+    let format = |fmt: &str| {
+        gtk4::glib::DateTime::from_unix_local(timestamp)
+            .and_then(|dt| dt.format(fmt))
+            .map(|s| s.to_string())
+            .unwrap_or_default()
+    };
+    template
+        .replace("{name}", name)
+        .replace("{address}", &address)
+        .replace("{date}", &format("%x"))
+        .replace("{time}", &format("%H:%M"))
+        .replace("{subject}", subject)
+}
+
+// Angehängtes Vollzitat am Ende der Vorlage (Antwort über dem Zitat) samt dessen
+// Einleitungszeile. Antworten zwischen den Zitatzeilen und Mails ohne eigenen Text
+// bleiben unverändert.
+fn trim_trailing_quote(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut keep = lines.len();
+    while keep > 0
+        && (lines[keep - 1].trim().is_empty() || mime::split_quote(lines[keep - 1]).0 > 0)
+    {
+        keep -= 1;
+    }
+    if keep == lines.len() {
+        return text.to_string();
+    }
+    if keep > 0 && lines[keep - 1].trim_end().ends_with(':') {
+        keep -= 1;
+    }
+    while keep > 0 && lines[keep - 1].trim().is_empty() {
+        keep -= 1;
+    }
+    if keep == 0 || lines[..keep].iter().any(|l| mime::split_quote(l).0 > 0) {
+        return text.to_string();
+    }
+    lines[..keep].join("\n")
+}
+
+// Text der Vorlage als Zitat: ohne Signatur und angehängtes Vollzitat, jede Zeile eine
+// Ebene tiefer (">" vor bereits zitierte Zeilen, "> " vor neuen)
+pub fn quote_body(text: &str) -> String {
+    let text = signature::strip(text).trim_end();
+    let mut quoted = String::new();
+    for line in trim_trailing_quote(text).lines() {
+        let (depth, content) = mime::split_quote(line);
+        quoted.push_str(&">".repeat(depth + 1));
+        if !content.trim().is_empty() {
+            quoted.push(' ');
+            quoted.push_str(content.trim_end());
+        }
+        quoted.push('\n');
+    }
+    quoted
+}
+
+// Zitierte Zeilen im Leser je nach Ebene einfärben
+pub fn apply_quote_tags(buffer: &gtk4::TextBuffer) {
+    let table = buffer.tag_table();
+    for (i, colour) in QUOTE_COLOURS.iter().enumerate() {
+        let name = format!("quote-{}", i + 1);
+        if table.lookup(&name).is_none() {
+            buffer.create_tag(Some(&name), &[("foreground", colour)]);
+        }
+    }
+    let (start, end) = buffer.bounds();
+    for name in (1..=QUOTE_COLOURS.len()).map(|i| format!("quote-{}", i)) {
+        buffer.remove_tag_by_name(&name, &start, &end);
+    }
+
+    for line in 0..buffer.line_count() {
+        let Some(line_start) = buffer.iter_at_line(line) else {
+            continue;
+        };
+        let mut line_end = line_start;
+        if !line_end.ends_line() {
+            line_end.forward_to_line_end();
+        }
+        let depth = mime::split_quote(&buffer.text(&line_start, &line_end, false)).0;
+        if depth > 0 {
+            let name = format!("quote-{}", (depth - 1) % QUOTE_COLOURS.len() + 1);
+            buffer.apply_tag_by_name(&name, &line_start, &line_end);
+        }
+    }
+}
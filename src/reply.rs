@@ -0,0 +1,215 @@
+use crate::mime;
+use crate::msmtprc;
+use crate::threading;
+use mailparse::{MailHeader, MailHeaderMap};
+use std::collections::HashSet;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ReplyMode {
+    // Reply-To, sonst From
+    Sender,
+    // zusätzlich alle To/Cc ohne die eigenen Adressen
+    All,
+    // Adresse aus List-Post
+    List,
+}
+
+// Empfänger und Threading-Header einer Antwort
+pub struct ReplyTarget {
+    pub to: String,
+    pub cc: String,
+    pub in_reply_to: String,
+    pub references: String,
+    // msmtp-Konto, an dessen Adresse die Vorlage ging
+    pub account: String,
+}
+
+pub fn reply_target(path: &Path, mode: ReplyMode) -> Result<ReplyTarget, String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    let parsed = mailparse::parse_mail(&data).map_err(|e| e.to_string())?;
+    let headers = parsed.get_headers();
+
+    let first_id = |name: &str| {
+        headers
+            .get_first_value(name)
+            .and_then(|v| threading::parse_message_ids(&v).into_iter().next())
+    };
+    let message_id = first_id("Message-ID");
+
+    // References der Vorlage (bzw. deren In-Reply-To) plus ihre eigene Message-ID
+    let mut refs = headers
+        .get_first_value("References")
+        .map(|v| threading::parse_message_ids(&v))
+        .unwrap_or_default();
+    if refs.is_empty() {
+        refs.extend(first_id("In-Reply-To"));
+    }
+    refs.extend(message_id.clone());
+
+    let in_reply_to = message_id.map(|id| format!("<{}>", id)).unwrap_or_default();
+    let references = refs
+        .iter()
+        .map(|r| format!("<{}>", r))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let primary: Vec<&MailHeader> = headers
+        .get_first_header("Reply-To")
+        .filter(|h| !h.get_value().trim().is_empty())
+        .or_else(|| headers.get_first_header("From"))
+        .into_iter()
+        .collect();
+
+    let (to, cc) = match mode {
+        ReplyMode::Sender => {
+            let to = unique_addresses(&primary, &mut HashSet::new());
+            // Unlesbarer Header: lieber unverändert übernehmen als ohne Empfänger antworten
+            let to = if to.is_empty() {
+                primary.first().map(|h| h.get_value()).unwrap_or_default()
+            } else {
+                to.join(", ")
+            };
+            (to.trim().to_string(), String::new())
+        }
+        ReplyMode::All => {
+            let mut seen: HashSet<String> = msmtprc::own_addresses().into_iter().collect();
+            let mut to = unique_addresses(&primary, &mut seen);
+            let others: Vec<&MailHeader> = headers
+                .get_all_headers("To")
+                .into_iter()
+                .chain(headers.get_all_headers("Cc"))
+                .collect();
+            let mut cc = unique_addresses(&others, &mut seen);
+
+            // Antwort auf eine eigene Mail: die ursprünglichen Empfänger rücken nach To
+            if to.is_empty() {
+                to = std::mem::take(&mut cc);
+            }
+            (to.join(", "), cc.join(", "))
+        }
+        ReplyMode::List => {
+            let post = headers
+                .get_first_value("List-Post")
+                .ok_or("Keine Mailingliste (List-Post fehlt)")?;
+            (list_post_address(&post)?, String::new())
+        }
+    };
+
+    Ok(ReplyTarget {
+        to,
+        cc,
+        in_reply_to,
+        references,
+        account: msmtprc::account_for_mail(&parsed.headers),
+    })
+}
+
+// "<mailto:liste@example.org>" bzw. "NO", wenn die Liste keine Beiträge annimmt (RFC 2369)
+fn list_post_address(value: &str) -> Result<String, String> {
+    let start = value
+        .find("<mailto:")
+        .ok_or("An diese Mailingliste kann nicht geschrieben werden")?;
+    let rest = &value[start + 8..];
+    let end = rest.find('>').unwrap_or(rest.len());
+    let addr = rest[..end].split('?').next().unwrap_or("").trim();
+    if addr.is_empty() {
+        Err("Ungültiger List-Post-Header".to_string())
+    } else {
+        Ok(addr.to_string())
+    }
+}
+
+// Adressen aus Kopfzeilen, ohne die bereits gesehenen (Vergleich ohne Gross-/Kleinschreibung).
+// Die Zeilen werden vor dem Dekodieren zerlegt, sonst teilt ein kodiertes Komma im Namen
+// ("=?utf-8?q?M=C3=BCller=2C_Hans?=") die Adresse in zwei.
+fn unique_addresses(headers: &[&MailHeader], seen: &mut HashSet<String>) -> Vec<String> {
+    let parsed: Vec<mailparse::MailAddr> = headers
+        .iter()
+        .filter_map(|h| mailparse::addrparse_header(h).ok())
+        .flat_map(|list| list.to_vec())
+        .collect();
+
+    let mut result = Vec::new();
+    for addr in parsed.iter() {
+        let singles = match addr {
+            mailparse::MailAddr::Single(info) => vec![info.clone()],
+            mailparse::MailAddr::Group(group) => group.addrs.clone(),
+        };
+        for info in singles {
+            if seen.insert(info.addr.to_lowercase()) {
+                let name = info.display_name.as_deref().unwrap_or("");
+                result.push(mime::display_address(name, &info.addr));
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(name: &str, mail: &str, mode: ReplyMode) -> ReplyTarget {
+        let path = std::env::temp_dir().join(format!("nox-reply-{}-{}", std::process::id(), name));
+        std::fs::write(&path, mail.replace('\n', "\r\n")).unwrap();
+        let target = reply_target(&path, mode);
+        std::fs::remove_file(&path).unwrap();
+        target.unwrap()
+    }
+
+    const MAIL: &str = "From: =?utf-8?q?M=C3=BCller=2C_Hans?= <hm@example.org>\n\
+        To: a@example.org, =?utf-8?B?SsO8cmc=?= <j@example.org>\n\
+        Cc: \"B, C\" <bc@example.org>, HM@example.org\n\
+        Message-ID: <m2@example.org>\n\
+        References: <m0@example.org> <m1@example.org>\n\
+        Subject: Test\n\
+        \n\
+        Text\n";
+
+    #[test]
+    fn sender_with_encoded_comma() {
+        let target = target("sender", MAIL, ReplyMode::Sender);
+        assert_eq!(target.to, "\"Müller, Hans\" <hm@example.org>");
+        assert_eq!(
+            mime::envelope_addresses(&target.to).unwrap(),
+            ["hm@example.org"]
+        );
+        assert!(target.cc.is_empty());
+        assert_eq!(target.in_reply_to, "<m2@example.org>");
+        assert_eq!(
+            target.references,
+            "<m0@example.org> <m1@example.org> <m2@example.org>"
+        );
+    }
+
+    #[test]
+    fn reply_to_wins() {
+        let mail = format!(
+            "Reply-To: =?utf-8?q?Liste=2C_Team?= <team@example.org>\n{}",
+            MAIL
+        );
+        let target = target("reply-to", &mail, ReplyMode::Sender);
+        assert_eq!(target.to, "\"Liste, Team\" <team@example.org>");
+    }
+
+    #[test]
+    fn all_recipients() {
+        let target = target("all", MAIL, ReplyMode::All);
+        assert_eq!(target.to, "\"Müller, Hans\" <hm@example.org>");
+        assert_eq!(
+            target.cc,
+            "a@example.org, Jürg <j@example.org>, \"B, C\" <bc@example.org>"
+        );
+    }
+
+    #[test]
+    fn list_post() {
+        assert_eq!(
+            list_post_address("<mailto:liste@example.org?subject=x>").unwrap(),
+            "liste@example.org"
+        );
+        assert!(list_post_address("NO").is_err());
+        assert!(list_post_address("<mailto:>").is_err());
+    }
+}
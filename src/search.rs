@@ -0,0 +1,805 @@
+use crate::MailEntry;
+use crate::db;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+
+// Suchsprache der Suchleiste, z.B.:
+//   from:hans subject:"Offerte 2024" after:2024-01-01 -is:unread
+//   (list:rust OR list:gtk) AND has:attachment
+// Mehrere Begriffe ohne Operator sind UND-verknüpft, NOT bindet stärker als AND, AND stärker als OR.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Term(Term),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Text(String),
+    From(String),
+    To(String),
+    Subject(String),
+    Body(String),
+    Folder(String),
+    List(String),
+    Before(DateSpec),
+    After(DateSpec),
+    Unread,
+    Verified,
+    HasAttachment,
+    HasKey,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateSpec {
+    // Kalendertag als Tage seit 1970-01-01, gilt ab Mitternacht Lokalzeit
+    Day(i64),
+    // Relativ zu jetzt, z.B. "7d" oder "2w"
+    DaysAgo(i64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (Zeichen {})", self.message, self.position + 1)
+    }
+}
+
+const FIELDS: &str = "from, to, subject, body, folder, list, before, after, is, has";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Word {
+        field: Option<String>,
+        value: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    tok: Tok,
+    pos: usize,
+}
+
+fn err<T>(message: impl Into<String>, position: usize) -> Result<T, ParseError> {
+    Err(ParseError {
+        message: message.into(),
+        position,
+    })
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    // Liest eine Phrase in Anführungszeichen ab chars[i] == '"'
+    let read_quoted = |i: &mut usize| -> Result<String, ParseError> {
+        let start = *i;
+        *i += 1;
+        let mut value = String::new();
+        while *i < chars.len() {
+            let c = chars[*i];
+            *i += 1;
+            if c == '"' {
+                return Ok(value);
+            }
+            value.push(c);
+        }
+        err("Anführungszeichen wird nicht geschlossen", start)
+    };
+
+    while i < chars.len() {
+        let (pos, c) = (i, chars[i]);
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' => {
+                tokens.push(Token {
+                    tok: Tok::LParen,
+                    pos,
+                });
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token {
+                    tok: Tok::RParen,
+                    pos,
+                });
+                i += 1;
+            }
+            '-' if i + 1 < chars.len() && !chars[i + 1].is_whitespace() => {
+                tokens.push(Token { tok: Tok::Not, pos });
+                i += 1;
+            }
+            '"' => {
+                let value = read_quoted(&mut i)?;
+                tokens.push(Token {
+                    tok: Tok::Word { field: None, value },
+                    pos,
+                });
+            }
+            _ => {
+                let mut word = String::new();
+                while i < chars.len() {
+                    let c = chars[i];
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    if c == ':' && !word.is_empty() && word.chars().all(|w| w.is_alphabetic()) {
+                        i += 1;
+                        let value = if i < chars.len() && chars[i] == '"' {
+                            read_quoted(&mut i)?
+                        } else {
+                            let mut v = String::new();
+                            while i < chars.len()
+                                && !chars[i].is_whitespace()
+                                && chars[i] != '('
+                                && chars[i] != ')'
+                            {
+                                v.push(chars[i]);
+                                i += 1;
+                            }
+                            v
+                        };
+                        push_field_word(&mut tokens, word, value, pos);
+                        word = String::new();
+                        break;
+                    }
+                    word.push(c);
+                    i += 1;
+                }
+                if word.is_empty() {
+                    continue;
+                }
+                let tok = match word.as_str() {
+                    "AND" => Tok::And,
+                    "OR" => Tok::Or,
+                    "NOT" => Tok::Not,
+                    _ => Tok::Word {
+                        field: None,
+                        value: word,
+                    },
+                };
+                tokens.push(Token { tok, pos });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn push_field_word(tokens: &mut Vec<Token>, field: String, value: String, pos: usize) {
+    let field = field.to_lowercase();
+    // "Re:" ohne Wert ist kein Feld, sondern normaler Suchtext
+    let tok = if value.is_empty() && !is_known_field(&field) {
+        Tok::Word {
+            field: None,
+            value: format!("{}:", field),
+        }
+    } else {
+        Tok::Word {
+            field: Some(field),
+            value,
+        }
+    };
+    tokens.push(Token { tok, pos });
+}
+
+fn is_known_field(field: &str) -> bool {
+    matches!(
+        field,
+        "from" | "to" | "subject" | "body" | "folder" | "list" | "before" | "after" | "is" | "has"
+    )
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    idx: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.idx)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.idx).cloned();
+        self.idx += 1;
+        t
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.parse_and()?;
+        while let Some(Token { tok: Tok::Or, .. }) = self.peek() {
+            let op = self.next().unwrap();
+            self.expect_operand("OR", op.pos)?;
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.parse_unary()?;
+        loop {
+            match self.peek().map(|t| &t.tok) {
+                Some(Tok::And) => {
+                    let op = self.next().unwrap();
+                    self.expect_operand("AND", op.pos)?;
+                    let right = self.parse_unary()?;
+                    left = Expr::And(Box::new(left), Box::new(right));
+                }
+                // Implizites UND zwischen aufeinanderfolgenden Begriffen
+                Some(Tok::Word { .. }) | Some(Tok::LParen) | Some(Tok::Not) => {
+                    let right = self.parse_unary()?;
+                    left = Expr::And(Box::new(left), Box::new(right));
+                }
+                _ => return Ok(left),
+            }
+        }
+    }
+
+    fn expect_operand(&self, op: &str, pos: usize) -> Result<(), ParseError> {
+        match self.peek().map(|t| &t.tok) {
+            None | Some(Tok::RParen) | Some(Tok::And) | Some(Tok::Or) => {
+                err(format!("Nach '{}' fehlt ein Suchbegriff", op), pos)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        if let Some(Token { tok: Tok::Not, .. }) = self.peek() {
+            let op = self.next().unwrap();
+            self.expect_operand("NOT", op.pos)?;
+            let inner = self.parse_unary()?;
+            return Ok(Expr::Not(Box::new(inner)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        match self.next() {
+            Some(Token {
+                tok: Tok::LParen,
+                pos,
+            }) => {
+                if let Some(Token {
+                    tok: Tok::RParen, ..
+                }) = self.peek()
+                {
+                    return err("Leere Klammer", pos);
+                }
+                let inner = self.parse_or()?;
+                match self.next() {
+                    Some(Token {
+                        tok: Tok::RParen, ..
+                    }) => Ok(inner),
+                    _ => err("Schliessende Klammer fehlt", pos),
+                }
+            }
+            Some(Token {
+                tok: Tok::Word { field, value },
+                pos,
+            }) => build_term(field.as_deref(), &value, pos),
+            Some(Token {
+                tok: Tok::RParen,
+                pos,
+            }) => err("Unerwartete schliessende Klammer", pos),
+            Some(Token { tok, pos }) => {
+                let op = match tok {
+                    Tok::And => "AND",
+                    Tok::Or => "OR",
+                    _ => "NOT",
+                };
+                err(format!("'{}' ohne Suchbegriff davor", op), pos)
+            }
+            None => err("Suchbegriff erwartet", self.end),
+        }
+    }
+}
+
+fn build_term(field: Option<&str>, value: &str, pos: usize) -> Result<Expr, ParseError> {
+    let Some(field) = field else {
+        return Ok(Expr::Term(Term::Text(value.to_lowercase())));
+    };
+
+    if value.is_empty() {
+        return err(format!("Wert fehlt nach '{}:'", field), pos);
+    }
+    let v = value.to_lowercase();
+
+    let term = match field {
+        "from" => Term::From(v),
+        "to" => Term::To(v),
+        "subject" => Term::Subject(v),
+        "body" => Term::Body(value.to_string()),
+        "folder" => Term::Folder(v),
+        "list" => Term::List(v),
+        "before" | "after" => {
+            let Some(date) = parse_date(&v) else {
+                return err(
+                    format!(
+                        "Ungültiges Datum '{}' – erwartet JJJJ-MM-TT, TT.MM.JJJJ oder z.B. 7d, 2w, 3m, 1y",
+                        value
+                    ),
+                    pos,
+                );
+            };
+            if field == "before" {
+                Term::Before(date)
+            } else {
+                Term::After(date)
+            }
+        }
+        "is" => match v.as_str() {
+            "unread" => Term::Unread,
+            "read" => return Ok(Expr::Not(Box::new(Expr::Term(Term::Unread)))),
+            "verified" => Term::Verified,
+            _ => {
+                return err(
+                    format!(
+                        "Unbekannter Wert 'is:{}' – erlaubt: unread, read, verified",
+                        value
+                    ),
+                    pos,
+                );
+            }
+        },
+        "has" => match v.as_str() {
+            "attachment" => Term::HasAttachment,
+            "key" => Term::HasKey,
+            _ => {
+                return err(
+                    format!(
+                        "Unbekannter Wert 'has:{}' – erlaubt: attachment, key",
+                        value
+                    ),
+                    pos,
+                );
+            }
+        },
+        _ => {
+            return err(
+                format!(
+                    "Unbekanntes Feld '{}:' – erlaubt: {} (Text mit Doppelpunkt in \"…\" setzen)",
+                    field, FIELDS
+                ),
+                pos,
+            );
+        }
+    };
+    Ok(Expr::Term(term))
+}
+
+// Leere Anfrage ergibt None (keine Filterung)
+pub fn parse(input: &str) -> Result<Option<Expr>, ParseError> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+    let mut parser = Parser {
+        tokens,
+        idx: 0,
+        end: input.chars().count(),
+    };
+    let expr = parser.parse_or()?;
+    if let Some(t) = parser.peek() {
+        return match t.tok {
+            Tok::RParen => err("Unerwartete schliessende Klammer", t.pos),
+            _ => err("Unerwarteter Suchbegriff", t.pos),
+        };
+    }
+    Ok(Some(expr))
+}
+
+fn parse_date(value: &str) -> Option<DateSpec> {
+    if let Some(unit) = value.chars().last().filter(|c| "dwmy".contains(*c)) {
+        let n: i64 = value[..value.len() - 1].parse().ok().filter(|n| *n >= 0)?;
+        let days = match unit {
+            'd' => Some(n),
+            'w' => n.checked_mul(7),
+            'm' => n.checked_mul(30),
+            _ => n.checked_mul(365),
+        }?;
+        // Bei der Auswertung wird daraus ein Unix-Zeitpunkt in Sekunden
+        days.checked_mul(86400)?;
+        return Some(DateSpec::DaysAgo(days));
+    }
+
+    let (y, m, d) = if value.contains('-') {
+        let parts: Vec<&str> = value.split('-').collect();
+        if parts.len() != 3 {
+            return None;
+        }
+        (
+            parts[0].parse().ok()?,
+            parts[1].parse().ok()?,
+            parts[2].parse().ok()?,
+        )
+    } else if value.contains('.') {
+        let parts: Vec<&str> = value.split('.').collect();
+        if parts.len() != 3 {
+            return None;
+        }
+        (
+            parts[2].parse().ok()?,
+            parts[1].parse().ok()?,
+            parts[0].parse().ok()?,
+        )
+    } else {
+        return None;
+    };
+
+    if !(1..=9999).contains(&y) || !(1..=12).contains(&m) || d < 1 || d > days_in_month(y, m) {
+        return None;
+    }
+    Some(DateSpec::Day(days_from_civil(y, m, d)))
+}
+
+fn days_in_month(y: i64, m: i64) -> i64 {
+    match m {
+        2 if (y % 4 == 0 && y % 100 != 0) || y % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Tage seit 1970-01-01 für ein Datum im gregorianischen Kalender
+pub fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// Eine geparste Anfrage samt den Daten aus der DB, die zur Auswertung nötig sind
+pub struct CompiledSearch {
+    expr: Option<Expr>,
+    verified: HashSet<String>,
+    with_key: HashSet<String>,
+    // Volltext-Treffer pro Begriff: Pfad -> Rang
+    body_hits: HashMap<String, HashMap<PathBuf, usize>>,
+    text_in_body: bool,
+    now: i64,
+    utc_offset: i64,
+}
+
+impl CompiledSearch {
+    pub fn empty() -> Self {
+        CompiledSearch {
+            expr: None,
+            verified: HashSet::new(),
+            with_key: HashSet::new(),
+            body_hits: HashMap::new(),
+            text_in_body: false,
+            now: 0,
+            utc_offset: 0,
+        }
+    }
+
+    // text_in_body: freie Suchbegriffe zusätzlich im Mailtext suchen (Suche über alle Ordner)
+    pub fn compile(
+        query: &str,
+        text_in_body: bool,
+        now: i64,
+        utc_offset: i64,
+    ) -> Result<Self, ParseError> {
+        let expr = parse(query)?;
+        let mut compiled = CompiledSearch::empty();
+        compiled.text_in_body = text_in_body;
+        compiled.now = now;
+        compiled.utc_offset = utc_offset;
+
+        if let Some(expr) = &expr {
+            let mut terms = Vec::new();
+            collect_terms(expr, &mut terms);
+
+            for term in terms {
+                match term {
+                    Term::Verified if compiled.verified.is_empty() => {
+                        compiled.verified = db::get_verified_emails().unwrap_or_default();
+                    }
+                    Term::HasKey if compiled.with_key.is_empty() => {
+                        compiled.with_key = db::get_emails_with_key().unwrap_or_default();
+                    }
+                    Term::Body(t) => compiled.load_body_hits(t),
+                    Term::Text(t) if text_in_body => compiled.load_body_hits(t),
+                    _ => {}
+                }
+            }
+        }
+        compiled.expr = expr;
+        Ok(compiled)
+    }
+
+    fn load_body_hits(&mut self, term: &str) {
+        if self.body_hits.contains_key(term) {
+            return;
+        }
+        let hits = db::search_body_paths(term)
+            .unwrap_or_default()
+            .into_iter()
+            .enumerate()
+            .map(|(rank, path)| (path, rank))
+            .collect();
+        self.body_hits.insert(term.to_string(), hits);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expr.is_none()
+    }
+
+    pub fn filter(&self, entries: &[MailEntry]) -> Vec<MailEntry> {
+        match &self.expr {
+            None => entries.to_vec(),
+            Some(expr) => entries
+                .iter()
+                .filter(|e| self.matches(expr, e))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    // Bester Volltext-Rang eines Treffers (kleiner ist besser)
+    pub fn rank(&self, entry: &MailEntry) -> Option<usize> {
+        self.body_hits
+            .values()
+            .filter_map(|hits| hits.get(&entry.path).copied())
+            .min()
+    }
+
+    fn body_matches(&self, term: &str, entry: &MailEntry) -> bool {
+        self.body_hits
+            .get(term)
+            .is_some_and(|hits| hits.contains_key(&entry.path))
+    }
+
+    fn timestamp(&self, date: DateSpec) -> i64 {
+        match date {
+            DateSpec::Day(days) => days * 86400 - self.utc_offset,
+            DateSpec::DaysAgo(days) => self.now - days * 86400,
+        }
+    }
+
+    fn matches(&self, expr: &Expr, e: &MailEntry) -> bool {
+        match expr {
+            Expr::And(a, b) => self.matches(a, e) && self.matches(b, e),
+            Expr::Or(a, b) => self.matches(a, e) || self.matches(b, e),
+            Expr::Not(a) => !self.matches(a, e),
+            Expr::Term(term) => self.matches_term(term, e),
+        }
+    }
+
+    fn matches_term(&self, term: &Term, e: &MailEntry) -> bool {
+        let sender = db::parse_from(&e.from).1.to_lowercase();
+        match term {
+            Term::Text(t) => {
+                e.subject.to_lowercase().contains(t)
+                    || e.from.to_lowercase().contains(t)
+                    || e.date_full.to_lowercase().contains(t)
+                    || (self.text_in_body && self.body_matches(t, e))
+            }
+            Term::From(t) => {
+                e.from.to_lowercase().contains(t) || e.return_path.to_lowercase().contains(t)
+            }
+            Term::To(t) => e.to.to_lowercase().contains(t) || e.cc.to_lowercase().contains(t),
+            Term::Subject(t) => e.subject.to_lowercase().contains(t),
+            Term::Body(t) => self.body_matches(t, e),
+            Term::Folder(t) => e.folder.to_lowercase().contains(t),
+            Term::List(t) => e
+                .list_id
+                .as_ref()
+                .is_some_and(|l| l.to_lowercase().contains(t)),
+            Term::Before(d) => e.timestamp < self.timestamp(*d),
+            Term::After(d) => e.timestamp >= self.timestamp(*d),
+            Term::Unread => !e.is_read,
+            Term::Verified => {
+                self.verified.contains(&e.return_path) || self.verified.contains(&sender)
+            }
+            Term::HasAttachment => e.has_attachment,
+            Term::HasKey => {
+                self.with_key.contains(&e.return_path) || self.with_key.contains(&sender)
+            }
+        }
+    }
+}
+
+fn collect_terms<'a>(expr: &'a Expr, out: &mut Vec<&'a Term>) {
+    match expr {
+        Expr::And(a, b) | Expr::Or(a, b) => {
+            collect_terms(a, out);
+            collect_terms(b, out);
+        }
+        Expr::Not(a) => collect_terms(a, out),
+        Expr::Term(t) => out.push(t),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Expr {
+        Expr::Term(Term::Text(s.to_string()))
+    }
+
+    fn and(a: Expr, b: Expr) -> Expr {
+        Expr::And(Box::new(a), Box::new(b))
+    }
+
+    fn or(a: Expr, b: Expr) -> Expr {
+        Expr::Or(Box::new(a), Box::new(b))
+    }
+
+    fn not(a: Expr) -> Expr {
+        Expr::Not(Box::new(a))
+    }
+
+    fn parsed(input: &str) -> Expr {
+        parse(input).unwrap().unwrap()
+    }
+
+    fn error(input: &str) -> (String, usize) {
+        let e = parse(input).unwrap_err();
+        (e.message, e.position)
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(parse(""), Ok(None));
+        assert_eq!(parse("   "), Ok(None));
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(parsed("a b"), and(text("a"), text("b")));
+        assert_eq!(parsed("a b OR c"), or(and(text("a"), text("b")), text("c")));
+        assert_eq!(
+            parsed("a OR b AND c"),
+            or(text("a"), and(text("b"), text("c")))
+        );
+        assert_eq!(
+            parsed("NOT a OR b c"),
+            or(not(text("a")), and(text("b"), text("c")))
+        );
+        assert_eq!(
+            parsed("(a OR b) c"),
+            and(or(text("a"), text("b")), text("c"))
+        );
+        assert_eq!(parsed("NOT NOT a"), not(not(text("a"))));
+        // Kleingeschriebene Operatoren sind gewöhnliche Wörter
+        assert_eq!(parsed("a or b"), and(and(text("a"), text("or")), text("b")));
+    }
+
+    #[test]
+    fn minus() {
+        assert_eq!(parsed("a -b"), and(text("a"), not(text("b"))));
+        assert_eq!(parsed("-is:unread"), not(Expr::Term(Term::Unread)));
+        assert_eq!(parsed("-(a OR b)"), not(or(text("a"), text("b"))));
+        assert_eq!(parsed("a - b"), and(and(text("a"), text("-")), text("b")));
+    }
+
+    #[test]
+    fn fields_and_phrases() {
+        assert_eq!(
+            parsed("subject:\"Offerte 2024\" \"Zwei Worte\""),
+            and(
+                Expr::Term(Term::Subject("offerte 2024".to_string())),
+                text("zwei worte")
+            )
+        );
+        assert_eq!(
+            parsed("FROM:Hans body:Rechnung"),
+            and(
+                Expr::Term(Term::From("hans".to_string())),
+                Expr::Term(Term::Body("Rechnung".to_string()))
+            )
+        );
+        assert_eq!(parsed("is:read"), not(Expr::Term(Term::Unread)));
+        assert_eq!(parsed("has:attachment"), Expr::Term(Term::HasAttachment));
+        // "Re:" ohne Wert ist Text
+        assert_eq!(parsed("Re: Hallo"), and(text("re:"), text("hallo")));
+        assert_eq!(parsed("\"foo:bar\""), text("foo:bar"));
+    }
+
+    #[test]
+    fn errors() {
+        let (message, position) = error("a foo:bar");
+        assert!(message.starts_with("Unbekanntes Feld 'foo:'"));
+        assert_eq!(position, 2);
+        assert_eq!(error("x from:"), ("Wert fehlt nach 'from:'".to_string(), 2));
+        assert_eq!(error("is:alt").1, 0);
+        assert_eq!(error("has:foo").1, 0);
+        assert_eq!(
+            error("x (a OR b"),
+            ("Schliessende Klammer fehlt".to_string(), 2)
+        );
+        assert_eq!(
+            error("a b)"),
+            ("Unerwartete schliessende Klammer".to_string(), 3)
+        );
+        assert_eq!(error("a ()"), ("Leere Klammer".to_string(), 2));
+        assert_eq!(
+            error("a OR"),
+            ("Nach 'OR' fehlt ein Suchbegriff".to_string(), 2)
+        );
+        assert_eq!(error("AND a").1, 0);
+        assert_eq!(
+            error("a \"offen"),
+            ("Anführungszeichen wird nicht geschlossen".to_string(), 2)
+        );
+        // Position zählt Zeichen, nicht Bytes
+        assert_eq!(error("Grüsse foo:x").1, 7);
+        let e = parse("ä)").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Unerwartete schliessende Klammer (Zeichen 2)"
+        );
+    }
+
+    #[test]
+    fn relative_dates() {
+        assert_eq!(parse_date("7d"), Some(DateSpec::DaysAgo(7)));
+        assert_eq!(parse_date("2w"), Some(DateSpec::DaysAgo(14)));
+        assert_eq!(parse_date("3m"), Some(DateSpec::DaysAgo(90)));
+        assert_eq!(parse_date("1y"), Some(DateSpec::DaysAgo(365)));
+        assert_eq!(parse_date("0d"), Some(DateSpec::DaysAgo(0)));
+        assert_eq!(parse_date("d"), None);
+        assert_eq!(parse_date("-3d"), None);
+        assert_eq!(parse_date("3x"), None);
+    }
+
+    #[test]
+    fn date_overflow() {
+        assert_eq!(parse_date("99999999999999999y"), None);
+        assert_eq!(parse_date("9999999999999999999d"), None);
+        assert_eq!(parse_date("999999999999999d"), None);
+        assert_eq!(parse_date("99999999999999-01-01"), None);
+        let (message, position) = error("a before:99999999999999999y");
+        assert!(message.starts_with("Ungültiges Datum"));
+        assert_eq!(position, 2);
+    }
+
+    #[test]
+    fn absolute_dates() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        assert_eq!(
+            parse_date("2024-02-29"),
+            Some(DateSpec::Day(days_from_civil(2024, 2, 29)))
+        );
+        assert_eq!(parse_date("29.02.2024"), parse_date("2024-02-29"));
+        assert_eq!(parse_date("1.3.2024"), parse_date("2024-03-01"));
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("2100-02-29"), None);
+        assert!(parse_date("2000-02-29").is_some());
+        assert_eq!(parse_date("2024-13-01"), None);
+        assert_eq!(parse_date("2024-04-31"), None);
+        assert_eq!(parse_date("2024-01"), None);
+        assert_eq!(parse_date("heute"), None);
+        assert_eq!(
+            parsed("after:2024-01-01"),
+            Expr::Term(Term::After(DateSpec::Day(days_from_civil(2024, 1, 1))))
+        );
+    }
+}
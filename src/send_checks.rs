@@ -0,0 +1,291 @@
+use crate::completion::Candidate;
+use crate::forward::FORWARD_MARKER;
+use std::collections::HashSet;
+
+// Wörter, die auf einen vergessenen Anhang hindeuten (im eigenen Text, nicht im Zitat)
+const ATTACHMENT_HINTS: [&str; 6] = [
+    "anhang",
+    "anhänge",
+    "angehängt",
+    "anbei",
+    "attached",
+    "attachment",
+];
+
+pub struct Warning {
+    // Empfängerfeld ("An", "Cc", "Bcc"), das markiert werden soll
+    pub field: Option<&'static str>,
+    pub message: String,
+}
+
+// Prüfungen vor dem Versand: unbekannte und nicht verifizierte Empfänger, vermutliche
+// Tippfehler und ein im Text erwähnter, aber fehlender Anhang
+pub fn check(
+    recipients: &[(&'static str, Vec<String>)],
+    body: &str,
+    has_attachments: bool,
+    candidates: &[Candidate],
+) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    let verified: Vec<&str> = candidates
+        .iter()
+        .filter(|c| c.is_verified)
+        .map(|c| c.email.as_str())
+        .collect();
+    let known_domains: HashSet<String> = candidates
+        .iter()
+        .filter_map(|c| c.email.rsplit_once('@'))
+        .map(|(_, d)| d.to_lowercase())
+        .collect();
+    let verified_domains: HashSet<String> = verified
+        .iter()
+        .filter_map(|e| e.rsplit_once('@'))
+        .map(|(_, d)| d.to_lowercase())
+        .collect();
+
+    for (field, addresses) in recipients {
+        for addr in addresses {
+            let contact = candidates
+                .iter()
+                .find(|c| c.email.eq_ignore_ascii_case(addr));
+            match contact {
+                Some(c) if c.is_verified => {}
+                Some(_) => warnings.push(Warning {
+                    field: Some(field),
+                    message: format!("{}: {} ist nicht verifiziert", field, addr),
+                }),
+                None => {
+                    let message =
+                        match typo_suggestion(addr, &verified, &known_domains, &verified_domains) {
+                            Some(suggestion) => format!(
+                                "{}: {} ist unbekannt – Tippfehler? Gemeint ist vielleicht {}",
+                                field, addr, suggestion
+                            ),
+                            None => format!("{}: {} ist nicht im Adressbuch", field, addr),
+                        };
+                    warnings.push(Warning {
+                        field: Some(field),
+                        message,
+                    });
+                }
+            }
+        }
+    }
+
+    if !has_attachments && mentions_attachment(body) {
+        warnings.push(Warning {
+            field: None,
+            message: "Im Text ist von einem Anhang die Rede, es ist aber keiner angehängt"
+                .to_string(),
+        });
+    }
+
+    warnings
+}
+
+// Nahe verifizierte Adresse, sonst dieselbe Adresse mit einer nahen verifizierten Domain
+fn typo_suggestion(
+    addr: &str,
+    verified: &[&str],
+    known_domains: &HashSet<String>,
+    verified_domains: &HashSet<String>,
+) -> Option<String> {
+    let addr_lower = addr.to_lowercase();
+    let close_address = verified
+        .iter()
+        .map(|v| (edit_distance(&addr_lower, &v.to_lowercase()), *v))
+        .filter(|(d, v)| *d > 0 && *d <= max_typo_distance(v))
+        .min_by_key(|(d, _)| *d);
+    if let Some((_, v)) = close_address {
+        return Some(v.to_string());
+    }
+
+    let (local, domain) = addr_lower.rsplit_once('@')?;
+    if known_domains.contains(domain) {
+        return None;
+    }
+    verified_domains
+        .iter()
+        .map(|d| (edit_distance(domain, d), d))
+        .filter(|(dist, d)| *dist > 0 && *dist <= max_typo_distance(d))
+        .min_by_key(|(dist, _)| *dist)
+        .map(|(_, d)| format!("{}@{}", local, d))
+}
+
+// Kurze Domains wie gmx.ch/gmx.de unterscheiden sich legitim um zwei Zeichen
+fn max_typo_distance(s: &str) -> usize {
+    if s.chars().count() < 10 { 1 } else { 2 }
+}
+
+// Levenshtein-Distanz über Zeichen, vertauschte Nachbarn ("gmial") zählen als ein Fehler
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+fn mentions_attachment(body: &str) -> bool {
+    body.lines()
+        .map(|line| line.trim_end_matches('\r'))
+        // Signatur und weitergeleitete Nachricht zählen nicht mehr, Zitate auch nicht
+        .take_while(|line| *line != "-- " && line.trim_end() != FORWARD_MARKER)
+        .map(str::trim_start)
+        .filter(|line| !line.starts_with('>'))
+        .any(|line| {
+            let line = line.to_lowercase();
+            ATTACHMENT_HINTS.iter().any(|hint| line.contains(hint))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(email: &str, is_verified: bool) -> Candidate {
+        Candidate {
+            name: String::new(),
+            email: email.to_string(),
+            is_verified,
+            has_key: false,
+            sent_count: 0,
+        }
+    }
+
+    fn suggestion(addr: &str, candidates: &[Candidate]) -> Option<String> {
+        let verified: Vec<&str> = candidates
+            .iter()
+            .filter(|c| c.is_verified)
+            .map(|c| c.email.as_str())
+            .collect();
+        let domains = |verified_only: bool| -> HashSet<String> {
+            candidates
+                .iter()
+                .filter(|c| c.is_verified || !verified_only)
+                .filter_map(|c| c.email.rsplit_once('@'))
+                .map(|(_, d)| d.to_string())
+                .collect()
+        };
+        typo_suggestion(addr, &verified, &domains(false), &domains(true))
+    }
+
+    #[test]
+    fn distance() {
+        assert_eq!(edit_distance("gmail", "gmail"), 0);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("müller", "muller"), 1);
+    }
+
+    #[test]
+    fn transpositions() {
+        assert_eq!(edit_distance("gmial", "gmail"), 1);
+        assert_eq!(edit_distance("ab", "ba"), 1);
+        assert_eq!(edit_distance("hnas@example.org", "hans@example.org"), 1);
+        assert_eq!(edit_distance("abcd", "badc"), 2);
+    }
+
+    #[test]
+    fn typo_threshold() {
+        let candidates = [
+            candidate("hans@example.org", true),
+            candidate("anna@gmx.ch", true),
+            candidate("otto@bekannt.ch", false),
+        ];
+        // Lange Adressen: bis zu zwei Fehler
+        assert_eq!(
+            suggestion("hnas@example.org", &candidates).as_deref(),
+            Some("hans@example.org")
+        );
+        assert_eq!(
+            suggestion("hnas@exmaple.org", &candidates).as_deref(),
+            Some("hans@example.org")
+        );
+        assert_eq!(suggestion("fritz@example.org", &candidates), None);
+        // Kurze Domain: nur ein Fehler, gmx.de ist eine andere Domain
+        assert_eq!(
+            suggestion("peter@gmx.cj", &candidates).as_deref(),
+            Some("peter@gmx.ch")
+        );
+        assert_eq!(suggestion("peter@gmx.de", &candidates), None);
+        // Bekannte Domain ist kein Tippfehler
+        assert_eq!(suggestion("fritz@bekannt.ch", &candidates), None);
+    }
+
+    #[test]
+    fn attachment_keywords() {
+        assert!(mentions_attachment("Hallo\n\nSiehe Anhang.\n"));
+        assert!(mentions_attachment("anbei die Unterlagen"));
+        assert!(mentions_attachment("Please find it ATTACHED."));
+        assert!(!mentions_attachment("Hallo\n\nbis morgen\n"));
+        assert!(!mentions_attachment("Danke!\n\n> Im Anhang die Offerte\n"));
+    }
+
+    #[test]
+    fn attachment_keywords_and_signature() {
+        assert!(!mentions_attachment("Hallo\n-- \nAnhänge bitte als PDF\n"));
+        assert!(!mentions_attachment(
+            "Hallo\r\n-- \r\nAnhänge bitte als PDF\r\n"
+        ));
+        assert!(mentions_attachment("Siehe Anhang\n-- \nHans\n"));
+        // Nur "-- " trennt die Signatur ab, andere Zeilen mit "--" nicht
+        assert!(mentions_attachment("Erster Teil\n---\nAnbei die Liste\n"));
+        assert!(mentions_attachment("-- siehe unten\nAnbei die Liste\n"));
+        assert!(!mentions_attachment(&format!(
+            "Zur Info\n\n{}\nVon: x\n\nIm Anhang die Offerte\n",
+            FORWARD_MARKER
+        )));
+    }
+
+    #[test]
+    fn missing_attachment_warning() {
+        let warnings = check(&[], "Anbei das Dokument", false, &[]);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].field.is_none());
+        assert!(check(&[], "Anbei das Dokument", true, &[]).is_empty());
+    }
+
+    #[test]
+    fn recipient_warnings() {
+        let candidates = [
+            candidate("hans@example.org", true),
+            candidate("otto@example.org", false),
+        ];
+        let recipients = [(
+            "An",
+            vec![
+                "hans@example.org".to_string(),
+                "otto@example.org".to_string(),
+                "hnas@example.org".to_string(),
+            ],
+        )];
+        let warnings = check(&recipients, "", false, &candidates);
+        let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "An: otto@example.org ist nicht verifiziert",
+                "An: hnas@example.org ist unbekannt – Tippfehler? Gemeint ist vielleicht hans@example.org",
+            ]
+        );
+        assert!(warnings.iter().all(|w| w.field == Some("An")));
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+// Signaturen pro Absender-Identität in ~/.config/noxmail/signatures, aufgebaut wie die
+// msmtprc (msmtp selbst lehnt unbekannte Schlüssel ab):
+//
+//   defaults
+//   placement below
+//
+//   account icloud
+//   text Viele Grüsse\nHans
+//
+//   account arbeit
+//   file ~/.signature-arbeit
+//   placement above
+//
+// Quelle ist text (\n für Zeilenumbrüche), file oder command (Ausgabe von sh -c).
+// "account default" (bzw. der defaults-Block) gilt für Konten ohne eigenen Abschnitt.
+// placement bestimmt, ob die Signatur bei Antworten über oder unter dem Zitat steht.
+// attribution ist die Zeile über dem Zitat, siehe quote::attribution.
+
+// Signaturtrenner nach RFC 3676, mit Leerzeichen
+pub const SIG_DASHES: &str = "-- ";
+
+#[derive(Clone, Default)]
+struct Entry {
+    text: Option<String>,
+    file: Option<String>,
+    command: Option<String>,
+    placement: Option<String>,
+    attribution: Option<String>,
+}
+
+pub struct Signature {
+    pub text: String,
+    pub above_quote: bool,
+}
+
+fn config_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("noxmail").join("signatures"))
+}
+
+fn read_config() -> HashMap<String, Entry> {
+    let Some(content) = config_path().and_then(|p| std::fs::read_to_string(p).ok()) else {
+        return HashMap::new();
+    };
+
+    let mut entries: HashMap<String, Entry> = HashMap::new();
+    let mut defaults = Entry::default();
+    let mut current: Option<String> = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = match line.split_once(char::is_whitespace) {
+            Some((k, v)) => (k, v.trim().to_string()),
+            None => (line, String::new()),
+        };
+
+        if key == "defaults" {
+            current = None;
+            continue;
+        }
+        if key == "account" {
+            entries
+                .entry(value.clone())
+                .or_insert_with(|| defaults.clone());
+            current = Some(value);
+            continue;
+        }
+        let entry = match &current {
+            Some(name) => entries.get_mut(name).unwrap(),
+            None => &mut defaults,
+        };
+        match key {
+            "text" => entry.text = Some(value.replace("\\n", "\n")),
+            "file" => entry.file = Some(value),
+            "command" => entry.command = Some(value),
+            "placement" => entry.placement = Some(value),
+            "attribution" => entry.attribution = Some(value.replace("\\n", "\n")),
+            _ => {}
+        }
+    }
+    entries.entry("default".to_string()).or_insert(defaults);
+    entries
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap().join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+// Signatur des Kontos (leer: Standardkonto), None ohne Eintrag oder bei leerem Ergebnis
+pub fn for_account(account: &str) -> Option<Signature> {
+    let entries = read_config();
+    let entry = entries.get(account).or_else(|| entries.get("default"))?;
+
+    let raw = if let Some(text) = &entry.text {
+        text.clone()
+    } else if let Some(file) = &entry.file {
+        std::fs::read_to_string(expand_home(file)).ok()?
+    } else if let Some(command) = &entry.command {
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout).to_string()
+    } else {
+        return None;
+    };
+
+    // Manche Signaturdateien bringen den Trenner schon mit
+    let text = raw
+        .strip_prefix("-- \n")
+        .or_else(|| raw.strip_prefix("--\n"))
+        .unwrap_or(&raw)
+        .trim_end()
+        .to_string();
+    if text.is_empty() {
+        return None;
+    }
+    Some(Signature {
+        text,
+        above_quote: entry.placement.as_deref() == Some("above"),
+    })
+}
+
+// Vorlage für die Zeile über dem Zitat, None ohne Eintrag
+pub fn attribution_for(account: &str) -> Option<String> {
+    let entries = read_config();
+    entries
+        .get(account)
+        .and_then(|e| e.attribution.clone())
+        .or_else(|| entries.get("default")?.attribution.clone())
+}
+
+// Trenner und Signatur, wie sie im Mailtext stehen
+pub fn block(signature: &Signature) -> String {
+    format!("{}\n{}\n", SIG_DASHES, signature.text)
+}
+
+// Signatur in einen neuen Mailtext einfügen. Bei Antworten (Zitat im Text) je nach
+// placement über oder unter dem Zitat, sonst über dem Text (z.B. einer Weiterleitung).
+pub fn insert(body: &str, signature: &Signature, is_reply: bool) -> String {
+    let block = block(signature);
+    if body.trim().is_empty() {
+        format!("\n\n{}", block)
+    } else if is_reply && !signature.above_quote {
+        // Das Zitat endet mit einer Leerzeile für die eigene Antwort
+        format!("{}\n{}", body, block)
+    } else {
+        format!("\n\n{}\n{}", block, body.trim_start_matches('\n'))
+    }
+}
+
+// Text ohne angehängte Signatur: alles ab der letzten Zeile "-- " fällt weg. Nur wenn es
+// keine solche gibt, gilt die letzte Zeile "--" (das Leerzeichen ging unterwegs verloren).
+// Ein "--" mitten im Text oder in einer zitierten Signatur bleibt so stehen.
+pub fn strip(text: &str) -> &str {
+    let mut separator = None;
+    let mut fallback = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\r', '\n']);
+        if content == "-- " {
+            separator = Some(offset);
+        } else if content.trim_end() == "--" {
+            fallback = Some(offset);
+        }
+        offset += line.len();
+    }
+    match separator.or(fallback) {
+        Some(offset) => &text[..offset],
+        None => text,
+    }
+}
@@ -0,0 +1,627 @@
+use crate::msmtprc::Account;
+use base64::Engine;
+use gtk4::gio;
+use gtk4::prelude::*;
+use std::io::{BufRead, BufReader, Write};
+
+// Direkter Versand per SMTP mit den Einstellungen eines msmtp-Kontos:
+// host, port, tls, tls_starttls, tls_certcheck, auth, user, password/passwordeval.
+// Läuft blockierend im Versand-Thread.
+
+const TIMEOUT_SECS: u32 = 60;
+
+pub struct SmtpError {
+    pub message: String,
+    // 5xx-Antwort oder Konfigurationsfehler: eine Wiederholung ändert nichts
+    pub permanent: bool,
+}
+
+impl SmtpError {
+    fn temporary(message: String) -> Self {
+        SmtpError {
+            message,
+            permanent: false,
+        }
+    }
+
+    fn permanent(message: String) -> Self {
+        SmtpError {
+            message,
+            permanent: true,
+        }
+    }
+}
+
+impl From<gio::glib::Error> for SmtpError {
+    fn from(e: gio::glib::Error) -> Self {
+        SmtpError::temporary(format!("Verbindung: {}", e))
+    }
+}
+
+impl From<std::io::Error> for SmtpError {
+    fn from(e: std::io::Error) -> Self {
+        SmtpError::temporary(format!("Verbindung: {}", e))
+    }
+}
+
+struct Reply {
+    code: u16,
+    // Alle Zeilen ohne Code, bei EHLO die Erweiterungen
+    lines: Vec<String>,
+}
+
+impl Reply {
+    fn text(&self) -> String {
+        format!("{} {}", self.code, self.lines.join(" "))
+    }
+}
+
+struct Session {
+    // Hält die Verbindung (bzw. die TLS-Schicht darüber) am Leben
+    stream: gio::IOStream,
+    reader: BufReader<gio::InputStreamRead<gio::InputStream>>,
+    writer: gio::OutputStreamWrite<gio::OutputStream>,
+}
+
+impl Session {
+    fn new(stream: gio::IOStream) -> Self {
+        Session {
+            reader: BufReader::new(stream.input_stream().into_read()),
+            writer: stream.output_stream().into_write(),
+            stream,
+        }
+    }
+
+    fn read_reply(&mut self) -> Result<Reply, SmtpError> {
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(SmtpError::temporary(
+                    "Verbindung vom Server geschlossen".to_string(),
+                ));
+            }
+            let line = line.trim_end();
+            let code = line
+                .get(..3)
+                .and_then(|c| c.parse::<u16>().ok())
+                .ok_or_else(|| SmtpError::temporary(format!("Ungültige Antwort: {}", line)))?;
+            lines.push(line.get(4..).unwrap_or("").to_string());
+            // "250-…" kündigt weitere Zeilen an, "250 …" ist die letzte
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return Ok(Reply { code, lines });
+            }
+        }
+    }
+
+    fn command(&mut self, command: &str) -> Result<Reply, SmtpError> {
+        self.writer.write_all(command.as_bytes())?;
+        self.writer.write_all(b"\r\n")?;
+        self.writer.flush()?;
+        self.read_reply()
+    }
+
+    // Befehl, der mit `expected` (z.B. 250) beantwortet werden muss. `label` erscheint in
+    // der Fehlermeldung statt des Befehls, damit keine Zugangsdaten im Postausgang stehen.
+    fn expect(&mut self, command: &str, expected: u16, label: &str) -> Result<Reply, SmtpError> {
+        let reply = self.command(command)?;
+        if reply.code == expected {
+            Ok(reply)
+        } else {
+            Err(reply_error(label, &reply))
+        }
+    }
+
+    fn ehlo(&mut self) -> Result<Vec<String>, SmtpError> {
+        let name = gio::glib::host_name();
+        let reply = self.command(&format!("EHLO {}", name))?;
+        if reply.code == 250 {
+            // Erste Zeile ist die Begrüssung, danach die Erweiterungen
+            return Ok(reply.lines.into_iter().skip(1).collect());
+        }
+        self.expect(&format!("HELO {}", name), 250, "HELO")?;
+        Ok(Vec::new())
+    }
+
+    fn start_tls(self, host: &str, certcheck: bool) -> Result<Session, SmtpError> {
+        let identity = gio::NetworkAddress::new(host, 0);
+        let tls = gio::TlsClientConnection::new(&self.stream, Some(&identity))?;
+        if !certcheck {
+            tls.connect_accept_certificate(|_, _, _| true);
+        }
+        tls.handshake(None::<&gio::Cancellable>)
+            .map_err(|e| SmtpError::temporary(format!("TLS: {}", e)))?;
+        Ok(Session::new(tls.upcast()))
+    }
+}
+
+fn reply_error(label: &str, reply: &Reply) -> SmtpError {
+    let message = format!("{}: {}", label, reply.text());
+    if reply.code >= 500 {
+        SmtpError::permanent(message)
+    } else {
+        SmtpError::temporary(message)
+    }
+}
+
+fn is_on(value: Option<&str>, default: bool) -> bool {
+    match value {
+        Some(v) => v != "off",
+        None => default,
+    }
+}
+
+// Werte wie passwordeval stehen in der msmtprc oft in Anführungszeichen
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn password(account: &Account) -> Result<String, SmtpError> {
+    if let Some(password) = account.setting("password") {
+        return Ok(unquote(password).to_string());
+    }
+    let command = account.setting("passwordeval").ok_or_else(|| {
+        SmtpError::permanent(format!(
+            "Konto {}: weder password noch passwordeval",
+            account.name
+        ))
+    })?;
+    let output = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(unquote(command))
+        .output()
+        .map_err(|e| SmtpError::temporary(format!("passwordeval: {}", e)))?;
+    if !output.status.success() {
+        return Err(SmtpError::temporary(format!(
+            "passwordeval fehlgeschlagen ({})",
+            output.status
+        )));
+    }
+    // Wie msmtp: nur die erste Zeile zählt
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .unwrap_or("")
+        .to_string())
+}
+
+fn authenticate(
+    session: &mut Session,
+    account: &Account,
+    extensions: &[String],
+    encrypted: bool,
+) -> Result<(), SmtpError> {
+    let auth = account.setting("auth").unwrap_or("off");
+    if auth == "off" {
+        return Ok(());
+    }
+    // PLAIN und LOGIN schicken das Passwort im Klartext. Wie msmtp nur über TLS, ausser
+    // das Verfahren ist ausdrücklich als "auth plain" bzw. "auth login" eingestellt.
+    if auth == "on" && !encrypted {
+        return Err(SmtpError::permanent(format!(
+            "Konto {}: Anmeldung ohne TLS würde das Passwort im Klartext senden (tls on setzen)",
+            account.name
+        )));
+    }
+    // "AUTH PLAIN LOGIN", bei alten Servern auch "AUTH=LOGIN"
+    let offered: Vec<String> = extensions
+        .iter()
+        .map(|e| e.to_uppercase())
+        .filter_map(|e| {
+            e.strip_prefix("AUTH=")
+                .or_else(|| e.strip_prefix("AUTH "))
+                .map(str::to_string)
+        })
+        .flat_map(|m| m.split_whitespace().map(str::to_string).collect::<Vec<_>>())
+        .collect();
+    let mechanism = match auth {
+        "on" if offered.iter().any(|m| m == "PLAIN") => "PLAIN",
+        "on" if offered.iter().any(|m| m == "LOGIN") => "LOGIN",
+        "on" => {
+            return Err(SmtpError::permanent(
+                "Server bietet weder AUTH PLAIN noch AUTH LOGIN an".to_string(),
+            ));
+        }
+        "plain" => "PLAIN",
+        "login" => "LOGIN",
+        other => {
+            return Err(SmtpError::permanent(format!(
+                "auth {} wird nicht unterstützt (nur plain, login)",
+                other
+            )));
+        }
+    };
+
+    let user = account.setting("user").map(unquote).unwrap_or("");
+    let password = password(account)?;
+    let b64 = |s: &str| base64::engine::general_purpose::STANDARD.encode(s);
+
+    if mechanism == "PLAIN" {
+        let credentials = b64(&format!("\0{}\0{}", user, password));
+        session.expect(&format!("AUTH PLAIN {}", credentials), 235, "AUTH PLAIN")?;
+    } else {
+        session.expect("AUTH LOGIN", 334, "AUTH LOGIN")?;
+        session.expect(&b64(user), 334, "AUTH LOGIN (Benutzer)")?;
+        session.expect(&b64(&password), 235, "AUTH LOGIN (Passwort)")?;
+    }
+    Ok(())
+}
+
+// Mail mit CRLF-Zeilenenden und verdoppelten führenden Punkten (RFC 5321, 4.5.2)
+fn dot_stuffed(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + 64);
+    let data = data.strip_suffix(b"\n").unwrap_or(data);
+    for line in data.split(|b| *b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.starts_with(b".") {
+            out.push(b'.');
+        }
+        out.extend_from_slice(line);
+        out.extend_from_slice(b"\r\n");
+    }
+    out.extend_from_slice(b".\r\n");
+    out
+}
+
+// Versand über den SMTP-Server des Kontos. Lehnt der Server einen Empfänger ab, wird
+// die Mail an niemanden verschickt und alle Ablehnungen stehen in der Fehlermeldung.
+pub fn send(
+    account: &Account,
+    from: &str,
+    recipients: &[String],
+    data: &[u8],
+) -> Result<(), SmtpError> {
+    let host = account.setting("host").ok_or_else(|| {
+        SmtpError::permanent(format!("Konto {}: kein host angegeben", account.name))
+    })?;
+    let tls = is_on(account.setting("tls"), false);
+    let starttls = tls && is_on(account.setting("tls_starttls"), true);
+    let implicit_tls = tls && !starttls;
+    let certcheck = is_on(account.setting("tls_certcheck"), true);
+    let port = match account.setting("port") {
+        Some(p) => p
+            .parse::<u16>()
+            .map_err(|_| SmtpError::permanent(format!("Ungültiger port: {}", p)))?,
+        None if implicit_tls => 465,
+        None => 25,
+    };
+
+    let client = gio::SocketClient::new();
+    client.set_timeout(TIMEOUT_SECS);
+    let connection = client
+        .connect_to_host(host, port, None::<&gio::Cancellable>)
+        .map_err(|e| SmtpError::temporary(format!("{}:{}: {}", host, port, e)))?;
+    let mut session = Session::new(connection.upcast());
+    if implicit_tls {
+        session = session.start_tls(host, certcheck)?;
+    }
+
+    let greeting = session.read_reply()?;
+    if greeting.code != 220 {
+        return Err(reply_error("Begrüssung", &greeting));
+    }
+    let mut extensions = session.ehlo()?;
+
+    if starttls {
+        if !extensions
+            .iter()
+            .any(|e| e.eq_ignore_ascii_case("STARTTLS"))
+        {
+            return Err(SmtpError::permanent(format!(
+                "{} bietet kein STARTTLS an",
+                host
+            )));
+        }
+        session.expect("STARTTLS", 220, "STARTTLS")?;
+        session = session.start_tls(host, certcheck)?;
+        extensions = session.ehlo()?;
+    }
+
+    authenticate(&mut session, account, &extensions, tls)?;
+
+    session.expect(&format!("MAIL FROM:<{}>", from), 250, "MAIL FROM")?;
+
+    let mut rejected = Vec::new();
+    for rcpt in recipients {
+        let reply = session.command(&format!("RCPT TO:<{}>", rcpt))?;
+        if reply.code != 250 && reply.code != 251 {
+            rejected.push((rcpt, reply));
+        }
+    }
+    if !rejected.is_empty() {
+        let _ = session.command("RSET");
+        let _ = session.command("QUIT");
+        let message = rejected
+            .iter()
+            .map(|(rcpt, reply)| format!("{} ({})", rcpt, reply.text()))
+            .collect::<Vec<_>>()
+            .join("; ");
+        let error = format!("Empfänger abgelehnt: {}", message);
+        // Nur wenn alle Ablehnungen endgültig sind, lohnt sich kein neuer Versuch
+        return Err(if rejected.iter().all(|(_, r)| r.code >= 500) {
+            SmtpError::permanent(error)
+        } else {
+            SmtpError::temporary(error)
+        });
+    }
+
+    session.expect("DATA", 354, "DATA")?;
+    session.writer.write_all(&dot_stuffed(data))?;
+    session.writer.flush()?;
+    let reply = session.read_reply()?;
+    if reply.code != 250 {
+        return Err(reply_error("Mail nicht angenommen", &reply));
+    }
+
+    let _ = session.command("QUIT");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    #[test]
+    fn dot_stuffing() {
+        assert_eq!(dot_stuffed(b"a\nb\n"), b"a\r\nb\r\n.\r\n");
+        assert_eq!(dot_stuffed(b"a\r\nb"), b"a\r\nb\r\n.\r\n");
+        assert_eq!(dot_stuffed(b".\n..x\nx.\n"), b"..\r\n...x\r\nx.\r\n.\r\n");
+        assert_eq!(dot_stuffed(b"a\n\nb\n"), b"a\r\n\r\nb\r\n.\r\n");
+    }
+
+    // Verbindung zu einem Server, der nur `script` schickt
+    fn session_reading(script: &'static [u8]) -> Session {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(script).unwrap();
+        });
+        let connection = gio::SocketClient::new()
+            .connect_to_host("127.0.0.1", port, None::<&gio::Cancellable>)
+            .unwrap();
+        Session::new(connection.upcast())
+    }
+
+    #[test]
+    fn multiline_reply() {
+        let mut session = session_reading(b"250-mx.example\r\n250-SIZE 1000\r\n250 8BITMIME\r\n");
+        let reply = session.read_reply().ok().unwrap();
+        assert_eq!(reply.code, 250);
+        assert_eq!(reply.lines, ["mx.example", "SIZE 1000", "8BITMIME"]);
+        assert_eq!(reply.text(), "250 mx.example SIZE 1000 8BITMIME");
+    }
+
+    #[test]
+    fn bare_code_reply() {
+        let mut session = session_reading(b"354\r\n");
+        let reply = session.read_reply().ok().unwrap();
+        assert_eq!(reply.code, 354);
+        assert_eq!(reply.lines, [""]);
+    }
+
+    #[test]
+    fn invalid_and_closed_reply() {
+        let mut session = session_reading(b"hello\r\n");
+        assert!(session.read_reply().is_err());
+        let mut session = session_reading(b"250-nur der Anfang\r\n");
+        let error = session.read_reply().err().unwrap();
+        assert!(!error.permanent);
+    }
+
+    #[test]
+    fn reply_codes() {
+        let reply = |code| Reply {
+            code,
+            lines: vec!["x".to_string()],
+        };
+        assert!(reply_error("RCPT", &reply(550)).permanent);
+        assert!(!reply_error("RCPT", &reply(451)).permanent);
+        assert_eq!(reply_error("RCPT", &reply(451)).message, "RCPT: 451 x");
+    }
+
+    // SMTP-Server, der RCPT TO je Empfänger mit der Antwort aus `rcpt_replies` beantwortet
+    // (sonst 250) und alle Befehle samt DATA-Inhalt mitschreibt
+    fn sink(rcpt_replies: &[(&str, &str)]) -> (u16, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let rcpt_replies: HashMap<String, String> = rcpt_replies
+            .iter()
+            .map(|(r, reply)| (r.to_string(), reply.to_string()))
+            .collect();
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut log = Vec::new();
+            writer.write_all(b"220 sink ESMTP\r\n").unwrap();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    break;
+                }
+                let line = line.trim_end().to_string();
+                log.push(line.clone());
+                let reply = if line.starts_with("EHLO") {
+                    "250-sink\r\n250-AUTH PLAIN LOGIN\r\n250 8BITMIME".to_string()
+                } else if let Some(rcpt) = line.strip_prefix("RCPT TO:<") {
+                    let rcpt = rcpt.trim_end_matches('>');
+                    rcpt_replies
+                        .get(rcpt)
+                        .cloned()
+                        .unwrap_or_else(|| "250 ok".to_string())
+                } else if line == "DATA" {
+                    writer.write_all(b"354 go ahead\r\n").unwrap();
+                    let mut data = Vec::new();
+                    while !data.ends_with(b"\r\n.\r\n") {
+                        let mut byte = [0u8];
+                        if reader.read(&mut byte).unwrap() == 0 {
+                            break;
+                        }
+                        data.push(byte[0]);
+                    }
+                    log.push(String::from_utf8(data).unwrap());
+                    "250 queued".to_string()
+                } else if line.starts_with("AUTH") {
+                    "235 authenticated".to_string()
+                } else if line == "QUIT" {
+                    writer.write_all(b"221 bye\r\n").unwrap();
+                    break;
+                } else {
+                    "250 ok".to_string()
+                };
+                writer
+                    .write_all(format!("{}\r\n", reply).as_bytes())
+                    .unwrap();
+            }
+            log
+        });
+        (port, handle)
+    }
+
+    fn account(port: u16) -> Account {
+        let mut settings = HashMap::new();
+        settings.insert("host".to_string(), "127.0.0.1".to_string());
+        settings.insert("port".to_string(), port.to_string());
+        Account {
+            name: "test".to_string(),
+            from: "me@example.org".to_string(),
+            settings,
+        }
+    }
+
+    fn recipients(list: &[&str]) -> Vec<String> {
+        list.iter().map(|r| r.to_string()).collect()
+    }
+
+    #[test]
+    fn accepted() {
+        let (port, server) = sink(&[]);
+        let result = send(
+            &account(port),
+            "me@example.org",
+            &recipients(&["a@example.org", "b@example.org"]),
+            b"Subject: x\n\n.punkt\nText\n",
+        );
+        assert!(result.is_ok());
+        let log = server.join().unwrap();
+        assert!(log.contains(&"MAIL FROM:<me@example.org>".to_string()));
+        assert!(log.contains(&"RCPT TO:<a@example.org>".to_string()));
+        assert!(log.contains(&"RCPT TO:<b@example.org>".to_string()));
+        assert!(log.contains(&"Subject: x\r\n\r\n..punkt\r\nText\r\n.\r\n".to_string()));
+        assert_eq!(log.last().unwrap(), "QUIT");
+    }
+
+    #[test]
+    fn temporary_rejection() {
+        let (port, server) = sink(&[("b@example.org", "450 mailbox busy")]);
+        let error = send(
+            &account(port),
+            "me@example.org",
+            &recipients(&["a@example.org", "b@example.org"]),
+            b"Subject: x\n\nText\n",
+        )
+        .err()
+        .unwrap();
+        assert!(!error.permanent);
+        assert!(error.message.contains("b@example.org (450 mailbox busy)"));
+        assert!(!error.message.contains("a@example.org"));
+        let log = server.join().unwrap();
+        assert!(log.contains(&"RSET".to_string()));
+        assert!(!log.contains(&"DATA".to_string()));
+    }
+
+    #[test]
+    fn permanent_rejection() {
+        let (port, server) = sink(&[
+            ("a@example.org", "550 no such user"),
+            ("b@example.org", "553 not allowed"),
+        ]);
+        let error = send(
+            &account(port),
+            "me@example.org",
+            &recipients(&["a@example.org", "b@example.org"]),
+            b"Subject: x\n\nText\n",
+        )
+        .err()
+        .unwrap();
+        assert!(error.permanent);
+        assert!(!server.join().unwrap().contains(&"DATA".to_string()));
+    }
+
+    #[test]
+    fn mixed_rejection_is_temporary() {
+        let (port, server) = sink(&[
+            ("a@example.org", "550 no such user"),
+            ("b@example.org", "451 try later"),
+        ]);
+        let error = send(
+            &account(port),
+            "me@example.org",
+            &recipients(&["a@example.org", "b@example.org"]),
+            b"Subject: x\n\nText\n",
+        )
+        .err()
+        .unwrap();
+        assert!(!error.permanent);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn no_cleartext_auth() {
+        let (port, server) = sink(&[]);
+        let mut account = account(port);
+        account
+            .settings
+            .insert("auth".to_string(), "on".to_string());
+        account
+            .settings
+            .insert("user".to_string(), "me".to_string());
+        account
+            .settings
+            .insert("password".to_string(), "geheim".to_string());
+        let error = send(
+            &account,
+            "me@example.org",
+            &recipients(&["a@example.org"]),
+            b"Subject: x\n\nText\n",
+        )
+        .err()
+        .unwrap();
+        assert!(error.permanent);
+        assert!(error.message.contains("Klartext"));
+        let log = server.join().unwrap();
+        assert!(!log.iter().any(|l| l.starts_with("AUTH")));
+        assert!(!log.iter().any(|l| l.contains("MAIL FROM")));
+    }
+
+    #[test]
+    fn explicit_cleartext_auth() {
+        let (port, server) = sink(&[]);
+        let mut account = account(port);
+        account
+            .settings
+            .insert("auth".to_string(), "plain".to_string());
+        account
+            .settings
+            .insert("user".to_string(), "me".to_string());
+        account
+            .settings
+            .insert("password".to_string(), "geheim".to_string());
+        let result = send(
+            &account,
+            "me@example.org",
+            &recipients(&["a@example.org"]),
+            b"Subject: x\n\nText\n",
+        );
+        assert!(result.is_ok());
+        let log = server.join().unwrap();
+        assert!(log.contains(&"AUTH PLAIN AG1lAGdlaGVpbQ==".to_string()));
+    }
+}
@@ -1,7 +1,8 @@
 use gtk4::prelude::*;
-use gtk4::{Align, Box, Label, Orientation};
+use gtk4::{Align, Box, Button, Label, Orientation};
 
-pub fn build_status_bar() -> (Box, Label) {
+// Statuszeile mit einem Knopf "Rückgängig", der nur nach dem Senden sichtbar ist
+pub fn build_status_bar() -> (Box, Label, Button) {
     let container = Box::builder()
         .orientation(Orientation::Horizontal)
         .margin_start(10)
@@ -15,7 +16,14 @@ pub fn build_status_bar() -> (Box, Label) {
         .halign(Align::Start)
         .build();
 
+    let undo_button = Button::builder()
+        .label("Rückgängig")
+        .margin_start(10)
+        .visible(false)
+        .build();
+
     container.append(&label);
+    container.append(&undo_button);
 
-    (container, label)
+    (container, label, undo_button)
 }
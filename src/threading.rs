@@ -0,0 +1,525 @@
+use crate::MailEntry;
+use std::collections::{HashMap, HashSet};
+
+// Ein Thread in Baum-Reihenfolge: (Index in der Eingabeliste, Einrückungstiefe)
+pub struct Thread {
+    pub messages: Vec<(usize, usize)>,
+}
+
+// Knoten des JWZ-Algorithmus; leere Container stehen für referenzierte, aber nicht
+// vorhandene Mails
+struct Container {
+    message: Option<usize>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+const REPLY_PREFIXES: [&str; 7] = ["re", "aw", "antw", "fwd", "fw", "wg", "sv"];
+
+// Message-IDs aus References/In-Reply-To, ohne spitze Klammern
+pub fn parse_message_ids(value: &str) -> Vec<String> {
+    let mut ids = Vec::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('<') {
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+        let id = rest[start + 1..start + len].trim();
+        if !id.is_empty() && !id.contains(char::is_whitespace) {
+            ids.push(id.to_string());
+        }
+        rest = &rest[start + len + 1..];
+    }
+    ids
+}
+
+// Betreff ohne "Re:", "AW:", "Fwd:", "WG:" usw. und ohne Listen-Tag "[liste]", kleingeschrieben.
+// Zweiter Wert: ob ein Antwort-Präfix entfernt wurde.
+pub fn normalize_subject(subject: &str) -> (String, bool) {
+    let mut rest = subject.trim();
+    let mut was_reply = false;
+
+    loop {
+        if rest.starts_with('[')
+            && let Some(end) = rest.find(']')
+        {
+            rest = rest[end + 1..].trim_start();
+            continue;
+        }
+
+        let word_len = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let word = rest[..word_len].to_lowercase();
+        if !REPLY_PREFIXES.contains(&word.as_str()) {
+            break;
+        }
+
+        // Zähler wie "Re[2]:" oder "AW(3):" überspringen
+        let mut after = &rest[word_len..];
+        for (open, close) in [('[', ']'), ('(', ')')] {
+            if let Some(inner) = after.strip_prefix(open)
+                && let Some(end) = inner.find(close)
+                && inner[..end].chars().all(|c| c.is_ascii_digit())
+            {
+                after = &inner[end + 1..];
+            }
+        }
+
+        match after.trim_start().strip_prefix(':') {
+            Some(stripped) => {
+                rest = stripped.trim_start();
+                was_reply = true;
+            }
+            None => break,
+        }
+    }
+
+    (rest.to_lowercase(), was_reply)
+}
+
+// Threads nach Jamie Zawinski (https://www.jwz.org/doc/threading.html): zuerst über
+// Message-ID/References/In-Reply-To, danach Zusammenführen über den normalisierten Betreff.
+// Die Reihenfolge der Threads folgt der frühesten Position eines Mitglieds in `entries`.
+pub fn build_threads(entries: &[MailEntry]) -> Vec<Thread> {
+    let mut containers: Vec<Container> = Vec::new();
+    let mut id_table: HashMap<String, usize> = HashMap::new();
+
+    fn new_container(containers: &mut Vec<Container>) -> usize {
+        containers.push(Container {
+            message: None,
+            parent: None,
+            children: Vec::new(),
+        });
+        containers.len() - 1
+    }
+
+    fn container_for(
+        id: &str,
+        containers: &mut Vec<Container>,
+        id_table: &mut HashMap<String, usize>,
+    ) -> usize {
+        if let Some(&c) = id_table.get(id) {
+            return c;
+        }
+        let c = new_container(containers);
+        id_table.insert(id.to_string(), c);
+        c
+    }
+
+    // Liegt `ancestor` auf dem Weg von `node` zur Wurzel?
+    fn is_ancestor(containers: &[Container], ancestor: usize, node: usize) -> bool {
+        let mut current = Some(node);
+        while let Some(c) = current {
+            if c == ancestor {
+                return true;
+            }
+            current = containers[c].parent;
+        }
+        false
+    }
+
+    fn unlink(containers: &mut [Container], child: usize) {
+        if let Some(parent) = containers[child].parent.take() {
+            containers[parent].children.retain(|&c| c != child);
+        }
+    }
+
+    fn link(containers: &mut [Container], parent: usize, child: usize) {
+        unlink(containers, child);
+        containers[child].parent = Some(parent);
+        containers[parent].children.push(child);
+    }
+
+    // 1. Container pro Message-ID, Eltern-Kind-Beziehungen aus den References
+    for (i, entry) in entries.iter().enumerate() {
+        let own = match id_table.get(&entry.message_id) {
+            Some(&c) if !entry.message_id.is_empty() && containers[c].message.is_none() => c,
+            // Doppelte oder fehlende Message-ID: eigener Container ohne Tabelleneintrag
+            Some(_) => new_container(&mut containers),
+            None if entry.message_id.is_empty() => new_container(&mut containers),
+            None => container_for(&entry.message_id, &mut containers, &mut id_table),
+        };
+        containers[own].message = Some(i);
+
+        let mut refs = entry.references.clone();
+        if !entry.in_reply_to.is_empty() && refs.last() != Some(&entry.in_reply_to) {
+            refs.push(entry.in_reply_to.clone());
+        }
+
+        let mut prev: Option<usize> = None;
+        for r in &refs {
+            let c = container_for(r, &mut containers, &mut id_table);
+            if let Some(p) = prev
+                && containers[c].parent.is_none()
+                && !is_ancestor(&containers, c, p)
+            {
+                link(&mut containers, p, c);
+            }
+            prev = Some(c);
+        }
+
+        // Die eigenen References sind massgeblich für den direkten Vorgänger
+        match prev {
+            Some(p) if !is_ancestor(&containers, own, p) => link(&mut containers, p, own),
+            Some(_) => {}
+            None => unlink(&mut containers, own),
+        }
+    }
+
+    // 2. Wurzeln
+    let roots: Vec<usize> = (0..containers.len())
+        .filter(|&c| containers[c].parent.is_none())
+        .collect();
+
+    // 3. Leere Container entfernen bzw. ihre Kinder eine Ebene hochziehen
+    fn prune(containers: &mut [Container], c: usize, is_root: bool) -> Vec<usize> {
+        let children = std::mem::take(&mut containers[c].children);
+        let mut kept = Vec::new();
+        for child in children {
+            kept.extend(prune(containers, child, false));
+        }
+
+        if containers[c].message.is_none() && (kept.is_empty() || !is_root || kept.len() == 1) {
+            for &k in &kept {
+                containers[k].parent = None;
+            }
+            return kept;
+        }
+
+        for &k in &kept {
+            containers[k].parent = Some(c);
+        }
+        containers[c].children = kept;
+        vec![c]
+    }
+
+    let mut root_set = Vec::new();
+    for r in roots {
+        root_set.extend(prune(&mut containers, r, true));
+    }
+
+    // 4. Wurzeln mit gleichem Betreff zusammenführen
+    let subject_of = |containers: &[Container], c: usize| -> Option<(String, bool)> {
+        let msg = containers[c].message.or_else(|| {
+            containers[c]
+                .children
+                .first()
+                .and_then(|&k| containers[k].message)
+        })?;
+        let (subject, was_reply) = normalize_subject(&entries[msg].subject);
+        if subject.is_empty() || subject == "kein betreff" {
+            None
+        } else {
+            Some((subject, was_reply))
+        }
+    };
+    let is_reply = |containers: &[Container], c: usize| -> bool {
+        containers[c]
+            .message
+            .map(|m| normalize_subject(&entries[m].subject).1)
+            .unwrap_or(false)
+    };
+
+    let mut subject_table: HashMap<String, usize> = HashMap::new();
+    for &r in &root_set {
+        let Some((subject, _)) = subject_of(&containers, r) else {
+            continue;
+        };
+        match subject_table.get(&subject) {
+            Some(&old) => {
+                let this_empty = containers[r].message.is_none();
+                let old_empty = containers[old].message.is_none();
+                if (this_empty && !old_empty)
+                    || (!this_empty && is_reply(&containers, old) && !is_reply(&containers, r))
+                {
+                    subject_table.insert(subject, r);
+                }
+            }
+            None => {
+                subject_table.insert(subject, r);
+            }
+        }
+    }
+
+    let mut merged: HashSet<usize> = HashSet::new();
+    let mut extra_roots = Vec::new();
+    for &r in &root_set {
+        let Some((subject, _)) = subject_of(&containers, r) else {
+            continue;
+        };
+        let Some(&target) = subject_table.get(&subject) else {
+            continue;
+        };
+        if target == r || merged.contains(&r) {
+            continue;
+        }
+
+        let r_empty = containers[r].message.is_none();
+        let t_empty = containers[target].message.is_none();
+
+        if r_empty && t_empty {
+            for child in std::mem::take(&mut containers[r].children) {
+                link(&mut containers, target, child);
+            }
+            merged.insert(r);
+        } else if t_empty {
+            link(&mut containers, target, r);
+            merged.insert(r);
+        } else if r_empty {
+            // Die Mail wird Kind des leeren Containers, nicht umgekehrt
+            link(&mut containers, r, target);
+            merged.insert(target);
+            subject_table.insert(subject, r);
+        } else if is_reply(&containers, r) && !is_reply(&containers, target) {
+            link(&mut containers, target, r);
+            merged.insert(r);
+        } else {
+            // Zwei eigenständige Mails mit gleichem Betreff: gemeinsamer leerer Elternknoten
+            let parent = new_container(&mut containers);
+            link(&mut containers, parent, target);
+            link(&mut containers, parent, r);
+            merged.insert(target);
+            merged.insert(r);
+            subject_table.insert(subject, parent);
+            extra_roots.push(parent);
+        }
+    }
+
+    let final_roots: Vec<usize> = root_set
+        .into_iter()
+        .chain(extra_roots)
+        .filter(|r| !merged.contains(r) && containers[*r].parent.is_none())
+        .collect();
+
+    // 5. Baum abflachen, Geschwister chronologisch
+    fn earliest(containers: &[Container], entries: &[MailEntry], c: usize) -> i64 {
+        let own = containers[c].message.map(|m| entries[m].timestamp);
+        let children = containers[c]
+            .children
+            .iter()
+            .map(|&k| earliest(containers, entries, k));
+        own.into_iter().chain(children).min().unwrap_or(0)
+    }
+
+    fn flatten(
+        containers: &[Container],
+        entries: &[MailEntry],
+        c: usize,
+        depth: usize,
+        out: &mut Vec<(usize, usize)>,
+    ) {
+        let child_depth = match containers[c].message {
+            Some(m) => {
+                out.push((m, depth));
+                depth + 1
+            }
+            None => depth,
+        };
+        let mut children = containers[c].children.clone();
+        children.sort_by_key(|&k| earliest(containers, entries, k));
+        for k in children {
+            flatten(containers, entries, k, child_depth, out);
+        }
+    }
+
+    let mut threads: Vec<Thread> = final_roots
+        .into_iter()
+        .map(|r| {
+            let mut messages = Vec::new();
+            flatten(&containers, entries, r, 0, &mut messages);
+            Thread { messages }
+        })
+        .filter(|t| !t.messages.is_empty())
+        .collect();
+
+    threads.sort_by_key(|t| t.messages.iter().map(|(i, _)| *i).min().unwrap_or(0));
+    threads
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn mail(id: &str, subject: &str, in_reply_to: &str, references: &[&str]) -> MailEntry {
+        MailEntry {
+            folder: String::new(),
+            path: PathBuf::from(id),
+            timestamp: 0,
+            date_short: String::new(),
+            date_full: String::new(),
+            from: String::new(),
+            return_path: String::new(),
+            subject: subject.to_string(),
+            is_read: true,
+            list_unsubscribe: None,
+            to: String::new(),
+            cc: String::new(),
+            list_id: None,
+            has_attachment: false,
+            message_id: id.to_string(),
+            in_reply_to: in_reply_to.to_string(),
+            references: references.iter().map(|r| r.to_string()).collect(),
+        }
+    }
+
+    // Threads als (Index, Tiefe); Zeitstempel = Position in der Liste
+    fn threads(mut entries: Vec<MailEntry>) -> Vec<Vec<(usize, usize)>> {
+        for (i, entry) in entries.iter_mut().enumerate() {
+            entry.timestamp = i as i64;
+        }
+        build_threads(&entries)
+            .into_iter()
+            .map(|t| t.messages)
+            .collect()
+    }
+
+    #[test]
+    fn message_ids() {
+        assert_eq!(
+            parse_message_ids("<a@x> <b@y>\r\n <c@z>"),
+            ["a@x", "b@y", "c@z"]
+        );
+        assert_eq!(parse_message_ids("<a@x> <> <mit leer> <offen"), ["a@x"]);
+        assert!(parse_message_ids("").is_empty());
+    }
+
+    #[test]
+    fn subjects() {
+        assert_eq!(normalize_subject("Thema"), ("thema".to_string(), false));
+        assert_eq!(normalize_subject("Re: Thema"), ("thema".to_string(), true));
+        assert_eq!(
+            normalize_subject("AW: WG: Fwd: Thema"),
+            ("thema".to_string(), true)
+        );
+        assert_eq!(
+            normalize_subject("[liste] Re[2]: AW(3): Thema"),
+            ("thema".to_string(), true)
+        );
+        assert_eq!(
+            normalize_subject("Reise: Bern"),
+            ("reise: bern".to_string(), false)
+        );
+        assert_eq!(
+            normalize_subject("Re Thema"),
+            ("re thema".to_string(), false)
+        );
+    }
+
+    #[test]
+    fn references() {
+        let result = threads(vec![
+            mail("a", "Thema", "", &[]),
+            mail("c", "Re: Thema", "b", &["a", "b"]),
+            mail("b", "Re: Thema", "a", &[]),
+            mail("d", "Anderes", "", &[]),
+        ]);
+        assert_eq!(result, [vec![(0, 0), (2, 1), (1, 2)], vec![(3, 0)]]);
+    }
+
+    #[test]
+    fn missing_parents() {
+        // Fehlende Zwischenstufe: leerer Container mitten im Baum fällt weg
+        let result = threads(vec![
+            mail("a", "Thema", "", &[]),
+            mail("c", "x", "", &["a", "fehlt"]),
+        ]);
+        assert_eq!(result, [vec![(0, 0), (1, 1)]]);
+
+        // Eine einzige Antwort auf eine fehlende Mail wird selbst zur Wurzel
+        let result = threads(vec![mail("b", "x", "fehlt", &[])]);
+        assert_eq!(result, [vec![(0, 0)]]);
+
+        // Mehrere Antworten auf dieselbe fehlende Mail bleiben zusammen
+        let result = threads(vec![
+            mail("b", "x", "fehlt", &[]),
+            mail("c", "y", "fehlt", &[]),
+        ]);
+        assert_eq!(result, [vec![(0, 0), (1, 0)]]);
+    }
+
+    #[test]
+    fn duplicate_message_ids() {
+        let result = threads(vec![
+            mail("a", "Eins", "", &[]),
+            mail("a", "Zwei", "", &[]),
+            mail("b", "Re: Eins", "a", &[]),
+            mail("", "Ohne", "", &[]),
+            mail("", "Leer", "", &[]),
+        ]);
+        assert_eq!(
+            result,
+            [
+                vec![(0, 0), (2, 1)],
+                vec![(1, 0)],
+                vec![(3, 0)],
+                vec![(4, 0)]
+            ]
+        );
+    }
+
+    #[test]
+    fn reference_cycles() {
+        let result = threads(vec![
+            mail("a", "x", "b", &[]),
+            mail("b", "y", "a", &[]),
+            mail("c", "z", "c", &["c"]),
+            mail("d", "w", "f", &["e", "f", "e"]),
+        ]);
+        let mut seen: Vec<usize> = result.iter().flatten().map(|(i, _)| *i).collect();
+        seen.sort();
+        assert_eq!(seen, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn subject_merging() {
+        let result = threads(vec![
+            mail("a", "Thema", "", &[]),
+            mail("b", "Re: Thema", "", &[]),
+            mail("c", "AW: [liste] thema", "", &[]),
+        ]);
+        assert_eq!(result, [vec![(0, 0), (1, 1), (2, 1)]]);
+
+        // Die Antwort kam zuerst an
+        let result = threads(vec![
+            mail("b", "AW: Thema", "", &[]),
+            mail("a", "Thema", "", &[]),
+        ]);
+        assert_eq!(result, [vec![(1, 0), (0, 1)]]);
+
+        // Zwei Mails ohne Präfix: gemeinsamer leerer Elternknoten
+        let result = threads(vec![
+            mail("a", "Thema", "", &[]),
+            mail("b", "Thema", "", &[]),
+        ]);
+        assert_eq!(result, [vec![(0, 0), (1, 0)]]);
+
+        // Ohne Betreff wird nichts zusammengeführt
+        let result = threads(vec![mail("a", "", "", &[]), mail("b", "Re: ", "", &[])]);
+        assert_eq!(result.len(), 2);
+    }
+
+    #[test]
+    fn merge_into_empty_root() {
+        // "c" und "d" antworten auf eine fehlende Mail; "a" mit gleichem Betreff wird Kind
+        // des leeren Containers, unabhängig von der Reihenfolge
+        let expected_members = |result: &Vec<Vec<(usize, usize)>>| {
+            assert_eq!(result.len(), 1);
+            assert!(result[0].iter().all(|(_, depth)| *depth == 0));
+            assert_eq!(result[0].len(), 3);
+        };
+        let result = threads(vec![
+            mail("a", "Thema", "", &[]),
+            mail("c", "Re: Thema", "fehlt", &[]),
+            mail("d", "Re: Thema", "fehlt", &[]),
+        ]);
+        expected_members(&result);
+        let result = threads(vec![
+            mail("c", "Re: Thema", "fehlt", &[]),
+            mail("d", "Re: Thema", "fehlt", &[]),
+            mail("a", "Thema", "", &[]),
+        ]);
+        expected_members(&result);
+    }
+}